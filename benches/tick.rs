@@ -0,0 +1,220 @@
+//! Measures one [`Game`] tick under load, along with the collision, integration and particle
+//! phases on their own. Enemies are spread over an area that grows with their count, so the grid
+//! collision checks should grow linearly while the brute force ones grow with enemies × projectiles.
+//!
+//! Run with `cargo bench --bench tick`.
+
+use std::f64::consts::TAU;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use electro_shoot::{
+    behavior::EnemyContext,
+    enemy::{ENEMY_KINDS, Enemy},
+    game::Game,
+    projectile::{PROJECTILE_KINDS, Projectile, ProjectileContext},
+    shape::Shape,
+};
+use nalgebra::{Isometry2, point, vector};
+
+const SEED: u64 = 0xbe_4c_41;
+const DT: f64 = 1.0 / 120.0;
+/// Enemies and projectiles
+const LOADS: [(usize, usize); 3] = [(100, 100), (1000, 500), (5000, 1000)];
+/// Square world units per enemy
+const AREA_PER_ENEMY: f64 = 4.0;
+/// Keeps enemies from spawning on top of the turret and ending the game
+const CLEAR_RADIUS: f64 = 5.0;
+/// The portion of enemies blown up to fill the particle phase
+const EXPLODED_FRACTION: usize = 10;
+
+/// A game with `enemies` enemies and `projectiles` projectiles scattered around the turret, and
+/// camera bounds that cover all of them
+fn loaded_game(enemies: usize, projectiles: usize) -> (Game, Shape) {
+    let mut game = Game {
+        invincible: true,
+        ..Game::with_seed(SEED)
+    };
+    let half_size = (enemies as f64 * AREA_PER_ENEMY).sqrt() * 0.5 + CLEAR_RADIUS;
+
+    for index in 0..enemies {
+        let position = loop {
+            let position = vector![
+                game.rng.gen_range(-half_size, half_size),
+                game.rng.gen_range(-half_size, half_size)
+            ];
+
+            if position.norm() > CLEAR_RADIUS {
+                break position;
+            }
+        };
+        let angle = game.rng.gen_range(0.0, TAU);
+
+        game.enemies.insert(Enemy::new(
+            Isometry2::new(position, angle),
+            &ENEMY_KINDS[index % ENEMY_KINDS.len()],
+        ));
+    }
+
+    for index in 0..projectiles {
+        let position = vector![
+            game.rng.gen_range(-half_size, half_size),
+            game.rng.gen_range(-half_size, half_size)
+        ];
+        let angle = game.rng.gen_range(0.0, TAU);
+        let kind = &PROJECTILE_KINDS[index % PROJECTILE_KINDS.len()];
+
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(position, angle),
+            kind,
+            &mut game.rng,
+        ));
+    }
+
+    let exploded = game.enemies.values().step_by(EXPLODED_FRACTION);
+    for (index, enemy) in exploded.enumerate() {
+        let hit_position = enemy.position.translation.vector.into();
+
+        enemy.explode_with_seed(
+            hit_position,
+            vector![15.0, 0.0],
+            &game.explosion,
+            &mut game.particles,
+            SEED + index as u64,
+        );
+    }
+
+    let camera_bounds = Shape::Rectangle {
+        half_size: vector![half_size, half_size].add_scalar(1.0),
+    };
+
+    (game, camera_bounds)
+}
+
+fn load_name(enemies: usize, projectiles: usize) -> String {
+    format!("{enemies}x{projectiles}")
+}
+
+fn full_tick(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("tick");
+
+    for (enemies, projectiles) in LOADS {
+        group.bench_function(load_name(enemies, projectiles), |bencher| {
+            bencher.iter_batched(
+                || loaded_game(enemies, projectiles),
+                |(mut game, camera_bounds)| {
+                    game.tick_with_input(point![1.0, 0.0], camera_bounds, DT);
+                    game
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Moves every projectile and checks it against the enemies, with and without the spatial grid
+fn collision(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("collision");
+
+    for (enemies, projectiles) in LOADS {
+        for use_grid in [true, false] {
+            let name = if use_grid { "grid" } else { "brute_force" };
+
+            group.bench_function(
+                BenchmarkId::new(name, load_name(enemies, projectiles)),
+                |bencher| {
+                    bencher.iter_batched(
+                        || loaded_game(enemies, projectiles).0,
+                        |mut game| {
+                            if use_grid {
+                                game.grid.rebuild(&game.enemies);
+                            }
+
+                            let context = ProjectileContext {
+                                explosion: &game.explosion,
+                                enemy_order: None,
+                                tick: game.tick_count,
+                                arena: None,
+                                grid: use_grid.then_some(&game.grid),
+                            };
+
+                            for projectile in game.projectiles.values_mut() {
+                                projectile.tick(
+                                    &mut game.enemies,
+                                    &mut game.particles,
+                                    &mut game.trail_zones,
+                                    &context,
+                                    &mut game.cosmetic_rng,
+                                    DT,
+                                );
+                            }
+
+                            game
+                        },
+                        BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+/// Moves every enemy by its behavior
+fn integration(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("integration");
+
+    for (enemies, projectiles) in LOADS {
+        group.bench_function(load_name(enemies, projectiles), |bencher| {
+            bencher.iter_batched(
+                || loaded_game(enemies, projectiles).0,
+                |mut game| {
+                    let context = EnemyContext {
+                        turret_position: point![0.0, 0.0],
+                        enemy_positions: (game.enemies.iter())
+                            .map(|(key, enemy)| (key, enemy.position.translation.vector.into()))
+                            .collect(),
+                    };
+
+                    for enemy in game.enemies.values_mut() {
+                        enemy.tick(&context, &mut game.cosmetic_rng, DT);
+                    }
+
+                    game
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+/// Ticks the fragments of every tenth enemy, blown up
+fn particles(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("particles");
+
+    for (enemies, projectiles) in LOADS {
+        group.bench_function(load_name(enemies, projectiles), |bencher| {
+            bencher.iter_batched(
+                || loaded_game(enemies, projectiles).0,
+                |mut game| {
+                    game.particles.retain(|_, particle| {
+                        particle.tick(DT);
+                        !particle.should_delete()
+                    });
+
+                    game
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, full_tick, collision, integration, particles);
+criterion_main!(benches);
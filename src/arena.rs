@@ -0,0 +1,61 @@
+use nalgebra::{Isometry2, Point2, Vector2};
+
+use crate::object::Object;
+
+/// A rectangular arena centered on the origin, where anything leaving one edge comes back in
+/// through the opposite edge
+#[derive(Clone, Copy, Debug)]
+pub struct WrapArena {
+    pub size: Vector2<f64>,
+    /// Whether projectiles wrap too, rather than being removed when they leave the screen
+    pub wrap_projectiles: bool,
+    /// How many times a projectile can wrap before it is removed
+    pub maximum_projectile_wraps: u32,
+}
+
+impl WrapArena {
+    /// Moves `position` back inside the arena. Returns whether it had to be moved.
+    pub(crate) fn wrap(&self, position: &mut Isometry2<f64>) -> bool {
+        let half_size = self.size / 2.0;
+        let translation = &mut position.translation.vector;
+
+        let wrapped = Vector2::from_fn(|i, _| {
+            (translation[i] + half_size[i]).rem_euclid(self.size[i]) - half_size[i]
+        });
+
+        let moved = wrapped != *translation;
+        *translation = wrapped;
+
+        moved
+    }
+
+    /// The copy of `position` closest to `from`, out of all the copies repeated across the seams
+    #[must_use]
+    pub(crate) fn nearest_image(
+        &self,
+        from: Point2<f64>,
+        position: Isometry2<f64>,
+    ) -> Isometry2<f64> {
+        let offset = position.translation.vector - from.coords;
+
+        let nearest_offset =
+            Vector2::from_fn(|i, _| offset[i] - (offset[i] / self.size[i]).round() * self.size[i]);
+
+        Isometry2::from_parts((from.coords + nearest_offset).into(), position.rotation)
+    }
+
+    /// Checks for collisions between `a` and the closest copy of `b`, so objects straddling a seam
+    /// still collide
+    #[must_use]
+    pub(crate) fn is_colliding(&self, a: &Object, b: &Object) -> bool {
+        a.is_colliding(&self.nearest_object(a, b))
+    }
+
+    /// `b` moved to its copy closest to `a`
+    #[must_use]
+    pub(crate) fn nearest_object(&self, a: &Object, b: &Object) -> Object {
+        let mut b = *b;
+        b.position = self.nearest_image(a.position.translation.vector.into(), b.position);
+        b
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::Debug;
+
+use nalgebra::{Complex, Point2, UnitComplex, vector};
+
+use crate::{enemy::Enemy, game::EnemyKey};
+
+/// Custom movement for an enemy. Called once per tick before the enemy moves, and expected to set
+/// its `direction` and `linear_velocity`.
+pub trait EnemyBehavior: Debug {
+    fn update(&mut self, enemy: &mut Enemy, context: &EnemyContext, dt: f64);
+
+    fn clone_box(&self) -> Box<dyn EnemyBehavior>;
+}
+
+impl Clone for Box<dyn EnemyBehavior> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// What an enemy can see of the rest of the game while updating
+#[derive(Clone, Debug, Default)]
+pub struct EnemyContext {
+    pub turret_position: Point2<f64>,
+    pub enemy_positions: Vec<(EnemyKey, Point2<f64>)>,
+}
+
+impl EnemyContext {
+    #[must_use]
+    pub fn nearest_enemy(
+        &self,
+        position: Point2<f64>,
+        exclude: Option<EnemyKey>,
+    ) -> Option<(EnemyKey, Point2<f64>)> {
+        self.enemy_positions
+            .iter()
+            .filter(|&&(key, _)| Some(key) != exclude)
+            .min_by(|(_, a), (_, b)| {
+                (a - position)
+                    .magnitude_squared()
+                    .total_cmp(&(b - position).magnitude_squared())
+            })
+            .copied()
+    }
+}
+
+/// Moves in a straight line in the enemy's current direction
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Straight;
+
+impl EnemyBehavior for Straight {
+    fn update(&mut self, enemy: &mut Enemy, _context: &EnemyContext, dt: f64) {
+        enemy.march(dt);
+    }
+
+    fn clone_box(&self) -> Box<dyn EnemyBehavior> {
+        Box::new(*self)
+    }
+}
+
+/// Turns towards the turret at a limited rate
+#[derive(Clone, Copy, Debug)]
+pub struct Homing {
+    /// Radians per second
+    pub turn_rate: f64,
+}
+
+impl EnemyBehavior for Homing {
+    fn update(&mut self, enemy: &mut Enemy, context: &EnemyContext, dt: f64) {
+        if let Some(target) = direction_towards(enemy.position.translation.vector.into(), context) {
+            let angle = enemy.direction.angle_to(&target);
+            let max_turn = self.turn_rate * dt;
+
+            enemy.direction *= UnitComplex::new(angle.clamp(-max_turn, max_turn));
+        }
+
+        enemy.march(dt);
+    }
+
+    fn clone_box(&self) -> Box<dyn EnemyBehavior> {
+        Box::new(*self)
+    }
+}
+
+/// Approaches the turret until `radius` away, then circles it
+#[derive(Clone, Copy, Debug)]
+pub struct Circling {
+    pub radius: f64,
+    /// Whether to circle clockwise or counterclockwise
+    pub clockwise: bool,
+}
+
+impl EnemyBehavior for Circling {
+    fn update(&mut self, enemy: &mut Enemy, context: &EnemyContext, dt: f64) {
+        let position = enemy.position.translation.vector.into();
+
+        if let Some(inwards) = direction_towards(position, context) {
+            let distance = (context.turret_position - position).magnitude();
+
+            let tangent = if self.clockwise {
+                vector![inwards.sin_angle(), -inwards.cos_angle()]
+            } else {
+                vector![-inwards.sin_angle(), inwards.cos_angle()]
+            };
+            let radial = inwards
+                * vector![
+                    ((distance - self.radius) / self.radius).clamp(-1.0, 1.0),
+                    0.0
+                ];
+
+            let direction = tangent + radial;
+
+            if direction.magnitude_squared() > 0.0 {
+                enemy.direction =
+                    UnitComplex::new_normalize(Complex::new(direction.x, direction.y));
+            }
+        }
+
+        enemy.march(dt);
+    }
+
+    fn clone_box(&self) -> Box<dyn EnemyBehavior> {
+        Box::new(*self)
+    }
+}
+
+fn direction_towards(position: Point2<f64>, context: &EnemyContext) -> Option<UnitComplex<f64>> {
+    let offset = context.turret_position - position;
+
+    (offset.magnitude_squared() > 0.0)
+        .then(|| UnitComplex::new_normalize(Complex::new(offset.x, offset.y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use nalgebra::Isometry2;
+
+    use super::*;
+    use crate::{enemy::ENEMY_KINDS, rng::Rng};
+
+    #[derive(Clone, Copy, Debug)]
+    struct Stop;
+
+    impl EnemyBehavior for Stop {
+        fn update(&mut self, enemy: &mut Enemy, _context: &EnemyContext, _dt: f64) {
+            enemy.current_speed = 0.0;
+            enemy.object.linear_velocity = vector![0.0, 0.0];
+        }
+
+        fn clone_box(&self) -> Box<dyn EnemyBehavior> {
+            Box::new(*self)
+        }
+    }
+
+    /// How far an enemy moves in a second, with `behavior` in place of its kind's own
+    fn distance_moved(behavior: Option<Box<dyn EnemyBehavior>>) -> f64 {
+        let start = Isometry2::new(vector![10.0, 0.0], PI);
+        let mut enemy = Enemy {
+            behavior,
+            ..Enemy::new(start, &ENEMY_KINDS[0])
+        };
+        let context = EnemyContext::default();
+        let mut rng = Rng::new(11);
+
+        for _ in 0..120 {
+            enemy.tick(&context, &mut rng, 1.0 / 120.0);
+        }
+
+        (enemy.position.translation.vector - start.translation.vector).magnitude()
+    }
+
+    #[test]
+    fn custom_behaviors_replace_the_default_march() {
+        assert!(distance_moved(None) > 0.1);
+        assert_eq!(distance_moved(Some(Box::new(Stop))), 0.0);
+    }
+}
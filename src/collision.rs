@@ -0,0 +1,413 @@
+//! Collision tests between shapes at arbitrary positions, for anything that collides without
+//! being an [`Object`](crate::object::Object)
+
+use nalgebra::{Isometry2, Point2, Unit, UnitComplex, Vector2, vector};
+
+use crate::shape::{self, Shape};
+
+/// How two overlapping shapes overlap
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Manifold {
+    /// The direction, in world space, to push the second shape to separate it from the first
+    pub normal: Unit<Vector2<f64>>,
+    /// How far the second shape would have to be pushed along `normal` to stop overlapping
+    pub depth: f64,
+}
+
+#[must_use]
+pub fn overlaps(
+    a_shape: &Shape,
+    a_position: &Isometry2<f64>,
+    b_shape: &Shape,
+    b_position: &Isometry2<f64>,
+) -> bool {
+    a_shape.is_colliding(b_shape, a_position.inverse() * b_position)
+}
+
+/// Like [`overlaps`], but also finds the shortest way to separate the shapes
+#[must_use]
+pub fn overlap_manifold(
+    a_shape: &Shape,
+    a_position: &Isometry2<f64>,
+    b_shape: &Shape,
+    b_position: &Isometry2<f64>,
+) -> Option<Manifold> {
+    if !overlaps(a_shape, a_position, b_shape, b_position) {
+        return None;
+    }
+
+    let manifold = match (*a_shape, *b_shape) {
+        (Shape::Rectangle { half_size }, Shape::Point | Shape::Circle { .. }) => {
+            rectangle_round(half_size, a_position, b_shape.bounding_radius(), b_position)
+        }
+        (Shape::Point | Shape::Circle { .. }, Shape::Rectangle { half_size }) => {
+            rectangle_round(half_size, b_position, a_shape.bounding_radius(), a_position)
+                .map(flipped)
+        }
+        (
+            Shape::Rectangle {
+                half_size: half_size_a,
+            },
+            Shape::Rectangle {
+                half_size: half_size_b,
+            },
+        ) => rectangle_rectangle(half_size_a, a_position, half_size_b, b_position),
+        (Shape::Polygon { .. }, _) | (_, Shape::Polygon { .. }) => {
+            convex_convex(a_shape, a_position, b_shape, b_position)
+        }
+        _ => round_round(
+            a_shape.bounding_radius(),
+            a_position,
+            b_shape.bounding_radius(),
+            b_position,
+        ),
+    };
+
+    manifold.map(|manifold| Manifold {
+        depth: manifold.depth.max(0.0),
+        ..manifold
+    })
+}
+
+fn flipped(manifold: Manifold) -> Manifold {
+    Manifold {
+        normal: -manifold.normal,
+        ..manifold
+    }
+}
+
+/// Separates two circles, or a circle and a point
+fn round_round(
+    radius_a: f64,
+    position_a: &Isometry2<f64>,
+    radius_b: f64,
+    position_b: &Isometry2<f64>,
+) -> Option<Manifold> {
+    let offset = position_b.translation.vector - position_a.translation.vector;
+    let distance = offset.norm();
+
+    Some(Manifold {
+        normal: Unit::try_new(offset, f64::EPSILON).unwrap_or(Vector2::x_axis()),
+        depth: radius_a + radius_b - distance,
+    })
+}
+
+/// Separates a circle or point from a rectangle, with the normal pointing away from the rectangle
+fn rectangle_round(
+    half_size: Vector2<f64>,
+    rectangle_position: &Isometry2<f64>,
+    radius: f64,
+    round_position: &Isometry2<f64>,
+) -> Option<Manifold> {
+    let center: Point2<f64> = round_position.translation.vector.into();
+    let local = rectangle_position.inverse_transform_point(&center).coords;
+
+    let closest = vector![
+        local.x.clamp(-half_size.x, half_size.x),
+        local.y.clamp(-half_size.y, half_size.y),
+    ];
+
+    let (local_normal, depth) = if closest != local {
+        let offset = local - closest;
+        (offset.normalize(), radius - offset.norm())
+    } else {
+        // The center is inside of the rectangle, so push it out through the nearest side
+        let inset = half_size - local.abs();
+        if inset.x < inset.y {
+            (vector![local.x.signum(), 0.0], inset.x + radius)
+        } else {
+            (vector![0.0, local.y.signum()], inset.y + radius)
+        }
+    };
+
+    Some(Manifold {
+        normal: Unit::new_normalize(rectangle_position.rotation * local_normal),
+        depth,
+    })
+}
+
+/// Separates two rectangles along whichever of their axes they overlap the least on
+fn rectangle_rectangle(
+    half_size_a: Vector2<f64>,
+    position_a: &Isometry2<f64>,
+    half_size_b: Vector2<f64>,
+    position_b: &Isometry2<f64>,
+) -> Option<Manifold> {
+    let offset = position_b.translation.vector - position_a.translation.vector;
+
+    let axes = [position_a.rotation, position_b.rotation]
+        .into_iter()
+        .flat_map(|rotation: UnitComplex<f64>| {
+            [rotation * Vector2::x_axis(), rotation * Vector2::y_axis()]
+        });
+
+    axes.map(|axis| {
+        let depth = projected_radius(half_size_a, position_a.rotation, &axis)
+            + projected_radius(half_size_b, position_b.rotation, &axis)
+            - offset.dot(&axis).abs();
+
+        let normal = if offset.dot(&axis) < 0.0 { -axis } else { axis };
+
+        Manifold { normal, depth }
+    })
+    .min_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+/// Separates two shapes where at least one is a polygon, along whichever axis they overlap the
+/// least on. Points and circles add the axis from the other shape's nearest corner to their center.
+fn convex_convex(
+    a_shape: &Shape,
+    a_position: &Isometry2<f64>,
+    b_shape: &Shape,
+    b_position: &Isometry2<f64>,
+) -> Option<Manifold> {
+    let world_corners = |shape: &Shape, position: &Isometry2<f64>| {
+        (shape.corners()).map(|corners| {
+            corners
+                .iter()
+                .map(|corner| position * corner)
+                .collect::<Vec<_>>()
+        })
+    };
+    let a = (
+        world_corners(a_shape, a_position),
+        a_position,
+        a_shape.bounding_radius(),
+    );
+    let b = (
+        world_corners(b_shape, b_position),
+        b_position,
+        b_shape.bounding_radius(),
+    );
+
+    let mut axes = Vec::new();
+
+    for ((corners, _, _), (other_corners, other_position, _)) in [(&a, &b), (&b, &a)] {
+        let Some(corners) = corners else {
+            continue;
+        };
+
+        axes.extend(
+            (corners.iter().zip(corners.iter().cycle().skip(1))).filter_map(|(start, end)| {
+                Unit::try_new(vector![start.y - end.y, end.x - start.x], f64::EPSILON)
+            }),
+        );
+
+        if other_corners.is_none() {
+            let center: Point2<f64> = other_position.translation.vector.into();
+            let nearest = (corners.iter())
+                .min_by(|a, b| {
+                    nalgebra::distance_squared(a, &center)
+                        .total_cmp(&nalgebra::distance_squared(b, &center))
+                })
+                .unwrap();
+
+            axes.extend(Unit::try_new(center - nearest, f64::EPSILON));
+        }
+    }
+
+    axes.into_iter()
+        .map(|axis| {
+            let (min_a, max_a) = shadow(&a, &axis);
+            let (min_b, max_b) = shadow(&b, &axis);
+
+            // Whichever way along the axis clears the shapes soonest, even if one contains the
+            // other's shadow
+            let forward = max_a - min_b;
+            let backward = max_b - min_a;
+
+            if forward <= backward {
+                Manifold {
+                    normal: axis,
+                    depth: forward,
+                }
+            } else {
+                Manifold {
+                    normal: -axis,
+                    depth: backward,
+                }
+            }
+        })
+        .min_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+/// The range of distances along `axis` covered by a shape's world space corners, or by a circle
+/// of its radius if it has none
+fn shadow(
+    (corners, position, radius): &(Option<Vec<Point2<f64>>>, &Isometry2<f64>, f64),
+    axis: &Unit<Vector2<f64>>,
+) -> (f64, f64) {
+    match corners {
+        Some(corners) => shape::project(corners, axis),
+        None => {
+            let center = position.translation.vector.dot(axis);
+            (center - radius, center + radius)
+        }
+    }
+}
+
+/// Half of the length of a rectangle's shadow on `axis`
+fn projected_radius(
+    half_size: Vector2<f64>,
+    rotation: UnitComplex<f64>,
+    axis: &Unit<Vector2<f64>>,
+) -> f64 {
+    (rotation * vector![half_size.x, 0.0]).dot(axis).abs()
+        + (rotation * vector![0.0, half_size.y]).dot(axis).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use nalgebra::point;
+
+    use super::*;
+
+    static TRIANGLE: [Point2<f64>; 3] = [point![1.0, 0.0], point![0.0, 1.0], point![-1.0, -1.0]];
+
+    fn at(x: f64, y: f64, angle: f64) -> Isometry2<f64> {
+        Isometry2::new(vector![x, y], angle)
+    }
+
+    /// Two shapes and where they are, and whether they should overlap
+    type Case = (Shape, Isometry2<f64>, Shape, Isometry2<f64>, bool);
+
+    /// Pairs of shapes placed away from the origin
+    fn cases() -> Vec<Case> {
+        let point = Shape::Point;
+        let circle = Shape::Circle { radius: 1.0 };
+        let small_circle = Shape::Circle { radius: 0.5 };
+        let square = Shape::Rectangle {
+            half_size: vector![1.0, 1.0],
+        };
+        let bar = Shape::Rectangle {
+            half_size: vector![2.0, 1.0],
+        };
+        let triangle = Shape::Polygon {
+            vertices: &TRIANGLE,
+            scale: 2.0,
+        };
+
+        vec![
+            (point, at(5.0, 5.0, 0.0), point, at(5.0, 5.0, 0.0), false),
+            (circle, at(5.0, 5.0, 0.0), point, at(5.9, 5.0, 0.0), true),
+            (circle, at(5.0, 5.0, 0.0), point, at(6.1, 5.0, 0.0), false),
+            (
+                circle,
+                at(-3.0, 2.0, 0.0),
+                small_circle,
+                at(-1.6, 2.0, 0.0),
+                true,
+            ),
+            (
+                circle,
+                at(-3.0, 2.0, 0.0),
+                small_circle,
+                at(-1.4, 2.0, 0.0),
+                false,
+            ),
+            (
+                bar,
+                at(10.0, 0.0, FRAC_PI_2),
+                point,
+                at(10.0, 1.9, 0.0),
+                true,
+            ),
+            (
+                bar,
+                at(10.0, 0.0, FRAC_PI_2),
+                point,
+                at(11.5, 0.0, 0.0),
+                false,
+            ),
+            (
+                square,
+                at(0.0, -4.0, 0.0),
+                small_circle,
+                at(1.4, -4.0, 0.0),
+                true,
+            ),
+            (
+                square,
+                at(0.0, -4.0, 0.0),
+                small_circle,
+                at(1.4, -2.6, 0.0),
+                false,
+            ),
+            (
+                square,
+                at(3.0, 3.0, 0.0),
+                square,
+                at(5.3, 3.0, FRAC_PI_4),
+                true,
+            ),
+            (
+                square,
+                at(3.0, 3.0, 0.0),
+                square,
+                at(5.5, 3.0, FRAC_PI_4),
+                false,
+            ),
+            (
+                triangle,
+                at(-6.0, -6.0, 0.0),
+                small_circle,
+                at(-4.5, -6.0, 0.0),
+                true,
+            ),
+            (
+                triangle,
+                at(-6.0, -6.0, 0.0),
+                small_circle,
+                at(-3.0, -3.0, 0.0),
+                false,
+            ),
+        ]
+    }
+
+    #[test]
+    fn overlaps_matches_shape_collisions_anywhere() {
+        for (a_shape, a_position, b_shape, b_position, expected) in cases() {
+            let message = format!("{a_shape:?} at {a_position} and {b_shape:?} at {b_position}");
+
+            assert_eq!(
+                overlaps(&a_shape, &a_position, &b_shape, &b_position),
+                expected,
+                "{message}"
+            );
+            assert_eq!(
+                overlaps(&b_shape, &b_position, &a_shape, &a_position),
+                expected,
+                "{message}"
+            );
+            assert_eq!(
+                a_shape.is_colliding(&b_shape, a_position.inv_mul(&b_position)),
+                expected,
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn manifolds_push_the_second_shape_clear() {
+        for (a_shape, a_position, b_shape, b_position, expected) in cases() {
+            let manifold = overlap_manifold(&a_shape, &a_position, &b_shape, &b_position);
+            assert_eq!(manifold.is_some(), expected);
+
+            let Some(manifold) = manifold else {
+                continue;
+            };
+            assert!(manifold.depth > 0.0);
+
+            let pushed = Isometry2::from_parts(
+                (b_position.translation.vector + *manifold.normal * (manifold.depth + 1e-6)).into(),
+                b_position.rotation,
+            );
+            assert!(
+                !overlaps(&a_shape, &a_position, &b_shape, &pushed),
+                "{a_shape:?} and {b_shape:?}"
+            );
+        }
+    }
+}
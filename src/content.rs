@@ -0,0 +1,634 @@
+//! Enemy kinds defined in JSON, so they can be changed without recompiling. A file holds an
+//! array of kinds like this one, where everything after `texture` is optional:
+//!
+//! ```json
+//! {
+//!     "name": "Red Circle",
+//!     "shape": { "type": "circle", "radius": 0.5 },
+//!     "speed": 3.0,
+//!     "angular_velocity": 0.0,
+//!     "maximum_health": 4,
+//!     "texture": "enemies/red_circle",
+//!     "acceleration": 10.0,
+//!     "hitbox_scale": 1.0,
+//!     "regen_rate": 0.0,
+//!     "regen_delay": 0.0,
+//!     "enrage_after": 20.0,
+//!     "enrage_multiplier": 1.5,
+//!     "death_effect": "explode"
+//! }
+//! ```
+//!
+//! Rectangles are given as `{ "type": "rectangle", "half_size": [0.6, 0.6] }`, and convex polygons
+//! as `{ "type": "polygon", "vertices": [[0.5, 0.0], [-0.25, 0.43], [-0.25, -0.43]] }` with the
+//! vertices counterclockwise. Textures can be given by name in the [`TextureRegistry`] or by
+//! numeric id.
+
+use std::{borrow::Cow, fmt, iter::Peekable, str::Chars};
+
+use nalgebra::{point, vector};
+
+use crate::{
+    enemy::{DeathEffect, EnemyKind, EnemyProperties},
+    shape::Shape,
+    utils::{TextureId, TextureRegistry},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// How deeply arrays and objects can be nested before a file is rejected, so that a malicious
+/// file can't overflow the stack
+pub const MAX_DEPTH: usize = 32;
+
+/// Parses an array of enemy kinds, looking up their textures in `registry`. Polygon vertices are
+/// leaked since shapes only borrow them, so this is meant to be called once per file when content
+/// is loaded.
+pub fn parse_enemy_kinds(
+    source: &str,
+    registry: &TextureRegistry,
+) -> Result<Vec<EnemyKind>, ContentError> {
+    let mut parser = Parser {
+        chars: source.chars().peekable(),
+        line: 1,
+        column: 1,
+        depth: 0,
+    };
+
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return Err(parser.error("unexpected text after the end of the file"));
+    }
+
+    let Json::Array(kinds) = &value.json else {
+        return Err(value.error("expected an array of enemy kinds"));
+    };
+
+    kinds
+        .iter()
+        .map(|kind| parse_enemy_kind(kind, registry))
+        .collect()
+}
+
+/// Appends `value` to `json` as a quoted string, escaping quotes, backslashes and control
+/// characters so that any JSON parser, including the one here, reads back the same string
+pub fn write_json_string(json: &mut String, value: &str) {
+    json.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+fn parse_enemy_kind(value: &Value, registry: &TextureRegistry) -> Result<EnemyKind, ContentError> {
+    let name = value.field("name")?.as_str()?;
+
+    let properties = EnemyProperties {
+        shape: parse_shape(value.field("shape")?)?,
+        speed: value.field("speed")?.as_non_negative()?,
+        acceleration: value
+            .optional_field("acceleration")?
+            .map_or(Ok(10.0), Value::as_non_negative)?,
+        angular_velocity: value.field("angular_velocity")?.as_f64()?,
+        maximum_health: value.field("maximum_health")?.as_positive_integer()?,
+        hitbox_scale: value
+            .optional_field("hitbox_scale")?
+            .map_or(Ok(1.0), Value::as_positive)?,
+        regen_rate: value
+            .optional_field("regen_rate")?
+            .map_or(Ok(0.0), Value::as_non_negative)?,
+        regen_delay: value
+            .optional_field("regen_delay")?
+            .map_or(Ok(0.0), Value::as_non_negative)?,
+        enrage_after: value
+            .optional_field("enrage_after")?
+            .map(Value::as_non_negative)
+            .transpose()?,
+        enrage_multiplier: value
+            .optional_field("enrage_multiplier")?
+            .map_or(Ok(1.0), Value::as_positive)?,
+        behavior: None,
+        texture: parse_texture(value.field("texture")?, registry)?,
+        death_effect: match value.optional_field("death_effect")? {
+            Some(effect) => match effect.as_str()? {
+                "explode" => DeathEffect::Explode,
+                "shrink_fade" => DeathEffect::ShrinkFade,
+                "none" => DeathEffect::None,
+                _ => {
+                    return Err(effect.error(
+                        "expected \"explode\", \"shrink_fade\", or \"none\" as the death effect",
+                    ));
+                }
+            },
+            None => DeathEffect::default(),
+        },
+    };
+
+    Ok(EnemyKind {
+        name: Cow::Owned(name.to_owned()),
+        properties,
+    })
+}
+
+fn parse_shape(value: &Value) -> Result<Shape, ContentError> {
+    let shape_type = value.field("type")?;
+
+    match shape_type.as_str()? {
+        "circle" => Ok(Shape::Circle {
+            radius: value.field("radius")?.as_positive()?,
+        }),
+        "rectangle" => {
+            let half_size = value.field("half_size")?;
+            let Json::Array(components) = &half_size.json else {
+                return Err(half_size.error("expected an array of two numbers"));
+            };
+            let [x, y] = components.as_slice() else {
+                return Err(half_size.error("expected an array of two numbers"));
+            };
+
+            Ok(Shape::Rectangle {
+                half_size: vector![x.as_positive()?, y.as_positive()?],
+            })
+        }
+        "polygon" => {
+            let vertices_value = value.field("vertices")?;
+            let Json::Array(items) = &vertices_value.json else {
+                return Err(vertices_value.error("expected an array of points"));
+            };
+
+            let vertices = items
+                .iter()
+                .map(|item| {
+                    let Json::Array(components) = &item.json else {
+                        return Err(item.error("expected an array of two numbers"));
+                    };
+                    let [x, y] = components.as_slice() else {
+                        return Err(item.error("expected an array of two numbers"));
+                    };
+
+                    Ok(point![x.as_f64()?, y.as_f64()?])
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let convex = vertices.len() >= 3
+                && (0..vertices.len()).all(|i| {
+                    let [a, b, c] = [0, 1, 2].map(|j| vertices[(i + j) % vertices.len()]);
+                    (b - a).perp(&(c - b)) > 0.0
+                });
+            if !convex {
+                return Err(vertices_value.error(
+                    "expected at least three vertices of a convex polygon, counterclockwise",
+                ));
+            }
+
+            Ok(Shape::Polygon {
+                vertices: Box::leak(vertices.into_boxed_slice()),
+                scale: 1.0,
+            })
+        }
+        _ => {
+            Err(shape_type
+                .error("expected \"circle\", \"rectangle\" or \"polygon\" as the shape type"))
+        }
+    }
+}
+
+fn parse_texture(value: &Value, registry: &TextureRegistry) -> Result<TextureId, ContentError> {
+    let id = match &value.json {
+        Json::String(name) => registry.id_of(name),
+        Json::Number(id) if id.fract() == 0.0 && *id >= 0.0 => Some(TextureId(*id as u32)),
+        _ => return Err(value.error("expected a texture name or id")),
+    };
+
+    id.filter(|&id| registry.get(id).is_some())
+        .ok_or_else(|| value.error("no texture is registered under this name or id"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/// A JSON value, along with where it started
+#[derive(Clone, Debug, PartialEq)]
+struct Value {
+    json: Json,
+    line: usize,
+    column: usize,
+}
+
+impl Value {
+    fn error(&self, message: &str) -> ContentError {
+        ContentError {
+            line: self.line,
+            column: self.column,
+            message: message.to_owned(),
+        }
+    }
+
+    fn optional_field(&self, key: &str) -> Result<Option<&Value>, ContentError> {
+        let Json::Object(fields) = &self.json else {
+            return Err(self.error("expected an object"));
+        };
+
+        Ok(fields
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value))
+    }
+
+    fn field(&self, key: &str) -> Result<&Value, ContentError> {
+        self.optional_field(key)?
+            .ok_or_else(|| self.error(&format!("missing the field \"{key}\"")))
+    }
+
+    fn as_str(&self) -> Result<&str, ContentError> {
+        match &self.json {
+            Json::String(string) => Ok(string),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    fn as_f64(&self) -> Result<f64, ContentError> {
+        match self.json {
+            Json::Number(number) if number.is_finite() => Ok(number),
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn as_non_negative(&self) -> Result<f64, ContentError> {
+        self.as_f64()?
+            .ge(&0.0)
+            .then_some(self.as_f64()?)
+            .ok_or_else(|| self.error("expected a number that isn't negative"))
+    }
+
+    fn as_positive(&self) -> Result<f64, ContentError> {
+        self.as_f64()?
+            .gt(&0.0)
+            .then_some(self.as_f64()?)
+            .ok_or_else(|| self.error("expected a positive number"))
+    }
+
+    fn as_positive_integer(&self) -> Result<u32, ContentError> {
+        let number = self.as_f64()?;
+
+        if number >= 1.0 && number.fract() == 0.0 && number <= u32::MAX as f64 {
+            Ok(number as u32)
+        } else {
+            Err(self.error("expected a positive whole number"))
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    /// How many arrays and objects the parser is inside of
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ContentError {
+        ContentError {
+            line: self.line,
+            column: self.column,
+            message: message.to_owned(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ContentError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{expected}'"))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ContentError> {
+        self.skip_whitespace();
+
+        let (line, column) = (self.line, self.column);
+
+        let json = match self.chars.peek() {
+            Some('{' | '[') if self.depth >= MAX_DEPTH => {
+                return Err(self.error("arrays and objects are nested too deeply"));
+            }
+            Some('{') => self.nested(Self::parse_object)?,
+            Some('[') => self.nested(Self::parse_array)?,
+            Some('"') => Json::String(self.parse_string()?),
+            Some('-' | '0'..='9') => self.parse_number()?,
+            Some('t' | 'f' | 'n') => self.parse_literal()?,
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of file")),
+        };
+
+        Ok(Value { json, line, column })
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, ContentError>,
+    ) -> Result<Json, ContentError> {
+        self.depth += 1;
+        let json = parse(self);
+        self.depth -= 1;
+
+        json
+    }
+
+    fn parse_object(&mut self) -> Result<Json, ContentError> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((key, self.parse_value()?));
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, ContentError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ContentError> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(match self.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let code = (0..4)
+                            .map(|_| self.next().and_then(|c| c.to_digit(16)))
+                            .try_fold(0, |code, digit| Some(code * 16 + digit?))
+                            .and_then(char::from_u32);
+
+                        code.ok_or_else(|| self.error("invalid unicode escape"))?
+                    }
+                    _ => return Err(self.error("invalid escape sequence")),
+                }),
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, ContentError> {
+        let mut text = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+
+            text.push(c);
+            self.next();
+        }
+
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+
+    fn parse_literal(&mut self) -> Result<Json, ContentError> {
+        let mut word = String::new();
+
+        while self.chars.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            word.push(self.next().unwrap());
+        }
+
+        match word.as_str() {
+            "true" => Ok(Json::Bool(true)),
+            "false" => Ok(Json::Bool(false)),
+            "null" => Ok(Json::Null),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: &str = r#"[
+        {
+            "name": "Green Square",
+            "shape": { "type": "rectangle", "half_size": [0.6, 0.4] },
+            "speed": 2.5,
+            "angular_velocity": 1.0,
+            "maximum_health": 6,
+            "texture": "enemies/red_square",
+            "death_effect": "shrink_fade"
+        },
+        {
+            "name": "Triangle",
+            "shape": { "type": "polygon", "vertices": [[0.5, 0.0], [-0.25, 0.43], [-0.25, -0.43]] },
+            "speed": 4,
+            "angular_velocity": 0,
+            "maximum_health": 1,
+            "texture": 0,
+            "enrage_after": 10
+        }
+    ]"#;
+
+    #[test]
+    fn enemy_kinds_are_parsed() {
+        let registry = TextureRegistry::with_built_in_textures();
+        let kinds = parse_enemy_kinds(KINDS, &registry).unwrap();
+
+        assert_eq!(kinds.len(), 2);
+
+        let square = &kinds[0];
+        assert_eq!(square.name, "Green Square");
+        assert!(matches!(
+            square.properties.shape,
+            Shape::Rectangle { half_size } if half_size == vector![0.6, 0.4],
+        ));
+        assert_eq!(square.properties.speed, 2.5);
+        assert_eq!(square.properties.angular_velocity, 1.0);
+        assert_eq!(square.properties.maximum_health, 6);
+        assert_eq!(square.properties.texture, TextureId::RED_SQUARE);
+        assert_eq!(square.properties.acceleration, 10.0);
+        assert_eq!(square.properties.enrage_after, None);
+        assert!(matches!(
+            square.properties.death_effect,
+            DeathEffect::ShrinkFade,
+        ));
+
+        let triangle = &kinds[1];
+        assert_eq!(triangle.name, "Triangle");
+        assert!(matches!(
+            triangle.properties.shape,
+            Shape::Polygon { vertices, .. } if vertices.len() == 3,
+        ));
+        assert_eq!(triangle.properties.texture, TextureId(0));
+        assert_eq!(triangle.properties.enrage_after, Some(10.0));
+    }
+
+    #[test]
+    fn malformed_files_report_where_they_went_wrong() {
+        let registry = TextureRegistry::with_built_in_textures();
+        let error = |source: &str| parse_enemy_kinds(source, &registry).unwrap_err();
+
+        let missing_comma = error("[\n  {\n    \"name\": \"A\"\n    \"speed\": 1\n  }\n]");
+        assert_eq!((missing_comma.line, missing_comma.column), (4, 6));
+        assert_eq!(missing_comma.message, "expected ',' or '}'");
+
+        let negative_health =
+            error(&KINDS.replace("\"maximum_health\": 6", "\"maximum_health\": -6"));
+        assert_eq!(negative_health.line, 7);
+        assert_eq!(negative_health.message, "expected a positive whole number");
+
+        let clockwise = error(&KINDS.replace(
+            "[[0.5, 0.0], [-0.25, 0.43], [-0.25, -0.43]]",
+            "[[0.5, 0.0], [-0.25, -0.43], [-0.25, 0.43]]",
+        ));
+        assert!(clockwise.message.contains("convex polygon"));
+
+        let unknown_texture = error(&KINDS.replace("enemies/red_square", "enemies/missing"));
+        assert_eq!(
+            unknown_texture.message,
+            "no texture is registered under this name or id",
+        );
+
+        assert_eq!(error("{}").message, "expected an array of enemy kinds");
+        assert_eq!(
+            error("[] []").message,
+            "unexpected text after the end of the file"
+        );
+    }
+
+    #[test]
+    fn deeply_nested_files_are_rejected() {
+        let registry = TextureRegistry::with_built_in_textures();
+
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        let error = parse_enemy_kinds(&nested(100_000), &registry).unwrap_err();
+
+        assert_eq!(error.message, "arrays and objects are nested too deeply");
+        assert_eq!((error.line, error.column), (1, MAX_DEPTH + 1));
+        assert!(
+            parse_enemy_kinds(&nested(MAX_DEPTH), &registry)
+                .is_err_and(|error| error.message != "arrays and objects are nested too deeply")
+        );
+    }
+
+    #[test]
+    fn written_strings_parse_back_unchanged() {
+        for value in [
+            "Red Circle",
+            "say \"hi\"",
+            "back\\slash",
+            "tab\tnew\nline\u{1}",
+            "ünï",
+        ] {
+            let mut json = String::new();
+            write_json_string(&mut json, value);
+
+            let mut parser = Parser {
+                chars: json.chars().peekable(),
+                line: 1,
+                column: 1,
+                depth: 0,
+            };
+            assert_eq!(parser.parse_string().unwrap(), value);
+            assert!(parser.chars.peek().is_none());
+            assert!(!json.contains('\n') && !json.contains('\u{1}'));
+        }
+    }
+}
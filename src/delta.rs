@@ -0,0 +1,667 @@
+//! Compact deltas between game states, for keeping a remote copy of a game in sync. Enemies,
+//! projectiles, and turrets are sent. Each one is identified by its key in the sending game, which
+//! [`DeltaDecoder`] maps to a key in the receiving game.
+//!
+//! Entities are created from their kind, in the format of [`crate::snapshot`], and only their
+//! motion and health are kept in sync after that. An entity whose kind changes, like a turret
+//! switching weapons, is sent again in full.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use nalgebra::{Complex, Isometry2, Translation2, UnitComplex, Vector2, vector};
+use slotmap::Key;
+
+use crate::{
+    enemy::Enemy,
+    game::{EnemyKey, Game, ProjectileKey, TurretKey},
+    object::Transform,
+    projectile::Projectile,
+    snapshot::{self, SnapshotError},
+    turret::Turret,
+};
+
+const TAG_UPDATE: u8 = 0;
+const TAG_CREATE: u8 = 1;
+const TAG_REMOVE: u8 = 2;
+
+const ENTITY_ENEMY: u8 = 0;
+const ENTITY_PROJECTILE: u8 = 1;
+const ENTITY_TURRET: u8 = 2;
+
+const CHANGED_POSITION: u8 = 1 << 0;
+const CHANGED_VELOCITY: u8 = 1 << 1;
+const CHANGED_HEALTH: u8 = 1 << 2;
+const CHANGED_ALL: u8 = CHANGED_POSITION | CHANGED_VELOCITY | CHANGED_HEALTH;
+
+/// The state of an entity that is sent over the network. Positions and velocities are sent with
+/// full precision, so the remote copy matches exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntitySnapshot {
+    /// What the entity is, which is only sent when it is created
+    pub kind: Vec<u8>,
+    pub position: Isometry2<f64>,
+    pub linear_velocity: Vector2<f64>,
+    pub angular_velocity: f64,
+    /// Always zero for entities without health
+    pub health: u32,
+}
+
+/// Everything needed to compute a delta against a later state of the game
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub enemies: BTreeMap<u64, EntitySnapshot>,
+    pub projectiles: BTreeMap<u64, EntitySnapshot>,
+    pub turrets: BTreeMap<u64, EntitySnapshot>,
+}
+
+/// Applies deltas from [`encode_delta`] to a local game. Each map takes keys from the sending
+/// game to keys in the local game.
+#[derive(Clone, Debug, Default)]
+pub struct DeltaDecoder {
+    pub enemies: HashMap<u64, EnemyKey>,
+    pub projectiles: HashMap<u64, ProjectileKey>,
+    pub turrets: HashMap<u64, TurretKey>,
+}
+
+/// Smooths out an entity that is only updated when snapshots arrive, by blending between the
+/// last two snapshots it was seen in
+#[derive(Clone, Copy, Debug)]
+pub struct RemoteEntity {
+    pub previous: Transform,
+    pub latest: Transform,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaError {
+    Truncated,
+    UnknownTag(u8),
+    UnknownEntity(u8),
+    /// A created entity's kind couldn't be restored
+    InvalidKind(SnapshotError),
+}
+
+/// The parts of an entity that a record changed
+#[derive(Clone, Copy, Debug, Default)]
+struct Changes {
+    position: Option<Isometry2<f64>>,
+    velocity: Option<(Vector2<f64>, f64)>,
+    health: Option<u32>,
+}
+
+impl EntitySnapshot {
+    /// Fails if `enemy` has a behavior the remote game would have no way to recreate
+    pub fn of_enemy(enemy: &Enemy) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            health: enemy.health,
+            ..Self::moving(snapshot::encode_enemy_kind(enemy)?, &enemy.transform)
+        })
+    }
+
+    #[must_use]
+    pub fn of_projectile(projectile: &Projectile) -> Self {
+        Self::moving(
+            snapshot::encode_projectile_kind(projectile),
+            &projectile.transform,
+        )
+    }
+
+    #[must_use]
+    pub fn of_turret(turret: &Turret) -> Self {
+        Self {
+            kind: snapshot::encode_turret_kind(turret),
+            position: turret.position,
+            linear_velocity: Vector2::zeros(),
+            angular_velocity: 0.0,
+            health: 0,
+        }
+    }
+
+    fn moving(kind: Vec<u8>, transform: &Transform) -> Self {
+        Self {
+            kind,
+            position: transform.position,
+            linear_velocity: transform.linear_velocity,
+            angular_velocity: transform.angular_velocity,
+            health: 0,
+        }
+    }
+
+    fn changes_from(&self, previous: &Self) -> u8 {
+        let mut changes = 0;
+
+        if self.position != previous.position {
+            changes |= CHANGED_POSITION;
+        }
+        if self.linear_velocity != previous.linear_velocity
+            || self.angular_velocity != previous.angular_velocity
+        {
+            changes |= CHANGED_VELOCITY;
+        }
+        if self.health != previous.health {
+            changes |= CHANGED_HEALTH;
+        }
+
+        changes
+    }
+
+    fn write(&self, changes: u8, bytes: &mut Vec<u8>) {
+        if changes & CHANGED_POSITION != 0 {
+            // The rotation is sent as is rather than as an angle, which wouldn't survive the trip
+            for value in [
+                self.position.translation.x,
+                self.position.translation.y,
+                self.position.rotation.re,
+                self.position.rotation.im,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        if changes & CHANGED_VELOCITY != 0 {
+            for value in [
+                self.linear_velocity.x,
+                self.linear_velocity.y,
+                self.angular_velocity,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        if changes & CHANGED_HEALTH != 0 {
+            bytes.extend_from_slice(&self.health.to_le_bytes());
+        }
+    }
+}
+
+impl Snapshot {
+    /// Fails if an enemy can't be sent, see [`EntitySnapshot::of_enemy`]
+    pub fn of(game: &Game) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            enemies: (game.enemies.iter())
+                .map(|(key, enemy)| Ok((key.data().as_ffi(), EntitySnapshot::of_enemy(enemy)?)))
+                .collect::<Result<_, _>>()?,
+            projectiles: (game.projectiles.iter())
+                .map(|(key, projectile)| {
+                    let snapshot = EntitySnapshot::of_projectile(projectile);
+                    (key.data().as_ffi(), snapshot)
+                })
+                .collect(),
+            turrets: (game.turrets.iter())
+                .map(|(key, turret)| (key.data().as_ffi(), EntitySnapshot::of_turret(turret)))
+                .collect(),
+        })
+    }
+}
+
+/// Encodes only what changed between `previous` and `current`, including entities that were
+/// created or removed
+#[must_use]
+pub fn encode_delta(previous: &Snapshot, current: &Snapshot) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for (entity, previous, current) in [
+        (ENTITY_ENEMY, &previous.enemies, &current.enemies),
+        (
+            ENTITY_PROJECTILE,
+            &previous.projectiles,
+            &current.projectiles,
+        ),
+        (ENTITY_TURRET, &previous.turrets, &current.turrets),
+    ] {
+        encode_entities(entity, previous, current, &mut bytes);
+    }
+
+    bytes
+}
+
+fn encode_entities(
+    entity: u8,
+    previous: &BTreeMap<u64, EntitySnapshot>,
+    current: &BTreeMap<u64, EntitySnapshot>,
+    bytes: &mut Vec<u8>,
+) {
+    let record = |bytes: &mut Vec<u8>, tag: u8, key: u64| {
+        bytes.push(tag);
+        bytes.push(entity);
+        bytes.extend_from_slice(&key.to_le_bytes());
+    };
+
+    for &key in previous.keys() {
+        if !current.contains_key(&key) {
+            record(bytes, TAG_REMOVE, key);
+        }
+    }
+
+    for (&key, snapshot) in current {
+        match previous.get(&key) {
+            Some(previous) if previous.kind == snapshot.kind => {
+                let changes = snapshot.changes_from(previous);
+
+                if changes != 0 {
+                    record(bytes, TAG_UPDATE, key);
+                    bytes.push(changes);
+                    snapshot.write(changes, bytes);
+                }
+            }
+            _ => {
+                record(bytes, TAG_CREATE, key);
+                bytes.extend_from_slice(&(snapshot.kind.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&snapshot.kind);
+                snapshot.write(CHANGED_ALL, bytes);
+            }
+        }
+    }
+}
+
+impl DeltaDecoder {
+    /// Applies a delta to `game`. Updates to entities this decoder doesn't know about are ignored.
+    /// If the delta is malformed, everything before the problem is still applied.
+    ///
+    /// Entities `game` already had are left alone, so its own turrets should be cleared before the
+    /// first delta for it to match the sending game. The first turret created after that becomes
+    /// its primary turret.
+    pub fn apply(&mut self, game: &mut Game, bytes: &[u8]) -> Result<(), DeltaError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        game.grid.stale = true;
+
+        while !reader.is_empty() {
+            let tag = reader.u8()?;
+            let entity = reader.u8()?;
+            let key = reader.u64()?;
+
+            if !matches!(entity, ENTITY_ENEMY | ENTITY_PROJECTILE | ENTITY_TURRET) {
+                return Err(DeltaError::UnknownEntity(entity));
+            }
+
+            match tag {
+                TAG_REMOVE => self.remove(game, entity, key),
+                TAG_CREATE => {
+                    let length = reader.u32()? as usize;
+                    let kind = reader.slice(length)?;
+                    let changes = reader.changes(CHANGED_ALL)?;
+
+                    self.remove(game, entity, key);
+                    self.create(game, entity, key, kind, changes)
+                        .map_err(DeltaError::InvalidKind)?;
+                }
+                TAG_UPDATE => {
+                    let changes = reader.u8()?;
+                    let changes = reader.changes(changes)?;
+
+                    self.update(game, entity, key, changes);
+                }
+                tag => return Err(DeltaError::UnknownTag(tag)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, game: &mut Game, entity: u8, key: u64) {
+        match entity {
+            ENTITY_ENEMY => {
+                if let Some(local_key) = self.enemies.remove(&key) {
+                    game.remove_enemy(local_key);
+                }
+            }
+            ENTITY_PROJECTILE => {
+                if let Some(local_key) = self.projectiles.remove(&key) {
+                    game.projectiles.remove(local_key);
+                }
+            }
+            _ => {
+                if let Some(local_key) = self.turrets.remove(&key) {
+                    game.turrets.remove(local_key);
+                }
+            }
+        }
+    }
+
+    fn create(
+        &mut self,
+        game: &mut Game,
+        entity: u8,
+        key: u64,
+        kind: &[u8],
+        changes: Changes,
+    ) -> Result<(), SnapshotError> {
+        match entity {
+            ENTITY_ENEMY => {
+                let mut enemy = snapshot::decode_enemy_kind(kind)?;
+                changes.apply(&mut enemy.object.transform, &mut enemy.direction);
+                enemy.health = changes.health.unwrap_or(enemy.health);
+
+                self.enemies.insert(key, game.insert_enemy(enemy));
+            }
+            ENTITY_PROJECTILE => {
+                let mut projectile = snapshot::decode_projectile_kind(kind)?;
+                changes.apply(&mut projectile.object.transform, &mut projectile.direction);
+
+                (self.projectiles).insert(key, game.insert_projectile(projectile));
+            }
+            _ => {
+                let mut turret = snapshot::decode_turret_kind(kind)?;
+                turret.position = changes.position.unwrap_or(turret.position);
+
+                let local_key = game.turrets.insert(turret);
+                if !game.turrets.contains_key(game.primary_turret_key) {
+                    game.primary_turret_key = local_key;
+                }
+                self.turrets.insert(key, local_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, game: &mut Game, entity: u8, key: u64, changes: Changes) {
+        match entity {
+            ENTITY_ENEMY => {
+                if let Some(enemy) =
+                    (self.enemies.get(&key)).and_then(|&local_key| game.enemies.get_mut(local_key))
+                {
+                    changes.apply(&mut enemy.object.transform, &mut enemy.direction);
+                    enemy.health = changes.health.unwrap_or(enemy.health);
+                }
+            }
+            ENTITY_PROJECTILE => {
+                if let Some(projectile) = (self.projectiles.get(&key))
+                    .and_then(|&local_key| game.projectiles.get_mut(local_key))
+                {
+                    changes.apply(&mut projectile.object.transform, &mut projectile.direction);
+                }
+            }
+            _ => {
+                if let Some(turret) =
+                    (self.turrets.get(&key)).and_then(|&local_key| game.turrets.get_mut(local_key))
+                {
+                    turret.position = changes.position.unwrap_or(turret.position);
+                }
+            }
+        }
+    }
+}
+
+impl Changes {
+    fn apply(&self, transform: &mut Transform, direction: &mut UnitComplex<f64>) {
+        if let Some(position) = self.position {
+            transform.position = position;
+        }
+
+        if let Some((linear_velocity, angular_velocity)) = self.velocity {
+            transform.linear_velocity = linear_velocity;
+            transform.angular_velocity = angular_velocity;
+
+            // Enemies and projectiles move along their direction, so it can be recovered from
+            // their velocity
+            if linear_velocity != Vector2::zeros() {
+                *direction = UnitComplex::new(linear_velocity.y.atan2(linear_velocity.x));
+            }
+        }
+    }
+}
+
+impl RemoteEntity {
+    /// How far past the latest snapshot [`RemoteEntity::render_transform`] will extrapolate, as a
+    /// fraction of the time between snapshots
+    pub const MAXIMUM_EXTRAPOLATION: f64 = 0.5;
+
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            latest: transform,
+        }
+    }
+
+    pub fn push(&mut self, transform: Transform) {
+        self.previous = self.latest;
+        self.latest = transform;
+    }
+
+    /// Where to draw the entity `alpha` of the way from the previous snapshot to the latest one.
+    /// Past `1.0` the motion continues, up to [`RemoteEntity::MAXIMUM_EXTRAPOLATION`] further.
+    #[must_use]
+    pub fn render_transform(&self, alpha: f64) -> Transform {
+        let alpha = alpha.clamp(0.0, 1.0 + Self::MAXIMUM_EXTRAPOLATION);
+
+        self.previous.interpolated(&self.latest, alpha)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], DeltaError> {
+        let bytes = (self.bytes)
+            .get(self.offset..self.offset.saturating_add(length))
+            .ok_or(DeltaError::Truncated)?;
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DeltaError> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, DeltaError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, DeltaError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, DeltaError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, DeltaError> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    fn changes(&mut self, changes: u8) -> Result<Changes, DeltaError> {
+        let mut read = Changes::default();
+
+        if changes & CHANGED_POSITION != 0 {
+            let (x, y) = (self.f64()?, self.f64()?);
+            let rotation = Complex::new(self.f64()?, self.f64()?);
+            read.position = Some(Isometry2::from_parts(
+                Translation2::new(x, y),
+                UnitComplex::new_unchecked(rotation),
+            ));
+        }
+
+        if changes & CHANGED_VELOCITY != 0 {
+            read.velocity = Some((vector![self.f64()?, self.f64()?], self.f64()?));
+        }
+
+        if changes & CHANGED_HEALTH != 0 {
+            read.health = Some(self.u32()?);
+        }
+
+        Ok(read)
+    }
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeltaError::Truncated => write!(f, "delta ended in the middle of a record"),
+            DeltaError::UnknownTag(tag) => write!(f, "unknown delta record tag {tag}"),
+            DeltaError::UnknownEntity(entity) => write!(f, "unknown entity type {entity}"),
+            DeltaError::InvalidKind(error) => write!(f, "invalid entity kind: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use nalgebra::{Isometry2, Point2, point, vector};
+
+    use super::*;
+    use crate::{
+        behavior::Straight,
+        enemy::{ENEMY_KINDS, EnemyKind, EnemyProperties},
+        projectile::PROJECTILE_KINDS,
+        rng::Rng,
+        shape::Shape,
+    };
+
+    fn tick(game: &mut Game, ticks: usize) {
+        let camera_bounds = Shape::Rectangle {
+            half_size: vector![20.0, 20.0],
+        };
+
+        for _ in 0..ticks {
+            game.tick_with_input(point![0.0, 10.0], camera_bounds, 1.0 / 120.0);
+        }
+    }
+
+    /// A snapshot of `game` under the keys of the game `decoder` is mirroring
+    fn mirrored(game: &Game, decoder: &DeltaDecoder) -> Snapshot {
+        fn remap<K: Key>(
+            entities: BTreeMap<u64, EntitySnapshot>,
+            keys: &HashMap<u64, K>,
+        ) -> BTreeMap<u64, EntitySnapshot> {
+            let remote_keys = (keys.iter())
+                .map(|(&remote_key, local_key)| (local_key.data().as_ffi(), remote_key))
+                .collect::<HashMap<_, _>>();
+
+            (entities.into_iter())
+                .map(|(key, entity)| (remote_keys[&key], entity))
+                .collect()
+        }
+
+        let snapshot = Snapshot::of(game).unwrap();
+
+        Snapshot {
+            enemies: remap(snapshot.enemies, &decoder.enemies),
+            projectiles: remap(snapshot.projectiles, &decoder.projectiles),
+            turrets: remap(snapshot.turrets, &decoder.turrets),
+        }
+    }
+
+    #[test]
+    fn applying_a_delta_reproduces_the_later_state() {
+        static TRIANGLE: [Point2<f64>; 3] =
+            [point![1.0, 0.0], point![-0.5, 0.8], point![-0.5, -0.8]];
+        let loaded = Box::leak(Box::new(EnemyKind {
+            name: String::from("Loaded Triangle").into(),
+            properties: EnemyProperties {
+                shape: Shape::Polygon {
+                    vertices: &TRIANGLE,
+                    scale: 1.0,
+                },
+                ..ENEMY_KINDS[0].properties
+            },
+        }));
+
+        let mut game = Game::with_seed(9);
+        for (i, kind_index) in [0, 1, 3, 4].into_iter().enumerate() {
+            let angle = i as f64 * PI / 2.0;
+            let position = Isometry2::new(vector![angle.cos(), angle.sin()] * 8.0, angle + PI);
+            game.spawn_enemy(kind_index, position).unwrap();
+        }
+        (game.enemies).insert(Enemy::new(Isometry2::new(vector![5.0, 5.0], 0.0), loaded));
+        game.turrets.insert(Turret {
+            position: Isometry2::new(vector![3.0, -2.0], 0.0),
+            ..Default::default()
+        });
+        let mut projectile = Projectile::new(
+            Isometry2::new(vector![0.0, 1.0], PI / 2.0),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::default(),
+        );
+        projectile.linear_velocity = vector![0.0, 15.0];
+        game.projectiles.insert(projectile);
+        tick(&mut game, 30);
+
+        let mut remote = Game::with_seed(9);
+        remote.turrets.clear();
+        let mut decoder = DeltaDecoder::default();
+        let before = Snapshot::of(&game).unwrap();
+        decoder
+            .apply(&mut remote, &encode_delta(&Snapshot::default(), &before))
+            .unwrap();
+        assert!(!before.projectiles.is_empty());
+        assert_eq!(before.turrets.len(), 2);
+        assert_eq!(mirrored(&remote, &decoder), before);
+
+        let removed = game.enemies.keys().next().unwrap();
+        game.remove_enemy(removed);
+        game.spawn_enemy(2, Isometry2::new(vector![-6.0, 3.0], 0.3))
+            .unwrap();
+        for turret in game.turrets.values_mut() {
+            turret.projectile_kind = PROJECTILE_KINDS[3].clone();
+        }
+        tick(&mut game, 45);
+
+        let after = Snapshot::of(&game).unwrap();
+        decoder
+            .apply(&mut remote, &encode_delta(&before, &after))
+            .unwrap();
+        assert_eq!(mirrored(&remote, &decoder), after);
+    }
+
+    #[test]
+    fn enemies_with_behaviors_from_outside_the_built_in_kinds_are_not_sent() {
+        let mut kind = ENEMY_KINDS[0].clone();
+        kind.properties.behavior = Some(|| Box::new(Straight));
+        let mut game = Game::default();
+        game.enemies
+            .insert(Enemy::new(Isometry2::identity(), &kind));
+
+        assert_eq!(
+            Snapshot::of(&game).unwrap_err(),
+            SnapshotError::UnknownEnemyKind,
+        );
+    }
+
+    #[test]
+    fn remote_entities_blend_between_snapshots_and_extrapolate_a_little() {
+        let snapshot = |x: f64, angle: f64| Transform {
+            position: Isometry2::new(vector![x, 1.0], angle),
+            linear_velocity: vector![4.0, 0.0],
+            angular_velocity: 1.0,
+        };
+        let mut remote = RemoteEntity::new(snapshot(0.0, 0.1));
+        remote.push(snapshot(2.0, 0.5));
+
+        let position = |alpha| remote.render_transform(alpha).position;
+        assert!((position(0.0).translation.vector - vector![0.0, 1.0]).norm() < 1e-9);
+        assert!((position(1.0).translation.vector - vector![2.0, 1.0]).norm() < 1e-9);
+
+        let mut last_x = 0.0;
+        for alpha in [0.25, 0.5, 0.75] {
+            let position = position(alpha);
+            let x = position.translation.vector.x;
+
+            assert!(x > last_x && x < 2.0);
+            assert!((x - alpha * 2.0).abs() < 1e-9);
+            assert!((position.translation.vector.y - 1.0).abs() < 1e-9);
+            assert!(position.rotation.angle() > 0.1 && position.rotation.angle() < 0.5);
+            last_x = x;
+        }
+
+        // Motion carries on past the latest snapshot, but only so far
+        assert!((position(1.25).translation.vector.x - 2.5).abs() < 1e-9);
+        let furthest = 2.0 + 2.0 * RemoteEntity::MAXIMUM_EXTRAPOLATION;
+        assert!((position(10.0).translation.vector.x - furthest).abs() < 1e-9);
+        assert!((position(-1.0).translation.vector.x).abs() < 1e-9);
+    }
+}
@@ -1,79 +1,126 @@
 use std::{
+    borrow::Cow,
     f64::consts::TAU,
     num::NonZeroUsize,
     ops::{Deref, DerefMut, Range},
-    sync::LazyLock,
+    ptr,
+    sync::{Arc, LazyLock},
 };
 
 use macroquad::{
-    color::colors,
-    texture::{self, DrawTextureParams, FilterMode, Image, Texture2D},
+    color::{Color, colors},
+    texture::{self, DrawTextureParams, Image},
 };
 use nalgebra::{DMatrix, Isometry2, Point2, UnitComplex, Vector2, point, vector};
-use slotmap::{HopSlotMap, SlotMap};
+use slotmap::{HopSlotMap, Key, SlotMap};
 
 use crate::{
-    game::ParticleKey,
+    behavior::{EnemyBehavior, EnemyContext},
+    game::{EnemyKey, ParticleKey},
     object::{Object, Transform},
-    particle::Particle,
+    particle::{Particle, ParticleLayer},
+    projectile::SourceId,
+    rng::{self, Rng},
     shape::Shape,
-    utils::{self, BoundingBox, ENEMY_TEXTURES, TextureEntry},
+    tether::TetherPull,
+    tween,
+    utils::{self, BoundingBox, GLITTER_TEXTURES, TextureEntry, TextureId},
 };
 
 pub static ENEMY_KINDS: LazyLock<[EnemyKind; 5]> = LazyLock::new(|| {
     [
         EnemyKind {
-            name: "Red Circle",
+            name: Cow::Borrowed("Red Circle"),
             properties: EnemyProperties {
                 shape: Shape::Circle { radius: 0.5 },
                 speed: 3.0,
+                acceleration: 10.0,
                 angular_velocity: 0.0,
                 maximum_health: 4,
-                texture: &ENEMY_TEXTURES[0],
+                hitbox_scale: 1.0,
+                regen_rate: 0.0,
+                regen_delay: 0.0,
+                enrage_after: None,
+                enrage_multiplier: 1.0,
+                behavior: None,
+                texture: TextureId::RED_CIRCLE,
+                death_effect: DeathEffect::Explode,
             },
         },
         EnemyKind {
-            name: "Purple Circle",
+            name: Cow::Borrowed("Purple Circle"),
             properties: EnemyProperties {
                 shape: Shape::Circle { radius: 0.5 },
                 speed: 9.0,
+                acceleration: 10.0,
                 angular_velocity: 0.0,
                 maximum_health: 4,
-                texture: &ENEMY_TEXTURES[1],
+                hitbox_scale: 1.0,
+                regen_rate: 0.0,
+                regen_delay: 0.0,
+                enrage_after: None,
+                enrage_multiplier: 1.0,
+                behavior: None,
+                texture: TextureId::PURPLE_CIRCLE,
+                death_effect: DeathEffect::Explode,
             },
         },
         EnemyKind {
-            name: "Electric Circle",
+            name: Cow::Borrowed("Electric Circle"),
             properties: EnemyProperties {
                 shape: Shape::Circle { radius: 0.6 },
                 speed: 12.0,
+                acceleration: 10.0,
                 angular_velocity: 0.0,
                 maximum_health: 4,
-                texture: &ENEMY_TEXTURES[2],
+                hitbox_scale: 1.0,
+                regen_rate: 0.0,
+                regen_delay: 0.0,
+                enrage_after: None,
+                enrage_multiplier: 1.0,
+                behavior: None,
+                texture: TextureId::ELECTRIC_CIRCLE,
+                death_effect: DeathEffect::Explode,
             },
         },
         EnemyKind {
-            name: "Red Square",
+            name: Cow::Borrowed("Red Square"),
             properties: EnemyProperties {
                 shape: Shape::Rectangle {
                     half_size: vector![0.6, 0.6],
                 },
                 speed: 3.0,
+                acceleration: 10.0,
                 angular_velocity: -5.0 / 24.0 * TAU,
                 maximum_health: 8,
-                texture: &ENEMY_TEXTURES[3],
+                hitbox_scale: 1.0,
+                regen_rate: 0.0,
+                regen_delay: 0.0,
+                enrage_after: None,
+                enrage_multiplier: 1.0,
+                behavior: None,
+                texture: TextureId::RED_SQUARE,
+                death_effect: DeathEffect::Explode,
             },
         },
         EnemyKind {
-            name: "Purple Square",
+            name: Cow::Borrowed("Purple Square"),
             properties: EnemyProperties {
                 shape: Shape::Rectangle {
                     half_size: vector![0.8, 0.8],
                 },
                 speed: 3.0,
+                acceleration: 10.0,
                 angular_velocity: 1.0 / 6.0 * TAU,
                 maximum_health: 12,
-                texture: &ENEMY_TEXTURES[4],
+                hitbox_scale: 1.0,
+                regen_rate: 0.0,
+                regen_delay: 0.0,
+                enrage_after: None,
+                enrage_multiplier: 1.0,
+                behavior: None,
+                texture: TextureId::PURPLE_SQUARE,
+                death_effect: DeathEffect::Explode,
             },
         },
     ]
@@ -81,21 +128,84 @@ pub static ENEMY_KINDS: LazyLock<[EnemyKind; 5]> = LazyLock::new(|| {
 
 #[derive(Clone, Debug)]
 pub struct Enemy {
+    /// The name of the kind this enemy was created from
+    pub name: Cow<'static, str>,
+    /// Where that kind is in [`ENEMY_KINDS`], or `None` if it isn't one of them
+    pub kind_index: Option<usize>,
+    /// From [`SpawnOrder::allocate`](crate::utils::SpawnOrder::allocate) when the enemy was
+    /// added to its game, or zero if it was inserted into [`Game::enemies`](crate::game::Game::enemies)
+    /// directly
+    pub spawn_index: u64,
     pub object: Object,
     pub direction: UnitComplex<f64>,
+    pub current_speed: f64,
 
     pub properties: EnemyProperties,
+    pub behavior: Option<Box<dyn EnemyBehavior>>,
+    /// Looked up from `properties.texture` when the enemy is created, so drawing doesn't have to
+    /// go through the registry. Enemies with no texture aren't drawn and explode into sparks.
+    pub texture: Option<Arc<TextureEntry>>,
 
     pub health: u32,
+    pub time_since_spawn: f64,
     pub time_since_hit: f64,
+    /// Whoever last damaged this enemy, if known
+    pub last_hit_source: Option<SourceId>,
+    /// The game tick this enemy was last damaged on
+    pub last_hit_tick: u64,
+    /// Damage over time that hasn't added up to a whole point of health yet
+    pub partial_damage: f64,
+    /// Regeneration that hasn't added up to a whole point of health yet
+    pub partial_regen: f64,
 
     pub brightness: f64,
     pub brightness_update_time: f64,
+
+    /// Set once the enemy has died, if its explosion has to wait
+    pub pending_explosion: Option<PendingExplosion>,
+
+    /// Counts up towards some upcoming action, tinting the enemy as it gets closer
+    pub charge_timer: Option<ChargeTimer>,
+    /// Set each tick by a tethered projectile, overriding the enemy's own movement
+    pub tether: Option<TetherPull>,
+
+    /// A point the enemy turns back towards whenever it strays further than `leash_range` away
+    pub anchor: Option<Point2<f64>>,
+    pub leash_range: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChargeTimer {
+    pub elapsed: f64,
+    pub duration: f64,
+}
+
+impl ChargeTimer {
+    pub fn new(duration: f64) -> Self {
+        Self {
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct EnemyKind {
-    pub name: &'static str,
+    pub name: Cow<'static, str>,
     pub properties: EnemyProperties,
 }
 
@@ -104,19 +214,152 @@ pub struct EnemyProperties {
     pub shape: Shape,
 
     pub speed: f64,
+    /// How quickly the enemy's speed approaches its target. Higher values feel lighter.
+    pub acceleration: f64,
     pub angular_velocity: f64,
 
     pub maximum_health: u32,
-    pub texture: &'static TextureEntry,
+    /// Scales the collision shape without changing how the enemy is drawn. Smaller values are
+    /// more forgiving.
+    pub hitbox_scale: f64,
+    /// Health regained per second once the enemy has gone `regen_delay` seconds without being
+    /// hit. Zero disables regeneration.
+    pub regen_rate: f64,
+    pub regen_delay: f64,
+    /// How long the enemy can live before it enrages, multiplying its speed and damage by
+    /// `enrage_multiplier`. `None` disables enraging.
+    pub enrage_after: Option<f64>,
+    pub enrage_multiplier: f64,
+    /// Creates the behavior controlling this enemy's movement. Enemies without one move in a
+    /// straight line.
+    pub behavior: Option<fn() -> Box<dyn EnemyBehavior>>,
+    pub texture: TextureId,
+    pub death_effect: DeathEffect,
+}
+
+/// What is left behind when an enemy dies
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DeathEffect {
+    /// Bursts into fragments of its texture, depending on [`ExplosionParameters`]
+    #[default]
+    Explode,
+    /// A cheap effect for swarms of weak enemies
+    ShrinkFade,
+    None,
+}
+
+/// Settings shared by every enemy explosion
+#[derive(Clone, Copy, Debug)]
+pub struct ExplosionParameters {
+    /// The size of fragments relative to the pixels of the enemy's texture. Fragments are spawned
+    /// at the same places regardless, so smaller fragments still cover the enemy's silhouette.
+    pub fragment_scale: f64,
+    pub quality: Quality,
+    /// Draws each explosion's randomness from a seed based on the enemy and tick, instead of the
+    /// game's generator, so that single explosions can be reproduced
+    pub seeded: bool,
+    /// The most explosions run in one tick. The rest wait for later ticks, with their enemies
+    /// held in place and shattering. `None` runs every explosion right away.
+    pub maximum_per_tick: Option<usize>,
+    /// How fragments fade over their lifetime, as in [`Particle::fade_curve`]. Curves that stay
+    /// low for longer let debris linger before vanishing.
+    pub fragment_fade_curve: fn(f64) -> f64,
+}
+
+/// An explosion waiting for its turn, from [`ExplosionParameters::maximum_per_tick`]
+#[derive(Clone, Copy, Debug)]
+pub struct PendingExplosion {
+    pub hit_position: Point2<f64>,
+    pub hit_velocity: Vector2<f64>,
+    pub seed: u64,
+}
+
+/// How much detail goes into explosions, for scaling down to slower hardware
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Quality {
+    /// A few sparks instead of fragments
+    Low,
+    /// Fragments from a downsampled texture, with a limited count
+    Medium,
+    /// Every fragment from the full resolution texture
+    #[default]
+    High,
+}
+
+impl Quality {
+    #[must_use]
+    pub fn maximum_fragments(self) -> Option<usize> {
+        match self {
+            Quality::Low => Some(0),
+            Quality::Medium => Some(16),
+            Quality::High => None,
+        }
+    }
+}
+
+impl Default for ExplosionParameters {
+    fn default() -> Self {
+        Self {
+            fragment_scale: 1.0,
+            quality: Quality::default(),
+            seeded: false,
+            maximum_per_tick: None,
+            fragment_fade_curve: tween::linear,
+        }
+    }
+}
+
+impl EnemyKind {
+    /// How many credits this enemy takes from a wave's budget. Tougher and faster enemies cost more.
+    #[must_use]
+    pub fn cost(&self) -> u32 {
+        let properties = &self.properties;
+
+        ((properties.maximum_health as f64 * (1.0 + properties.speed / 3.0) / 4.0).round() as u32)
+            .max(1)
+    }
+
+    /// Where this kind is in [`ENEMY_KINDS`], or `None` if it isn't one of them
+    #[must_use]
+    pub fn index(&self) -> Option<usize> {
+        ENEMY_KINDS.iter().position(|other| ptr::eq(other, self))
+    }
 }
 
 impl Enemy {
     pub const SLOWDOWN_TIME: f64 = 1.0 / 3.0;
+    /// How long after spawning an enemy can't be hit by projectiles
+    pub const SPAWN_IMMUNITY_TIME: f64 = 0.25;
+
+    pub const MEDIUM_QUALITY_DOWNSAMPLE_FACTOR: usize = 2;
+    pub const SPARK_COUNT: usize = 6;
+    pub const SPARK_SPEED: f64 = 4.0;
+    pub const SHRINK_FADE_TIME: f64 = 0.25;
+    /// How much health the turret loses when this enemy reaches it, before enraging
+    pub const CONTACT_DAMAGE: u32 = 1;
+    /// Angular velocity gained per unit of torque from an off-center hit
+    pub const SPIN_PER_TORQUE: f64 = 0.15;
+    /// How quickly spin from hits eases back to the kind's angular velocity
+    pub const SPIN_DECAY: f64 = 3.0;
+
+    pub const WARNING_COLOR: Color = Color::from_hex(0xff2020);
+    /// How much of the warning color shows through when a charge timer finishes
+    pub const WARNING_INTENSITY: f32 = 0.75;
+    pub const ENRAGE_COLOR: Color = Color::from_hex(0xff0000);
+    pub const SHATTER_COLOR: Color = Color::from_hex(0xa0a0ff);
+    pub const SHATTER_FLICKER_TICKS: u64 = 4;
+    pub const ENRAGE_INTENSITY: f32 = 0.5;
+    /// Shows through while the enemy is regenerating, in place of a health bar
+    pub const REGEN_COLOR: Color = Color::from_hex(0x40ff40);
+    pub const REGEN_INTENSITY: f32 = 0.35;
 
     pub fn new(position: Isometry2<f64>, kind: &EnemyKind) -> Self {
         Self {
+            name: kind.name.clone(),
+            kind_index: kind.index(),
+            spawn_index: 0,
             object: Object {
-                shape: kind.properties.shape,
+                shape: kind.properties.shape.scaled(kind.properties.hitbox_scale),
                 transform: Transform {
                     position,
                     linear_velocity: vector![0.0, 0.0], // managed each tick
@@ -124,18 +367,54 @@ impl Enemy {
                 },
             },
             direction: position.rotation,
+            current_speed: 0.0,
             properties: kind.properties,
+            behavior: kind.properties.behavior.map(|behavior| behavior()),
+            texture: utils::texture(kind.properties.texture),
             health: kind.properties.maximum_health,
+            time_since_spawn: 0.0,
             time_since_hit: f64::INFINITY,
+            last_hit_source: None,
+            last_hit_tick: 0,
+            partial_damage: 0.0,
+            partial_regen: 0.0,
             brightness: 0.0,
             brightness_update_time: 0.0,
+            charge_timer: None,
+            pending_explosion: None,
+            tether: None,
+            anchor: None,
+            leash_range: f64::INFINITY,
         }
     }
 
-    pub fn tick(&mut self, dt: f64) {
-        let speed = self.properties.speed * self.speed_multiplier();
-        self.object.linear_velocity = self.direction * vector![speed, 0.0];
+    pub fn tick(&mut self, context: &EnemyContext, rng: &mut Rng, dt: f64) {
+        if let Some(tether) = self.tether.take() {
+            self.object.linear_velocity = tether.apply(
+                self.position.translation.vector.into(),
+                self.linear_velocity,
+                dt,
+            );
+            self.current_speed = self
+                .linear_velocity
+                .dot(&(self.direction * vector![1.0, 0.0]));
+        } else if let Some(anchor) = self.anchor.filter(|&anchor| !self.is_within_leash(anchor)) {
+            let offset = anchor - Point2::from(self.position.translation.vector);
+            self.direction = UnitComplex::new(offset.y.atan2(offset.x));
+            self.march(dt);
+        } else if let Some(mut behavior) = self.behavior.take() {
+            behavior.update(self, context, dt);
+            self.behavior = Some(behavior);
+        } else {
+            self.march(dt);
+        }
 
+        self.object.angular_velocity = tween::exp_decay(
+            self.object.angular_velocity,
+            self.properties.angular_velocity,
+            Self::SPIN_DECAY,
+            dt,
+        );
         self.object.tick(dt);
 
         self.brightness_update_time += dt * 30.0;
@@ -143,20 +422,48 @@ impl Enemy {
         if self.brightness_update_time > 1.0 {
             self.brightness_update_time %= 1.0;
             self.brightness =
-                utils::next_flickering_brightness(self.brightness, self.speed_multiplier());
+                utils::next_flickering_brightness(self.brightness, self.speed_multiplier(), rng);
         }
 
+        self.time_since_spawn += dt;
         self.time_since_hit += dt;
+        self.regenerate(dt);
+
+        if let Some(charge_timer) = &mut self.charge_timer {
+            charge_timer.elapsed += dt;
+        }
+    }
+
+    /// Draws the enemy, using a smaller mip level of its texture if `pixels_per_unit` shows it
+    /// below its native size. A `pixels_per_unit` of zero always draws at full detail.
+    pub fn draw(&self, pixels_per_unit: f64) {
+        self.draw_tinted(pixels_per_unit, self.threat_tint());
+    }
+
+    /// Draws the enemy flickering to [`Enemy::SHATTER_COLOR`] while it waits for its explosion.
+    /// The game tick drives the flicker.
+    pub fn draw_shattering(&self, pixels_per_unit: f64, tick: u64) {
+        let tint = if (tick / Self::SHATTER_FLICKER_TICKS).is_multiple_of(2) {
+            Self::SHATTER_COLOR
+        } else {
+            colors::WHITE
+        };
+
+        self.draw_tinted(pixels_per_unit, tint);
     }
 
-    pub fn draw(&self) {
-        let size = self.properties.texture.size() * 0.1;
+    fn draw_tinted(&self, pixels_per_unit: f64, tint: Color) {
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        let size = texture.size() * 0.1;
 
         texture::draw_texture_ex(
-            &self.properties.texture,
+            texture.texture_for_width(size.x as f64 * pixels_per_unit),
             self.position.translation.x as f32 - size.x / 2.0,
             self.position.translation.y as f32 - size.y / 2.0,
-            utils::darken_color(colors::WHITE, self.brightness),
+            tint,
             DrawTextureParams {
                 dest_size: Some(size),
                 source: None,
@@ -168,18 +475,178 @@ impl Enemy {
         );
     }
 
+    /// The color the texture is drawn with, shifting towards [`Enemy::WARNING_COLOR`] as the charge
+    /// timer nears completion
+    #[must_use]
+    pub fn threat_tint(&self) -> Color {
+        let mut tint = utils::darken_color(colors::WHITE, self.brightness);
+
+        if self.is_enraged() {
+            tint = utils::color_lerp(tint, Self::ENRAGE_COLOR, Self::ENRAGE_INTENSITY);
+        }
+        if self.is_regenerating() {
+            tint = utils::color_lerp(tint, Self::REGEN_COLOR, Self::REGEN_INTENSITY);
+        }
+
+        match self.charge_timer {
+            Some(charge_timer) => utils::color_lerp(
+                tint,
+                Self::WARNING_COLOR,
+                charge_timer.progress() as f32 * Self::WARNING_INTENSITY,
+            ),
+            None => tint,
+        }
+    }
+
+    /// Leaves behind the enemy's death effect. `seed` is only used if the explosion parameters
+    /// ask for seeded explosions, and usually comes from [`Enemy::explosion_seed`]. If explosions
+    /// are limited per tick, the explosion is left pending for the game to run later.
+    pub fn die(
+        &mut self,
+        hit_position: Point2<f64>,
+        hit_velocity: Vector2<f64>,
+        explosion: &ExplosionParameters,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        seed: u64,
+        rng: &mut Rng,
+    ) {
+        match self.properties.death_effect {
+            DeathEffect::Explode if explosion.maximum_per_tick.is_some() => {
+                self.pending_explosion = Some(PendingExplosion {
+                    hit_position,
+                    hit_velocity,
+                    seed,
+                });
+            }
+            DeathEffect::Explode if explosion.seeded => {
+                self.explode_with_seed(hit_position, hit_velocity, explosion, particles, seed);
+            }
+            DeathEffect::Explode => {
+                self.explode(hit_position, hit_velocity, explosion, particles, rng);
+            }
+            DeathEffect::ShrinkFade => self.shrink_fade(particles),
+            DeathEffect::None => (),
+        }
+    }
+
+    /// Runs the explosion left pending by [`Enemy::die`], if there is one
+    pub fn run_pending_explosion(
+        &self,
+        explosion: &ExplosionParameters,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        rng: &mut Rng,
+    ) {
+        let Some(pending) = self.pending_explosion else {
+            return;
+        };
+
+        if explosion.seeded {
+            self.explode_with_seed(
+                pending.hit_position,
+                pending.hit_velocity,
+                explosion,
+                particles,
+                pending.seed,
+            );
+        } else {
+            self.explode(
+                pending.hit_position,
+                pending.hit_velocity,
+                explosion,
+                particles,
+                rng,
+            );
+        }
+    }
+
+    /// Leaves behind a copy of the enemy that keeps drifting while shrinking and fading out
+    pub fn shrink_fade(&self, particles: &mut HopSlotMap<ParticleKey, Particle>) {
+        let Some(texture) = self.texture.clone() else {
+            return;
+        };
+
+        let size = texture.pixel_size();
+
+        particles.insert(Particle {
+            transform: self.object.transform,
+            target_position: None,
+            color: colors::WHITE,
+            time_since_creation: 0.0,
+            maximum_lifetime: Self::SHRINK_FADE_TIME,
+            texture,
+            start: None,
+            size,
+            pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+            layer: ParticleLayer::AboveEntities,
+            shrink: true,
+            fade_curve: tween::linear,
+        });
+    }
+
+    /// A seed for the explosion of the enemy at `key` on game tick `tick`, which doesn't depend
+    /// on any other explosion
+    #[must_use]
+    pub fn explosion_seed(key: EnemyKey, tick: u64) -> u64 {
+        rng::mix(key.data().as_ffi(), tick)
+    }
+
+    /// Like [`Enemy::explode`], but with randomness drawn only from `seed`, so the same seed
+    /// always gives the same fragments
+    pub fn explode_with_seed(
+        &self,
+        hit_position: Point2<f64>,
+        hit_velocity: Vector2<f64>,
+        explosion: &ExplosionParameters,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        seed: u64,
+    ) {
+        self.explode(
+            hit_position,
+            hit_velocity,
+            explosion,
+            particles,
+            &mut Rng::new(seed),
+        );
+    }
+
+    /// Bursts the enemy into fragments of its texture, or sparks at low quality
     pub fn explode(
         &self,
         hit_position: Point2<f64>,
         hit_velocity: Vector2<f64>,
+        explosion: &ExplosionParameters,
         particles: &mut HopSlotMap<ParticleKey, Particle>,
+        rng: &mut Rng,
     ) {
         const RECTANGLE_WIDTH: Range<usize> = 4..8;
         const RECTANGLE_HEIGHT: Range<usize> = 4..8;
 
-        let size = self.properties.texture.pixel_size();
+        let Some(texture) = &self.texture else {
+            self.explode_into_sparks(hit_position, hit_velocity, particles, rng);
+            return;
+        };
+
+        let downsample_factor = match explosion.quality {
+            Quality::Low => {
+                self.explode_into_sparks(hit_position, hit_velocity, particles, rng);
+                return;
+            }
+            Quality::Medium => Self::MEDIUM_QUALITY_DOWNSAMPLE_FACTOR,
+            Quality::High => 1,
+        };
+
+        let source = if downsample_factor > 1 {
+            Cow::Owned(utils::downsample_image(&texture.image, downsample_factor))
+        } else {
+            Cow::Borrowed(&texture.image)
+        };
+        let source_pixel_scale = Particle::DEFAULT_PIXEL_SCALE * downsample_factor as f64;
+
+        let mut fragments_left = explosion.quality.maximum_fragments().unwrap_or(usize::MAX);
 
-        let mut num_valid_pixels = (self.properties.texture.image)
+        let size = vector![source.width(), source.height()];
+
+        let mut num_valid_pixels = source
             .get_image_data()
             .iter()
             .filter(|&&[_, _, _, opacity]| opacity > 0)
@@ -189,11 +656,11 @@ impl Enemy {
         let mut next_group_id = NonZeroUsize::new(1).unwrap();
 
         while num_valid_pixels > 0 {
-            let mut count = macroquad::rand::gen_range(1, num_valid_pixels);
+            let mut count = rng.gen_range(1, num_valid_pixels);
 
             let index = group_ids
                 .iter()
-                .zip(self.properties.texture.image.get_image_data())
+                .zip(source.get_image_data())
                 .take_while(|(group, [_, _, _, opacity])| {
                     if group.is_none() && *opacity > 0 {
                         count -= 1;
@@ -205,15 +672,15 @@ impl Enemy {
 
             let position = point![index % size.x, index / size.x];
 
-            for _ in 0..macroquad::rand::gen_range(1, 3) {
+            for _ in 0..rng.gen_range(1usize, 3) {
                 let rectangle_size = vector![
-                    macroquad::rand::gen_range(RECTANGLE_WIDTH.start, RECTANGLE_WIDTH.end),
-                    macroquad::rand::gen_range(RECTANGLE_HEIGHT.start, RECTANGLE_HEIGHT.end),
+                    rng.gen_range(RECTANGLE_WIDTH.start, RECTANGLE_WIDTH.end),
+                    rng.gen_range(RECTANGLE_HEIGHT.start, RECTANGLE_HEIGHT.end),
                 ];
 
                 let mut rectangle_offset = vector![
-                    macroquad::rand::gen_range(0, rectangle_size.x),
-                    macroquad::rand::gen_range(0, rectangle_size.y),
+                    rng.gen_range(0, rectangle_size.x),
+                    rng.gen_range(0, rectangle_size.y),
                 ];
 
                 if rectangle_offset.x > position.x {
@@ -230,18 +697,19 @@ impl Enemy {
                     rectangle_offset.y = rectangle_size.y;
                 }
 
+                // Textures too small for the rectangle, like downsampled ones, would still leave it
+                // hanging off an edge, so it's cut down to fit. This changes nothing otherwise.
+                let min = position - rectangle_offset.inf(&position.coords);
                 let bounding_box = BoundingBox {
-                    min: position - rectangle_offset,
-                    max: position - rectangle_offset + rectangle_size - vector![1, 1],
+                    min,
+                    max: (min + rectangle_size - vector![1, 1])
+                        .inf(&point![size.x - 1, size.y - 1]),
                 };
 
                 for x in bounding_box.min.x..bounding_box.max.x + 1 {
                     for y in bounding_box.min.y..bounding_box.max.y + 1 {
                         if group_ids[(x, y)].is_none()
-                            && (self.properties.texture.image)
-                                .get_pixel(x as u32, y as u32)
-                                .a
-                                > f32::EPSILON
+                            && source.get_pixel(x as u32, y as u32).a > f32::EPSILON
                         {
                             group_ids[(x, y)] = Some(next_group_id);
                             num_valid_pixels -= 1;
@@ -348,6 +816,10 @@ impl Enemy {
         }
 
         while let Some(key) = bounding_boxes.keys().next() {
+            if fragments_left == 0 {
+                break;
+            }
+
             let (bounding_box, _) = bounding_boxes.remove(key).unwrap();
 
             let mut texture_bounding_boxes = vec![(key, bounding_box)];
@@ -372,18 +844,22 @@ impl Enemy {
                         let i = x + y * size.x;
 
                         if group_keys[(x, y)] == Some(group) {
-                            image.get_image_data_mut()[i] =
-                                self.properties.texture.image.get_image_data()[i];
+                            image.get_image_data_mut()[i] = source.get_image_data()[i];
                         }
                     }
                 }
             }
 
-            let texture = Texture2D::from_image(&image);
-            texture.set_filter(FilterMode::Nearest);
+            let texture = Arc::new(TextureEntry::from_image(image));
 
             for (_, bounding_box) in texture_bounding_boxes {
-                let offset = 0.1 * (bounding_box.center() - size.map(|x| x as f64) / 2.0);
+                if fragments_left == 0 {
+                    break;
+                }
+                fragments_left -= 1;
+
+                let offset =
+                    source_pixel_scale * (bounding_box.center() - size.map(|x| x as f64) / 2.0);
 
                 let translation = self.position * offset;
 
@@ -397,7 +873,7 @@ impl Enemy {
                     transform: Transform {
                         position: Isometry2::from_parts(translation.into(), self.position.rotation),
                         linear_velocity: self.velocity_of_point(translation) - self.linear_velocity
-                            + additional_velocity * macroquad::rand::gen_range(0.5, 1.25),
+                            + additional_velocity * rng.gen_range(0.5, 1.25),
                         angular_velocity: self.angular_velocity,
                     },
                     target_position: None,
@@ -407,21 +883,186 @@ impl Enemy {
                     texture: texture.clone(),
                     start: Some(bounding_box.min),
                     size: bounding_box.size(),
+                    pixel_scale: source_pixel_scale * explosion.fragment_scale,
+                    layer: ParticleLayer::AboveEntities,
+                    shrink: false,
+                    fade_curve: explosion.fragment_fade_curve,
                 });
             }
         }
     }
 
+    /// A cheap replacement for [`Enemy::explode`] that emits a few untextured sparks
+    pub fn explode_into_sparks(
+        &self,
+        hit_position: Point2<f64>,
+        hit_velocity: Vector2<f64>,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        rng: &mut Rng,
+    ) {
+        let center = self.position.translation.vector;
+
+        for i in 0..Self::SPARK_COUNT {
+            let angle = (i as f64 + rng.gen_range(0.0, 1.0)) / Self::SPARK_COUNT as f64 * TAU;
+            let direction = UnitComplex::new(angle);
+
+            let displacement = center - hit_position.coords;
+            let distance_squared = displacement.magnitude_squared().clamp(0.5, 5.0);
+
+            particles.insert(Particle {
+                transform: Transform {
+                    position: Isometry2::from_parts(center.into(), direction),
+                    linear_velocity: direction * vector![Self::SPARK_SPEED, 0.0]
+                        + hit_velocity * 0.5 / distance_squared.sqrt(),
+                    angular_velocity: 0.0,
+                },
+                target_position: None,
+                color: colors::WHITE,
+                time_since_creation: 0.0,
+                maximum_lifetime: 0.5,
+                texture: GLITTER_TEXTURES[rng.gen_range(0, GLITTER_TEXTURES.len())].clone(),
+                start: None,
+                size: vector![2, 2],
+                pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+                layer: ParticleLayer::AboveEntities,
+                shrink: false,
+                fade_curve: tween::linear,
+            });
+        }
+    }
+
+    /// Eases the current speed towards the enemy's target speed, and sets the velocity to move
+    /// forwards in `direction` at that speed
+    pub fn march(&mut self, dt: f64) {
+        self.current_speed = tween::exp_decay(
+            self.current_speed,
+            self.target_speed(),
+            self.properties.acceleration,
+            dt,
+        );
+        self.object.linear_velocity = self.direction * vector![self.current_speed, 0.0];
+    }
+
+    #[must_use]
+    pub fn target_speed(&self) -> f64 {
+        self.properties.speed * self.speed_multiplier() * self.enrage_multiplier()
+    }
+
+    /// Whether the enemy has lived past its `enrage_after` time
+    #[must_use]
+    pub fn is_enraged(&self) -> bool {
+        self.properties
+            .enrage_after
+            .is_some_and(|enrage_after| self.time_since_spawn >= enrage_after)
+    }
+
+    /// Multiplies the enemy's speed and any damage it deals
+    #[must_use]
+    pub fn enrage_multiplier(&self) -> f64 {
+        if self.is_enraged() {
+            self.properties.enrage_multiplier
+        } else {
+            1.0
+        }
+    }
+
+    /// A seed that depends only on where the enemy is and how it's moving, so that identical
+    /// enemies get identical seeds wherever they're stored
+    #[must_use]
+    pub fn state_seed(&self) -> u64 {
+        let position = self.position.translation.vector;
+        let velocity = self.linear_velocity;
+
+        [
+            position.y,
+            self.position.rotation.angle(),
+            velocity.x,
+            velocity.y,
+        ]
+        .into_iter()
+        .fold(position.x.to_bits(), |seed, value| {
+            rng::mix(seed, value.to_bits())
+        })
+    }
+
+    /// How much health the turret loses when this enemy reaches it
+    #[must_use]
+    pub fn contact_damage(&self) -> u32 {
+        (Self::CONTACT_DAMAGE as f64 * self.enrage_multiplier()).round() as u32
+    }
+
     pub fn speed_multiplier(&self) -> f64 {
         (self.time_since_hit / Self::SLOWDOWN_TIME).min(1.0)
     }
 
-    pub fn hit(&mut self, damage: u32) {
+    pub fn hit(&mut self, damage: u32, source: Option<SourceId>, tick: u64) {
         self.health = self.health.saturating_sub(damage);
+        self.last_hit_source = source;
+        self.last_hit_tick = tick;
         self.time_since_hit = 0.0;
+        self.partial_regen = 0.0;
         self.brightness_update_time = 1.0;
     }
 
+    /// Spins rectangular enemies when they're hit off-center, in proportion to the torque of
+    /// `velocity` acting at `contact`. Other shapes look the same at any angle, so they don't spin.
+    pub fn spin_from_hit(&mut self, contact: Point2<f64>, velocity: Vector2<f64>) {
+        if !matches!(self.object.shape, Shape::Rectangle { .. }) {
+            return;
+        }
+
+        let offset = contact - Point2::from(self.position.translation.vector);
+        let torque = offset.perp(&velocity);
+
+        self.object.angular_velocity += torque * Self::SPIN_PER_TORQUE;
+    }
+
+    #[must_use]
+    pub fn is_within_leash(&self, anchor: Point2<f64>) -> bool {
+        (Point2::from(self.position.translation.vector) - anchor).norm() <= self.leash_range
+    }
+
+    #[must_use]
+    pub fn is_immune(&self) -> bool {
+        self.time_since_spawn < Self::SPAWN_IMMUNITY_TIME
+    }
+
+    #[must_use]
+    pub fn is_regenerating(&self) -> bool {
+        self.properties.regen_rate > 0.0
+            && self.time_since_hit >= self.properties.regen_delay
+            && self.health < self.properties.maximum_health
+            && !self.should_delete()
+    }
+
+    /// Heals towards the maximum health once the enemy has gone long enough without being hit
+    pub fn regenerate(&mut self, dt: f64) {
+        if !self.is_regenerating() {
+            return;
+        }
+
+        self.partial_regen += self.properties.regen_rate * dt;
+
+        if self.partial_regen >= 1.0 {
+            let health = self.partial_regen.floor();
+            self.partial_regen -= health;
+
+            self.health = (self.health + health as u32).min(self.properties.maximum_health);
+        }
+    }
+
+    /// Applies fractional damage, only counting as a hit once a whole point has built up
+    pub fn damage_over_time(&mut self, damage: f64, source: Option<SourceId>, tick: u64) {
+        self.partial_damage += damage;
+
+        if self.partial_damage >= 1.0 {
+            let damage = self.partial_damage.floor();
+            self.partial_damage -= damage;
+
+            self.hit(damage as u32, source, tick);
+        }
+    }
+
     pub fn should_delete(&self) -> bool {
         self.health == 0
     }
@@ -440,3 +1081,533 @@ impl DerefMut for Enemy {
         &mut self.object
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use nalgebra::{Isometry2, point, vector};
+
+    use super::*;
+    use crate::{
+        game::Game,
+        projectile::{PROJECTILE_KINDS, Projectile},
+    };
+
+    fn explosion(fragment_scale: f64) -> ExplosionParameters {
+        ExplosionParameters {
+            fragment_scale,
+            ..ExplosionParameters::default()
+        }
+    }
+
+    /// The fragments of a purple square at `(3, -2)` exploding with a fixed seed
+    fn fragments(fragment_scale: f64) -> Vec<Particle> {
+        let enemy = Enemy::new(Isometry2::new(vector![3.0, -2.0], 0.5), &ENEMY_KINDS[4]);
+        let mut particles = HopSlotMap::with_key();
+
+        enemy.explode_with_seed(
+            point![3.0, -2.0],
+            vector![0.0, 0.0],
+            &explosion(fragment_scale),
+            &mut particles,
+            42,
+        );
+
+        particles
+            .into_iter()
+            .map(|(_, particle)| particle)
+            .collect()
+    }
+
+    #[test]
+    fn fragment_scale_shrinks_fragments_in_place() {
+        let full = fragments(1.0);
+        let half = fragments(0.5);
+
+        assert!(!full.is_empty());
+        assert_eq!(full.len(), half.len());
+
+        let center = vector![3.0, -2.0];
+
+        for (full, half) in full.iter().zip(&half) {
+            assert_eq!(full.size, half.size);
+            assert!((half.pixel_scale - full.pixel_scale * 0.5).abs() < 1e-12);
+
+            assert_eq!(full.position, half.position);
+        }
+
+        for fragments in [&full, &half] {
+            let mean = fragments
+                .iter()
+                .map(|particle| particle.position.translation.vector)
+                .sum::<Vector2<f64>>()
+                / fragments.len() as f64;
+
+            assert!((mean - center).norm() < 0.25);
+        }
+    }
+
+    fn explode(kind: &EnemyKind, quality: Quality, seed: u64) -> Vec<Particle> {
+        let enemy = Enemy::new(Isometry2::identity(), kind);
+        let mut particles = HopSlotMap::with_key();
+
+        enemy.explode_with_seed(
+            Point2::origin(),
+            vector![0.0, 0.0],
+            &ExplosionParameters {
+                quality,
+                ..ExplosionParameters::default()
+            },
+            &mut particles,
+            seed,
+        );
+
+        particles
+            .into_iter()
+            .map(|(_, particle)| particle)
+            .collect()
+    }
+
+    #[test]
+    fn every_kind_explodes_at_every_quality() {
+        for kind in ENEMY_KINDS.iter() {
+            for seed in 0..64 {
+                let low = explode(kind, Quality::Low, seed);
+                let medium = explode(kind, Quality::Medium, seed);
+                let high = explode(kind, Quality::High, seed);
+
+                assert_eq!(low.len(), Enemy::SPARK_COUNT);
+                assert!(low.iter().all(|particle| particle.start.is_none()));
+                assert!(low.iter().all(|particle| is_glitter(&particle.texture)));
+
+                assert!(medium.len() <= Quality::Medium.maximum_fragments().unwrap());
+                assert!(high.iter().all(|particle| particle.start.is_some()));
+            }
+        }
+    }
+
+    fn is_glitter(texture: &Arc<TextureEntry>) -> bool {
+        GLITTER_TEXTURES
+            .iter()
+            .any(|glitter| Arc::ptr_eq(glitter, texture))
+    }
+
+    #[test]
+    fn low_quality_makes_far_fewer_particles() {
+        let kind = &ENEMY_KINDS[4];
+
+        let (low, high): (Vec<_>, Vec<_>) = (0..16)
+            .map(|seed| {
+                let low = explode(kind, Quality::Low, seed).len();
+                let high = explode(kind, Quality::High, seed).len();
+
+                assert!(high > 2 * low, "{high} fragments vs {low} sparks");
+                (low, high)
+            })
+            .unzip();
+
+        assert!(high.iter().sum::<usize>() > 3 * low.iter().sum::<usize>());
+    }
+
+    #[test]
+    fn high_quality_fragments_cover_every_opaque_pixel_once() {
+        for kind in ENEMY_KINDS.iter() {
+            let source = &utils::texture(kind.properties.texture).unwrap().image;
+            let opaque = |image: &Image, x, y| image.get_pixel(x as u32, y as u32).a > 0.0;
+
+            for seed in 0..16 {
+                let mut covered = DMatrix::from_element(source.width(), source.height(), 0);
+
+                for fragment in explode(kind, Quality::High, seed) {
+                    assert_eq!(fragment.pixel_scale, Particle::DEFAULT_PIXEL_SCALE);
+
+                    let start = fragment.start.unwrap();
+                    for x in start.x..start.x + fragment.size.x {
+                        for y in start.y..start.y + fragment.size.y {
+                            if opaque(&fragment.texture.image, x, y) {
+                                covered[(x, y)] += 1;
+                            }
+                        }
+                    }
+                }
+
+                for x in 0..source.width() {
+                    for y in 0..source.height() {
+                        let expected = usize::from(opaque(source, x, y));
+                        assert_eq!(covered[(x, y)], expected, "{} at ({x}, {y})", kind.name);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn textures_are_resolved_when_enemies_are_created() {
+        let enemy = Enemy::new(Isometry2::identity(), &ENEMY_KINDS[1]);
+        let registered = utils::texture(ENEMY_KINDS[1].properties.texture).unwrap();
+
+        assert!(Arc::ptr_eq(enemy.texture.as_ref().unwrap(), &registered));
+
+        let mut kind = ENEMY_KINDS[0].clone();
+        kind.properties.texture = TextureId(u32::MAX);
+        let untextured = Enemy::new(Isometry2::identity(), &kind);
+        assert!(untextured.texture.is_none());
+
+        let mut particles = HopSlotMap::default();
+        untextured.explode(
+            point![0.0, 0.0],
+            vector![0.0, 0.0],
+            &ExplosionParameters::default(),
+            &mut particles,
+            &mut Rng::new(1),
+        );
+        assert_eq!(particles.len(), Enemy::SPARK_COUNT);
+    }
+
+    /// Fires a classic projectile up past a red circle `0.45` from its center, returning whether
+    /// the circle got hurt or killed
+    fn grazing_shot_hits(hitbox_scale: f64) -> bool {
+        let mut kind = ENEMY_KINDS[0].clone();
+        kind.properties.hitbox_scale = hitbox_scale;
+
+        let mut game = Game::with_seed(15);
+        let key = game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2), &kind)
+        });
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.45, 2.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+
+        let camera_bounds = Shape::Rectangle {
+            half_size: vector![20.0, 20.0],
+        };
+        for _ in 0..30 {
+            game.tick_with_input(point![0.0, 10.0], camera_bounds, 1.0 / 120.0);
+        }
+
+        (game.enemies.get(key)).is_none_or(|enemy| enemy.health < kind.properties.maximum_health)
+    }
+
+    #[test]
+    fn smaller_hitboxes_miss_shots_that_graze_the_sprite() {
+        assert!(grazing_shot_hits(1.0));
+        assert!(!grazing_shot_hits(0.5));
+    }
+
+    fn distance_to_warning(color: Color) -> f32 {
+        let warning = Enemy::WARNING_COLOR;
+
+        [
+            color.r - warning.r,
+            color.g - warning.g,
+            color.b - warning.b,
+        ]
+        .iter()
+        .map(|channel| channel * channel)
+        .sum::<f32>()
+        .sqrt()
+    }
+
+    #[test]
+    fn threat_tint_nears_the_warning_color_as_the_charge_completes() {
+        let enemy = Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0]);
+        let charged = |elapsed| Enemy {
+            charge_timer: Some(ChargeTimer {
+                elapsed,
+                ..ChargeTimer::new(2.0)
+            }),
+            ..enemy.clone()
+        };
+
+        assert_eq!(
+            enemy.threat_tint(),
+            utils::darken_color(colors::WHITE, enemy.brightness)
+        );
+        assert!(
+            distance_to_warning(charged(1.8).threat_tint())
+                < distance_to_warning(charged(0.2).threat_tint())
+        );
+    }
+
+    #[test]
+    fn fresh_enemies_ramp_up_to_speed() {
+        let mut enemy = Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0])
+        };
+        let target = enemy.target_speed();
+        let context = EnemyContext::default();
+        let mut rng = Rng::new(20);
+
+        let mut speeds = Vec::new();
+        for _ in 0..120 {
+            enemy.tick(&context, &mut rng, 1.0 / 120.0);
+            speeds.push(enemy.linear_velocity.magnitude());
+        }
+
+        assert!(speeds[0] > 0.0 && speeds[0] < target * 0.2);
+        assert!(speeds[..10].windows(2).all(|pair| pair[1] > pair[0]));
+        assert!(speeds[9] < target);
+        assert!((speeds.last().unwrap() - target).abs() < target * 1e-3);
+
+        let mut sluggish = Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0])
+        };
+        sluggish.properties.acceleration = 1.0;
+        sluggish.tick(&context, &mut rng, 1.0 / 120.0);
+        assert!(sluggish.linear_velocity.magnitude() < speeds[0]);
+    }
+
+    /// A purple square that regenerates after a second, hit down to half health, then left for
+    /// three seconds while being hit again every `hit_interval` ticks
+    fn health_after_regenerating(hit_interval: Option<usize>) -> u32 {
+        let mut enemy = Enemy::new(Isometry2::identity(), &ENEMY_KINDS[4]);
+        enemy.properties.regen_rate = 4.0;
+        enemy.properties.regen_delay = 1.0;
+        enemy.hit(enemy.properties.maximum_health / 2, None, 0);
+
+        let context = EnemyContext::default();
+        let mut rng = Rng::new(23);
+        for tick in 0..360 {
+            if hit_interval.is_some_and(|interval| tick % interval == 0) {
+                enemy.hit(0, None, tick as u64);
+            }
+
+            enemy.tick(&context, &mut rng, 1.0 / 120.0);
+        }
+
+        enemy.health
+    }
+
+    #[test]
+    fn enemies_regenerate_only_once_left_alone() {
+        let maximum_health = ENEMY_KINDS[4].properties.maximum_health;
+        let damaged = maximum_health / 2;
+
+        let healed = health_after_regenerating(None);
+        assert!(healed > damaged);
+        assert!(healed <= maximum_health);
+
+        assert_eq!(health_after_regenerating(Some(60)), damaged);
+    }
+
+    #[test]
+    fn regenerating_enemies_are_tinted() {
+        let mut enemy = Enemy::new(Isometry2::identity(), &ENEMY_KINDS[4]);
+        enemy.properties.regen_rate = 4.0;
+        enemy.properties.regen_delay = 1.0;
+        let untinted = enemy.threat_tint();
+
+        enemy.hit(1, None, 0);
+        assert!(!enemy.is_regenerating());
+        assert_eq!(enemy.threat_tint(), untinted);
+
+        enemy.time_since_hit = 1.0;
+        assert!(enemy.is_regenerating());
+        assert_eq!(
+            enemy.threat_tint(),
+            utils::color_lerp(untinted, Enemy::REGEN_COLOR, Enemy::REGEN_INTENSITY)
+        );
+    }
+
+    #[test]
+    fn leashed_enemies_turn_back_towards_their_anchor() {
+        let anchor = point![0.0, 0.0];
+        let mut enemy = Enemy {
+            time_since_spawn: 1.0,
+            anchor: Some(anchor),
+            leash_range: 3.0,
+            ..Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0])
+        };
+        let context = EnemyContext::default();
+        let mut rng = Rng::new(24);
+
+        let mut turned_back = false;
+        let mut furthest: f64 = 0.0;
+        for _ in 0..600 {
+            let strayed = !enemy.is_within_leash(anchor);
+            enemy.tick(&context, &mut rng, 1.0 / 120.0);
+
+            let position = Point2::from(enemy.position.translation.vector);
+            furthest = furthest.max((position - anchor).norm());
+            if strayed {
+                let heading = enemy.direction * vector![1.0, 0.0];
+                assert!(heading.dot(&(anchor - position).normalize()) > 0.99);
+                turned_back = true;
+            }
+        }
+
+        assert!(turned_back);
+        assert!(furthest < 4.0, "{furthest}");
+
+        let mut unleashed = Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0])
+        };
+        for _ in 0..600 {
+            unleashed.tick(&context, &mut rng, 1.0 / 120.0);
+        }
+        assert!(unleashed.position.translation.vector.norm() > 4.0);
+    }
+
+    fn particles_left_by(death_effect: DeathEffect) -> Vec<Particle> {
+        let mut enemy = Enemy::new(Isometry2::new(vector![3.0, -2.0], 0.5), &ENEMY_KINDS[4]);
+        enemy.properties.death_effect = death_effect;
+        let mut particles = HopSlotMap::with_key();
+
+        enemy.die(
+            point![3.0, -2.0],
+            vector![5.0, 0.0],
+            &ExplosionParameters::default(),
+            &mut particles,
+            7,
+            &mut Rng::new(25),
+        );
+
+        particles
+            .into_iter()
+            .map(|(_, particle)| particle)
+            .collect()
+    }
+
+    #[test]
+    fn only_exploding_enemies_break_into_fragments() {
+        let is_fragment = |particle: &Particle| particle.start.is_some();
+
+        let exploded = particles_left_by(DeathEffect::Explode);
+        assert!(
+            exploded
+                .iter()
+                .filter(|particle| is_fragment(particle))
+                .count()
+                > 1
+        );
+
+        let faded = particles_left_by(DeathEffect::ShrinkFade);
+        assert_eq!(faded.len(), 1);
+        assert!(!is_fragment(&faded[0]));
+        assert!(faded[0].shrink);
+
+        assert!(particles_left_by(DeathEffect::None).is_empty());
+    }
+
+    #[test]
+    fn enemies_speed_up_and_redden_once_enraged() {
+        let enemy_alive_for = |time_since_spawn| {
+            let mut enemy = Enemy {
+                time_since_spawn,
+                ..Enemy::new(Isometry2::identity(), &ENEMY_KINDS[0])
+            };
+            enemy.properties.enrage_after = Some(5.0);
+            enemy.properties.enrage_multiplier = 2.0;
+
+            let context = EnemyContext::default();
+            let mut rng = Rng::new(26);
+            for _ in 0..120 {
+                enemy.tick(&context, &mut rng, 1.0 / 120.0);
+            }
+
+            enemy
+        };
+        let calm = enemy_alive_for(3.5);
+        let enraged = enemy_alive_for(6.0);
+        let speed = ENEMY_KINDS[0].properties.speed;
+
+        assert!(!calm.is_enraged());
+        assert!((calm.linear_velocity.norm() - speed).abs() < speed * 1e-3);
+        assert_eq!(
+            calm.threat_tint(),
+            utils::darken_color(colors::WHITE, calm.brightness)
+        );
+        assert_eq!(calm.contact_damage(), Enemy::CONTACT_DAMAGE);
+
+        assert!(enraged.is_enraged());
+        assert!((enraged.linear_velocity.norm() - speed * 2.0).abs() < speed * 1e-3);
+        assert_eq!(
+            enraged.threat_tint(),
+            utils::color_lerp(
+                utils::darken_color(colors::WHITE, enraged.brightness),
+                Enemy::ENRAGE_COLOR,
+                Enemy::ENRAGE_INTENSITY,
+            )
+        );
+        assert_eq!(enraged.contact_damage(), Enemy::CONTACT_DAMAGE * 2);
+    }
+
+    #[test]
+    fn seeded_explosions_ignore_other_random_draws() {
+        let describe = |particles: Vec<Particle>| format!("{particles:?}");
+        let alone = describe(explode(&ENEMY_KINDS[4], Quality::High, 7));
+
+        let mut shared = Rng::new(3);
+        for _ in 0..100 {
+            shared.gen_range(0.0, 1.0);
+        }
+        let mut particles = HopSlotMap::with_key();
+        Enemy::new(Isometry2::identity(), &ENEMY_KINDS[3]).explode(
+            Point2::origin(),
+            vector![1.0, 0.0],
+            &ExplosionParameters::default(),
+            &mut particles,
+            &mut shared,
+        );
+
+        assert_eq!(describe(explode(&ENEMY_KINDS[4], Quality::High, 7)), alone);
+        assert_ne!(describe(explode(&ENEMY_KINDS[4], Quality::High, 8)), alone);
+
+        let mut keys = HopSlotMap::<EnemyKey, ()>::with_key();
+        let (first, second) = (keys.insert(()), keys.insert(()));
+        assert_eq!(
+            Enemy::explosion_seed(first, 10),
+            Enemy::explosion_seed(first, 10)
+        );
+        assert_ne!(
+            Enemy::explosion_seed(first, 10),
+            Enemy::explosion_seed(second, 10)
+        );
+        assert_ne!(
+            Enemy::explosion_seed(first, 10),
+            Enemy::explosion_seed(first, 11)
+        );
+    }
+
+    #[test]
+    fn fragments_fade_along_the_chosen_curve() {
+        let opacities = |fade_curve: fn(f64) -> f64| {
+            let enemy = Enemy::new(Isometry2::identity(), &ENEMY_KINDS[3]);
+            let mut particles = HopSlotMap::with_key();
+            enemy.explode_with_seed(
+                Point2::origin(),
+                vector![0.0, 0.0],
+                &ExplosionParameters {
+                    fragment_fade_curve: fade_curve,
+                    ..ExplosionParameters::default()
+                },
+                &mut particles,
+                5,
+            );
+
+            let mut fragment = particles.into_iter().next().unwrap().1;
+            [0.0, 0.25, 0.5, 0.7, 0.9, 1.0].map(|progress| {
+                fragment.time_since_creation = progress * fragment.maximum_lifetime;
+                fragment.opacity()
+            })
+        };
+        let assert_close = |actual: [f64; 6], expected: [f64; 6]| {
+            for (actual, expected) in actual.into_iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+            }
+        };
+
+        assert_close(opacities(tween::linear), [1.0, 0.75, 0.5, 0.3, 0.1, 0.0]);
+        assert_close(
+            opacities(tween::hold_then_linear),
+            [1.0, 1.0, 1.0, 0.75, 0.25, 0.0],
+        );
+    }
+}
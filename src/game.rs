@@ -1,63 +1,3045 @@
-use macroquad::camera::Camera2D;
-use slotmap::{HopSlotMap, new_key_type};
+use std::f64::consts::TAU;
 
-use crate::{enemy::Enemy, particle::Particle, projectile::Projectile, turret::Turret, utils};
+use macroquad::{
+    camera::Camera2D,
+    color::{Color, colors},
+    input,
+    texture::{Image, RenderTarget},
+};
+use nalgebra::{Complex, Isometry2, Point2, UnitComplex, Vector2, vector};
+use slotmap::{HopSlotMap, Key, new_key_type};
 
-#[derive(Debug, Default)]
+use crate::{
+    arena::WrapArena,
+    behavior::EnemyContext,
+    collision,
+    enemy::{ENEMY_KINDS, Enemy, EnemyKind, ExplosionParameters, Quality},
+    ghost::{GhostPlayer, GhostRecording},
+    indicator::DamageIndicator,
+    object::{Object, Transform},
+    particle::{Particle, ParticleLayer},
+    projectile::{Faction, Projectile, ProjectileContext, ProjectileKind, SourceId},
+    rng::{self, Rng},
+    score::Score,
+    shape::Shape,
+    snapshot::{self, SnapshotError},
+    spatial::SpatialGrid,
+    spawn::{AutoSpawner, SpawnQueue},
+    spawner::Spawner,
+    stats::{RunStats, RunSummary},
+    trail::TrailZone,
+    turret::{Parry, ReticleSnapping, Turret, VirtualCursor},
+    tween,
+    utils::{self, BoundingBox, SpawnOrder},
+};
+
+#[derive(Debug)]
 pub struct Game {
     pub enemies: HopSlotMap<EnemyKey, Enemy>,
     pub projectiles: HopSlotMap<ProjectileKey, Projectile>,
     pub particles: HopSlotMap<ParticleKey, Particle>,
-    pub turret: Turret,
+    pub trail_zones: Vec<TrailZone>,
+    /// Every turret in the game. Add them with [`Game::add_turret`], so that each gets a source
+    /// id of its own.
+    pub turrets: HopSlotMap<TurretKey, Turret>,
+    /// The player's turret, see [`Game::primary_turret`]
+    pub primary_turret_key: TurretKey,
+    /// The id [`Game::allocate_source_id`] hands out next
+    pub next_source_id: u32,
+    /// Numbers entities added with [`Game::insert_enemy`] and [`Game::insert_projectile`], and by
+    /// spawners and turrets, for [`Game::stable_order`]
+    pub spawn_order: SpawnOrder,
+    /// How many more hits from enemies the turret can take before the game is over
+    pub health: u32,
+    pub state: GameState,
+    /// Where this game sits in the world, so that several can share one world. The turret starts
+    /// here, spawns are placed relative to it, and camera bounds are centered on it.
+    pub origin: Isometry2<f64>,
+    /// Aims with the arrow keys instead of the mouse when set
+    pub virtual_cursor: Option<VirtualCursor>,
+    /// Snaps the aim point onto enemies or a grid when set
+    pub reticle: Option<ReticleSnapping>,
+    pub explosion: ExplosionParameters,
+    /// Dead enemies waiting for their explosions, when explosions are limited per tick
+    pub explosion_queue: Vec<Enemy>,
+    /// Finds the enemies near each projectile and region query, rebuilt before projectiles move and
+    /// again at the end of every tick. Its cell size can be tuned. Enemies added, moved or removed
+    /// directly between ticks need `grid.stale` set, or region queries won't see the change.
+    pub grid: SpatialGrid,
+    pub spawn_queue: SpawnQueue,
+    /// Keeps spawning enemies on its own when set
+    pub auto_spawner: Option<AutoSpawner>,
+    /// Spawns a fixed list of waves when set. Its waves count as the game's waves.
+    pub spawner: Option<Spawner>,
+    pub score: Score,
+    pub stats: RunStats,
+    pub damage_indicators: Vec<DamageIndicator>,
+    /// Whether projectiles fired by players can damage turrets
+    pub friendly_fire: bool,
+    /// Prevents the turret from taking damage, for testing
+    pub invincible: bool,
+    /// Resolves collisions in the order entities were created in rather than storage order, so
+    /// that the outcome doesn't depend on how their keys were reused. This costs a sort of every
+    /// enemy and projectile key each tick.
+    pub stable_order: bool,
+    /// Makes enemy updates independent of the order enemies are stored and updated in, for when
+    /// they interact. Enemies are updated in spawn order, each sees the others as they were at the
+    /// start of the tick, and each draws from its own random number generator seeded from its
+    /// state. This costs a sort of every enemy key and of the position snapshot each tick, plus
+    /// seeding a generator per enemy.
+    pub double_buffered_enemies: bool,
+    /// How many times per tick to push overlapping circular enemies apart. More passes settle
+    /// crowds faster but cost a check of every pair of circles each time.
+    pub separation_iterations: usize,
+    /// Makes enemies, and optionally projectiles, wrap around the edges of the arena
+    pub arena: Option<WrapArena>,
+
+    pub tick_count: u64,
+    /// What `rng` started from, to record alongside the run
+    pub seed: u64,
+    /// Every random choice that affects gameplay comes from here, so that games started from the
+    /// same seed with the same input play out the same
+    pub rng: Rng,
+    /// Randomness that only changes how things look, like particle placement, textures and
+    /// lifetimes. It's a separate stream so that cosmetic changes don't change gameplay, and the
+    /// other way around.
+    pub cosmetic_rng: Rng,
+    pub ghost_recording: Option<GhostRecording>,
+    pub ghost_player: Option<GhostPlayer>,
+
+    /// How many screen pixels a world unit covered as of the last [`Game::tick`], for picking
+    /// texture detail. Zero draws everything at full detail.
+    pub pixels_per_unit: f64,
+
+    /// Things that happened during recent ticks, for the UI to react to with
+    /// [`Game::drain_events`]
+    pub events: Vec<GameEvent>,
+
+    /// The color tinting the whole screen after heavy hits, fading out over time
+    pub flash: Color,
+    /// Accessibility option that turns off full screen flashes
+    pub reduced_flashing: bool,
+    /// Readability option that colors projectiles by how much damage they deal, instead of all
+    /// cyan
+    pub tint_projectiles_by_damage: bool,
+    /// Graphics option that draws soft drop shadows under enemies and the turret
+    pub shadows: bool,
+    pub last_wave_perfect: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GameState {
+    #[default]
+    Playing,
+    /// The turret has run out of health. Only particles keep moving, so the last explosions can
+    /// play out.
+    GameOver,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    WaveCleared { perfect: bool, bonus: u64 },
+    GameOver,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        let mut game = Self {
+            enemies: Default::default(),
+            projectiles: Default::default(),
+            particles: Default::default(),
+            trail_zones: Default::default(),
+            turrets: Default::default(),
+            primary_turret_key: TurretKey::null(),
+            next_source_id: 1,
+            spawn_order: Default::default(),
+            health: Self::STARTING_HEALTH,
+            state: Default::default(),
+            origin: Default::default(),
+            virtual_cursor: Default::default(),
+            reticle: Default::default(),
+            explosion: Default::default(),
+            explosion_queue: Default::default(),
+            grid: Default::default(),
+            spawn_queue: Default::default(),
+            auto_spawner: Default::default(),
+            spawner: Default::default(),
+            score: Default::default(),
+            stats: Default::default(),
+            damage_indicators: Default::default(),
+            friendly_fire: Default::default(),
+            invincible: Default::default(),
+            stable_order: Default::default(),
+            double_buffered_enemies: Default::default(),
+            separation_iterations: Self::DEFAULT_SEPARATION_ITERATIONS,
+            arena: Default::default(),
+            tick_count: Default::default(),
+            seed: 0,
+            rng: Rng::new(0),
+            cosmetic_rng: Rng::new(rng::mix(0, Self::COSMETIC_STREAM)),
+            ghost_recording: Default::default(),
+            ghost_player: Default::default(),
+            pixels_per_unit: Default::default(),
+            events: Default::default(),
+            flash: Default::default(),
+            reduced_flashing: Default::default(),
+            tint_projectiles_by_damage: Default::default(),
+            shadows: Default::default(),
+            last_wave_perfect: Default::default(),
+        };
+
+        game.primary_turret_key = game.add_turret(Turret::default());
+
+        game
+    }
+}
+
+/// What counts as an enemy being inside a region, for region queries like
+/// [`Game::count_enemies_in_circle`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RegionMembership {
+    /// Any part of the enemy's shape overlaps the region
+    #[default]
+    Shape,
+    /// The enemy's center is in the region, which is cheaper to check
+    Center,
+}
+
+/// A drop shadow to draw under an entity, from [`Game::shadow_primitives`]
+#[derive(Clone, Copy, Debug)]
+pub struct Shadow {
+    /// The entity's footprint, scaled by the shadow's pulse
+    pub shape: Shape,
+    pub position: Isometry2<f64>,
+}
+
+impl Shadow {
+    pub const COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.35);
+    /// How far shadows are cast from what casts them
+    pub const OFFSET: Vector2<f64> = vector![0.15, -0.15];
+    /// How much shadows grow and shrink as they pulse, relative to their size
+    pub const PULSE_AMOUNT: f64 = 0.05;
+    /// Radians per second
+    pub const PULSE_RATE: f64 = 2.0;
+
+    /// A shadow for `shape` at `position`, pulsing `phase` radians ahead of others at `time`
+    #[must_use]
+    pub fn new(shape: Shape, position: Isometry2<f64>, time: f64, phase: f64) -> Self {
+        let scale = 1.0 + (time * Self::PULSE_RATE + phase).sin() * Self::PULSE_AMOUNT;
+
+        Self {
+            shape: shape.scaled(scale),
+            position: Isometry2::from_parts(
+                (position.translation.vector + Self::OFFSET).into(),
+                position.rotation,
+            ),
+        }
+    }
+
+    pub fn draw(&self) {
+        self.shape.draw_filled(self.position, Self::COLOR);
+    }
+}
+
+/// Input for one tick of [`Game::simulate`]
+#[derive(Clone, Copy, Debug)]
+pub struct InputState {
+    pub mouse_position: Point2<f64>,
+    pub shoot: bool,
+    /// Whether the parry key was pressed this tick
+    pub parry: bool,
+    pub camera_bounds: Shape,
+}
+
+/// What a shot would do if it were fired now, from [`Game::preview_shot`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShotPreview {
+    /// Every enemy the shot would hit, in order
+    pub hits: Vec<PreviewHit>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PreviewHit {
+    pub enemy: EntityId,
+    pub damage: u32,
+    pub kills: bool,
+}
+
+impl ShotPreview {
+    pub fn kills(&self) -> impl Iterator<Item = EntityId> {
+        self.hits
+            .iter()
+            .filter(|hit| hit.kills)
+            .map(|hit| hit.enemy)
+    }
 }
 
 new_key_type! {
     pub struct EnemyKey;
     pub struct ProjectileKey;
     pub struct ParticleKey;
+    pub struct TurretKey;
+}
+
+/// Refers to any entity in a [`Game`], whichever map it's stored in. An id whose entity has been
+/// removed resolves to `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntityId {
+    Enemy(EnemyKey),
+    Projectile(ProjectileKey),
+    Particle(ParticleKey),
+}
+
+impl From<EnemyKey> for EntityId {
+    fn from(key: EnemyKey) -> Self {
+        Self::Enemy(key)
+    }
+}
+
+impl From<ProjectileKey> for EntityId {
+    fn from(key: ProjectileKey) -> Self {
+        Self::Projectile(key)
+    }
+}
+
+impl From<ParticleKey> for EntityId {
+    fn from(key: ParticleKey) -> Self {
+        Self::Particle(key)
+    }
 }
 
 impl Game {
+    /// The longest step the simulation will take at once. Longer frames (from a debugger
+    /// breakpoint or a loading hitch) are shortened to this to prevent objects from teleporting.
+    pub const MAX_DT: f64 = 1.0 / 20.0;
+
+    /// The oldest trail zones are removed once there are more than this many
+    pub const MAX_TRAIL_ZONES: usize = 256;
+
+    pub const BACKGROUND_COLOR: Color = colors::BLACK;
+    pub const STARTING_HEALTH: u32 = 5;
+    /// Mixed into the seed to get the seed of [`Game::cosmetic_rng`]
+    pub const COSMETIC_STREAM: u64 = 0x7061_7274_6963_6c65;
+    /// How much health the turret loses to a projectile that can damage it
+    pub const PROJECTILE_DAMAGE: u32 = 1;
+    pub const DEFAULT_SEPARATION_ITERATIONS: usize = 2;
+    pub const FLASH_DECAY: f64 = 12.0;
+
+    /// The step size used when tracing the path of a shot for [`Game::preview_shot`]
+    pub const PREVIEW_DT: f64 = 1.0 / 120.0;
+    /// How far [`Game::preview_shot`] follows a shot before giving up
+    pub const PREVIEW_RANGE: f64 = 100.0;
+    /// The most steps [`Game::predict_collisions`] checks each pair in, however fast they move
+    pub const MAX_PREDICTION_STEPS: usize = 64;
+
+    /// Only enemies this close to the turret are kept in view by [`Game::auto_frame`]
+    pub const AUTO_FRAME_RADIUS: f64 = 40.0;
+    /// Extra space left around everything being framed
+    pub const AUTO_FRAME_MARGIN: f64 = 3.0;
+    pub const AUTO_FRAME_MINIMUM_HALF_HEIGHT: f64 = 12.0;
+    pub const AUTO_FRAME_MAXIMUM_HALF_HEIGHT: f64 = 45.0;
+    pub const AUTO_FRAME_DECAY: f64 = 3.0;
+
+    /// Clamps `dt` to `0.0..=MAX_DT`, treating NaN as zero.
+    #[must_use]
+    pub fn sanitize_dt(dt: f64) -> f64 {
+        if dt.is_nan() {
+            0.0
+        } else {
+            dt.clamp(0.0, Self::MAX_DT)
+        }
+    }
+
     pub fn draw(&self) {
-        self.turret.draw();
+        if let Some(ghost_player) = &self.ghost_player {
+            ghost_player.draw(self.tick_count);
+        }
+
+        for trail_zone in &self.trail_zones {
+            trail_zone.draw();
+        }
+
+        for shadow in self.shadow_primitives() {
+            shadow.draw();
+        }
+
+        for turret in self.turrets.values() {
+            turret.draw();
+        }
+
+        self.spawn_queue.draw();
+
+        for particle in self.particles_in_layer(ParticleLayer::BehindEntities) {
+            particle.draw();
+        }
 
         for (_, enemy) in &self.enemies {
-            enemy.draw();
+            enemy.draw(self.pixels_per_unit);
+        }
+
+        for enemy in &self.explosion_queue {
+            enemy.draw_shattering(self.pixels_per_unit, self.tick_count);
         }
 
-        for (_, particle) in &self.particles {
+        for particle in self.particles_in_layer(ParticleLayer::AboveEntities) {
             particle.draw();
         }
 
         for (_, projectile) in &self.projectiles {
-            projectile.draw();
+            projectile.draw(self.tint_projectiles_by_damage);
+        }
+    }
+
+    /// The drop shadows [`Game::draw`] draws under each turret and enemy, or none if
+    /// [`Game::shadows`] is off. Each enemy's shadow pulses out of step with the others.
+    #[must_use]
+    pub fn shadow_primitives(&self) -> Vec<Shadow> {
+        if !self.shadows {
+            return Vec::new();
+        }
+
+        let time = self.stats.duration;
+        let turrets = (self.turrets.values())
+            .map(|turret| Shadow::new(turret.shape, turret.position, time, 0.0));
+
+        let enemies = self.enemies.iter().map(|(key, enemy)| {
+            let phase = rng::mix(key.data().as_ffi(), 0) as f64 / u64::MAX as f64 * TAU;
+
+            Shadow::new(enemy.shape, enemy.position, time, phase)
+        });
+
+        turrets.chain(enemies).collect()
+    }
+
+    /// The particles drawn in `layer`'s pass of [`Game::draw`]
+    pub fn particles_in_layer(&self, layer: ParticleLayer) -> impl Iterator<Item = &Particle> {
+        self.particles
+            .values()
+            .filter(move |particle| particle.layer == layer)
+    }
+
+    /// Draws the scene into `target` as seen by `camera`, then restores the previous camera
+    pub fn draw_to_target(&self, target: &RenderTarget, camera: &Camera2D) {
+        utils::draw_to_target(target, camera, Self::BACKGROUND_COLOR, || self.draw());
+    }
+
+    /// Draws the scene into `target` and reads back the resulting pixels
+    #[must_use]
+    pub fn capture_image(&self, target: &RenderTarget, camera: &Camera2D) -> Image {
+        self.draw_to_target(target, camera);
+
+        target.texture.get_texture_data()
+    }
+
+    #[must_use]
+    pub fn quality(&self) -> Quality {
+        self.explosion.quality
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.explosion.quality = quality;
+    }
+
+    /// Spawns an enemy from [`ENEMY_KINDS`] immediately, returning `None` if `kind_index` is out of
+    /// range
+    pub fn spawn_enemy(&mut self, kind_index: usize, position: Isometry2<f64>) -> Option<EnemyKey> {
+        let kind = ENEMY_KINDS.get(kind_index)?;
+
+        Some(self.insert_enemy(Enemy::new(position, kind)))
+    }
+
+    /// Spawns a `columns` by `rows` grid of enemies from [`ENEMY_KINDS`], all facing the same way.
+    /// The first enemy is placed at `origin`, with the grid extending along its local x and y axes.
+    ///
+    /// Returns the keys in row-major order, or `None` if `kind_index` is out of range.
+    pub fn spawn_grid(
+        &mut self,
+        kind_index: usize,
+        origin: Isometry2<f64>,
+        spacing: Vector2<f64>,
+        columns: usize,
+        rows: usize,
+    ) -> Option<Vec<EnemyKey>> {
+        let kind = ENEMY_KINDS.get(kind_index)?;
+
+        Some(
+            (0..rows)
+                .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                .map(|(column, row)| {
+                    let offset = vector![column as f64 * spacing.x, row as f64 * spacing.y];
+                    let position = Isometry2::from_parts(
+                        (origin.translation.vector + origin.rotation * offset).into(),
+                        origin.rotation,
+                    );
+
+                    self.insert_enemy(Enemy::new(position, kind))
+                })
+                .collect(),
+        )
+    }
+
+    /// Adds `enemy` with a spawn index after every entity already in the game
+    pub fn insert_enemy(&mut self, enemy: Enemy) -> EnemyKey {
+        let spawn_index = self.spawn_order.allocate();
+        self.grid.stale = true;
+
+        self.enemies.insert(Enemy {
+            spawn_index,
+            ..enemy
+        })
+    }
+
+    /// Adds `projectile` with a spawn index after every entity already in the game
+    pub fn insert_projectile(&mut self, projectile: Projectile) -> ProjectileKey {
+        let spawn_index = self.spawn_order.allocate();
+
+        self.projectiles.insert(Projectile {
+            spawn_index,
+            ..projectile
+        })
+    }
+
+    /// Removes the enemy at `key`, and forgets it in every projectile passing through it
+    pub fn remove_enemy(&mut self, key: EnemyKey) -> Option<Enemy> {
+        let enemy = self.enemies.remove(key)?;
+        self.grid.stale = true;
+
+        for projectile in self.projectiles.values_mut() {
+            projectile.forget_enemy(key);
+        }
+
+        Some(enemy)
+    }
+
+    /// Removes every enemy, including ones waiting to spawn
+    pub fn clear_enemies(&mut self) {
+        self.enemies.clear();
+        self.spawn_queue.telegraphs.clear();
+        self.grid.stale = true;
+
+        for projectile in self.projectiles.values_mut() {
+            projectile.enemies_colliding.clear();
+            projectile.enemies_intersecting.clear();
+        }
+    }
+
+    /// Removes every enemy, projectile, particle, trail zone, and damage indicator
+    pub fn clear_all(&mut self) {
+        self.clear_enemies();
+        self.projectiles.clear();
+        self.particles.clear();
+        self.trail_zones.clear();
+        self.damage_indicators.clear();
+    }
+
+    pub fn set_invincible(&mut self, invincible: bool) {
+        self.invincible = invincible;
+    }
+
+    /// Where the entity `id` refers to is and how it's moving, or `None` if it's gone
+    #[must_use]
+    pub fn transform(&self, id: EntityId) -> Option<Transform> {
+        match id {
+            EntityId::Particle(key) => self.particles.get(key).map(|particle| particle.transform),
+            _ => self.object(id).map(|object| object.transform),
+        }
+    }
+
+    /// The collision object of the entity `id` refers to, or `None` if it's gone. Particles don't
+    /// collide, so they never have one.
+    #[must_use]
+    pub fn object(&self, id: EntityId) -> Option<Object> {
+        match id {
+            EntityId::Enemy(key) => self.enemies.get(key).map(|enemy| enemy.object),
+            EntityId::Projectile(key) => self
+                .projectiles
+                .get(key)
+                .map(|projectile| projectile.object),
+            EntityId::Particle(_) => None,
+        }
+    }
+
+    /// Every projectile and enemy that would start colliding if both moved forward by `dt`, without
+    /// changing either. The movement is checked in steps of at most half the smaller of the two,
+    /// so that fast projectiles can't pass through an enemy between checks.
+    #[must_use]
+    pub fn predict_collisions(&self, dt: f64) -> Vec<(EntityId, EntityId)> {
+        let enemies = (self.enemies.iter())
+            .filter(|(_, enemy)| !enemy.should_delete() && !enemy.is_immune())
+            .map(|(key, enemy)| {
+                let mut end = enemy.object;
+                end.tick(dt);
+
+                (key, enemy, end)
+            })
+            .collect::<Vec<_>>();
+
+        let mut collisions = Vec::new();
+
+        for (projectile_key, projectile) in &self.projectiles {
+            if projectile.should_delete() || projectile.tether.is_some() {
+                continue;
+            }
+
+            let projectile_travel = projectile.predicted_object(dt).position.translation.vector
+                - projectile.position.translation.vector;
+
+            for (enemy_key, enemy, enemy_end) in &enemies {
+                if projectile.enemies_intersecting.contains(enemy_key)
+                    || projectile.enemies_colliding.contains(enemy_key)
+                {
+                    continue;
+                }
+
+                let enemy_travel =
+                    enemy_end.position.translation.vector - enemy.position.translation.vector;
+                let step_length =
+                    0.5 * (projectile.shape.bounding_radius()).min(enemy.shape.bounding_radius());
+                let steps = ((projectile_travel - enemy_travel).magnitude() / step_length)
+                    .ceil()
+                    .clamp(1.0, Self::MAX_PREDICTION_STEPS as f64)
+                    as usize;
+
+                let colliding = (1..=steps).any(|step| {
+                    let t = dt * step as f64 / steps as f64;
+
+                    let object = projectile.predicted_object(t);
+                    let mut enemy = enemy.object;
+                    enemy.tick(t);
+
+                    match &self.arena {
+                        Some(arena) => arena.is_colliding(&object, &enemy),
+                        None => object.is_colliding(&enemy),
+                    }
+                });
+
+                if colliding {
+                    collisions.push((projectile_key.into(), (*enemy_key).into()));
+                }
+            }
+        }
+
+        collisions
+    }
+
+    /// Traces the path of a projectile of `kind` fired from `muzzle` through the enemies as they
+    /// are now, without changing anything. Ricochets and tethers aren't followed.
+    #[must_use]
+    pub fn preview_shot(&self, muzzle: Isometry2<f64>, kind: &ProjectileKind) -> ShotPreview {
+        let properties = &kind.properties;
+
+        let mut object = Object {
+            shape: Shape::Rectangle {
+                half_size: properties.size / 2.0,
+            },
+            transform: Transform {
+                position: muzzle,
+                linear_velocity: vector![0.0, 0.0],
+                angular_velocity: 0.0,
+            },
+        };
+        let mut fall_velocity = Vector2::zeros();
+        let mut travelled = 0.0;
+
+        let mut preview = ShotPreview::default();
+
+        while travelled < Self::PREVIEW_RANGE {
+            fall_velocity += properties.gravity * Self::PREVIEW_DT;
+            object.linear_velocity =
+                muzzle.rotation * vector![properties.speed, 0.0] + fall_velocity;
+
+            if object.linear_velocity == Vector2::zeros() {
+                break;
+            }
+
+            object.position.rotation =
+                UnitComplex::new(object.linear_velocity.y.atan2(object.linear_velocity.x));
+            object.tick(Self::PREVIEW_DT);
+            travelled += object.linear_velocity.norm() * Self::PREVIEW_DT;
+
+            let mut touching = (self.enemies.iter())
+                .filter(|(key, enemy)| {
+                    !enemy.should_delete()
+                        && !enemy.is_immune()
+                        && !preview
+                            .hits
+                            .iter()
+                            .any(|hit| hit.enemy == EntityId::Enemy(*key))
+                        && object.is_colliding(enemy)
+                })
+                .collect::<Vec<_>>();
+            // Matches the order projectiles hit enemies in
+            let forward = object.position.rotation * vector![1.0, 0.0];
+            touching.sort_by(|(_, a), (_, b)| {
+                let depth = |enemy: &Enemy| object.linear_offset_to(enemy).dot(&forward);
+                depth(a).total_cmp(&depth(b))
+            });
+
+            for (key, enemy) in touching {
+                let damage = properties.damage_after_hits(preview.hits.len(), travelled);
+
+                preview.hits.push(PreviewHit {
+                    enemy: key.into(),
+                    damage,
+                    kills: damage >= enemy.health,
+                });
+
+                if !properties.piercing {
+                    return preview;
+                }
+            }
+        }
+
+        preview
+    }
+
+    /// How many enemies are in the circle of `radius` around `center`
+    #[must_use]
+    pub fn count_enemies_in_circle(
+        &self,
+        center: Point2<f64>,
+        radius: f64,
+        membership: RegionMembership,
+    ) -> usize {
+        let bounds = BoundingBox::around(center, vector![radius, radius]);
+        let shape = Shape::Circle { radius };
+        let position = Isometry2::translation(center.x, center.y);
+
+        self.enemies_in_region(bounds, &shape, &position, membership)
+            .count()
+    }
+
+    /// Every enemy in `bounds`, in no particular order
+    #[must_use]
+    pub fn enemies_in_aabb(
+        &self,
+        bounds: BoundingBox<f64>,
+        membership: RegionMembership,
+    ) -> Vec<EnemyKey> {
+        let center = nalgebra::center(&bounds.min, &bounds.max);
+        let shape = Shape::Rectangle {
+            half_size: (bounds.max - bounds.min) / 2.0,
+        };
+        let position = Isometry2::translation(center.x, center.y);
+
+        self.enemies_in_region(bounds, &shape, &position, membership)
+            .collect()
+    }
+
+    /// The enemies in `shape` at `position`, which fits inside `bounds`. Candidates come from the
+    /// spatial grid when it isn't stale and there's no arena to wrap around, and from every enemy
+    /// otherwise.
+    fn enemies_in_region<'a>(
+        &'a self,
+        bounds: BoundingBox<f64>,
+        shape: &'a Shape,
+        position: &'a Isometry2<f64>,
+        membership: RegionMembership,
+    ) -> impl Iterator<Item = EnemyKey> + 'a {
+        let candidates: Box<dyn Iterator<Item = EnemyKey>> =
+            if self.arena.is_none() && !self.grid.stale {
+                Box::new(self.grid.query_region(bounds))
+            } else {
+                Box::new(self.enemies.keys())
+            };
+
+        candidates.filter(move |&key| {
+            let Some(enemy) = self.enemies.get(key) else {
+                return false;
+            };
+
+            match membership {
+                RegionMembership::Shape => {
+                    collision::overlaps(shape, position, &enemy.shape, &enemy.position)
+                }
+                RegionMembership::Center => {
+                    collision::overlaps(shape, position, &Shape::Point, &enemy.position)
+                }
+            }
+        })
+    }
+
+    /// An id that nothing else in this game has been given, for a new turret or player to mark
+    /// its projectiles with. [`SourceId::default`] is never handed out.
+    pub fn allocate_source_id(&mut self) -> SourceId {
+        let source = SourceId(self.next_source_id);
+        self.next_source_id += 1;
+
+        source
+    }
+
+    /// Adds `turret` with a source id of its own, so that its shots can be told apart from other
+    /// turrets'
+    pub fn add_turret(&mut self, turret: Turret) -> TurretKey {
+        let source = self.allocate_source_id();
+
+        self.turrets.insert(Turret { source, ..turret })
+    }
+
+    /// The turret in [`Game::primary_turret_key`], which the player aims and shoots with.
+    /// Enemies chase it and spawns are placed around it.
+    ///
+    /// Panics if the game has no primary turret.
+    #[must_use]
+    pub fn primary_turret(&self) -> &Turret {
+        (self.turrets.get(self.primary_turret_key)).expect("game has no primary turret")
+    }
+
+    /// Like [`Game::primary_turret`], but mutable
+    #[must_use]
+    pub fn primary_turret_mut(&mut self) -> &mut Turret {
+        (self.turrets.get_mut(self.primary_turret_key)).expect("game has no primary turret")
+    }
+
+    /// Starts a parry if the primary turret can parry and isn't cooling down from the last one
+    pub fn start_parry(&mut self) {
+        if let Some(parry) = &mut self.primary_turret_mut().parry {
+            parry.start();
+        }
+    }
+
+    /// Whether `projectile` should damage a turret it collides with
+    #[must_use]
+    pub fn can_damage_turret(&self, projectile: &Projectile) -> bool {
+        match projectile.faction {
+            Faction::Player => self.friendly_fire,
+            Faction::Enemy => true,
+        }
+    }
+
+    /// Reacts to the turret being damaged by something at `source`, unless it's invincible
+    pub fn on_turret_damaged(&mut self, source: Point2<f64>) {
+        if self.invincible {
+            return;
+        }
+
+        self.score.on_turret_damaged();
+        self.spawn_queue.wave_damaged = true;
+        self.indicate_damage_from(source);
+    }
+
+    /// The color to tint the whole screen with, which is transparent when reduced flashing is on
+    #[must_use]
+    pub fn screen_flash(&self) -> Color {
+        if self.reduced_flashing {
+            Color {
+                a: 0.0,
+                ..self.flash
+            }
+        } else {
+            self.flash
+        }
+    }
+
+    /// Whether the latest cleared wave was cleared without the turret being damaged
+    #[must_use]
+    pub fn last_wave_perfect(&self) -> bool {
+        self.last_wave_perfect
+    }
+
+    /// Awards the bonus for the latest wave if it has just been cleared
+    fn check_wave_cleared(&mut self) {
+        if self.spawn_queue.wave == 0 || self.spawn_queue.wave_cleared || self.is_wave_active() {
+            return;
+        }
+
+        let perfect = !self.spawn_queue.wave_damaged;
+        let fast = self.spawn_queue.wave_time <= self.spawn_queue.wave_time_target;
+        let bonus = self
+            .score
+            .on_wave_cleared(self.spawn_queue.wave, perfect, fast);
+
+        self.spawn_queue.wave_cleared = true;
+        self.spawn_queue.queue_next_wave(SpawnQueue::WAVE_BREAK);
+        self.last_wave_perfect = perfect;
+        self.events.push(GameEvent::WaveCleared { perfect, bonus });
+    }
+
+    /// Shows a damage indicator pointing from the primary turret towards `source`
+    pub fn indicate_damage_from(&mut self, source: Point2<f64>) {
+        let offset = source.coords - self.primary_turret().position.translation.vector;
+
+        if offset.magnitude_squared() > 0.0 {
+            self.damage_indicators
+                .push(DamageIndicator::new(UnitComplex::new_normalize(
+                    Complex::new(offset.x, offset.y),
+                )));
+        }
+    }
+
+    /// The center and half size of a box around every turret and every enemy within
+    /// [`Game::AUTO_FRAME_RADIUS`] of one, including [`Game::AUTO_FRAME_MARGIN`]
+    #[must_use]
+    pub fn framing(&self) -> (Point2<f64>, Vector2<f64>) {
+        let turret_positions = (self.turrets.values())
+            .map(|turret| turret.position.translation.vector)
+            .collect::<Vec<_>>();
+        let primary_position = self.primary_turret().position.translation.vector;
+
+        let near_enemies = (self.enemies.values())
+            .map(|enemy| enemy.position.translation.vector)
+            .filter(|position| {
+                (turret_positions.iter())
+                    .any(|turret| (position - turret).norm() <= Self::AUTO_FRAME_RADIUS)
+            });
+
+        let (min, max) = (turret_positions.iter().copied()).chain(near_enemies).fold(
+            (primary_position, primary_position),
+            |(min, max), position| (min.inf(&position), max.sup(&position)),
+        );
+
+        (
+            ((min + max) / 2.0).into(),
+            (max - min) / 2.0 + vector![1.0, 1.0] * Self::AUTO_FRAME_MARGIN,
+        )
+    }
+
+    /// Eases `camera` towards keeping the turrets and nearby enemies in view, zooming within
+    /// [`Game::AUTO_FRAME_MINIMUM_HALF_HEIGHT`] and [`Game::AUTO_FRAME_MAXIMUM_HALF_HEIGHT`].
+    /// Heights are measured along the screen, so this works with a rotated view.
+    pub fn auto_frame(&self, camera: &mut Camera2D, dt: f64) {
+        let (center, half_size) = self.framing();
+        let half_size = utils::rotated_half_size(half_size, (camera.rotation as f64).to_radians());
+
+        let aspect_ratio = (camera.zoom.y / camera.zoom.x).abs() as f64;
+        let target_half_height = half_size.y.max(half_size.x / aspect_ratio).clamp(
+            Self::AUTO_FRAME_MINIMUM_HALF_HEIGHT,
+            Self::AUTO_FRAME_MAXIMUM_HALF_HEIGHT,
+        );
+
+        let half_height = tween::exp_decay(
+            1.0 / camera.zoom.y.abs() as f64,
+            target_half_height,
+            Self::AUTO_FRAME_DECAY,
+            dt,
+        );
+        camera.zoom.y = (camera.zoom.y.signum() as f64 / half_height) as f32;
+
+        camera.target.x =
+            tween::exp_decay(camera.target.x as f64, center.x, Self::AUTO_FRAME_DECAY, dt) as f32;
+        camera.target.y =
+            tween::exp_decay(camera.target.y as f64, center.y, Self::AUTO_FRAME_DECAY, dt) as f32;
+
+        utils::update_camera_aspect_ratio(camera);
+    }
+
+    /// The number of the latest wave, or `0` before the first one
+    #[must_use]
+    pub fn current_wave(&self) -> u32 {
+        self.spawn_queue.wave
+    }
+
+    /// How many enemies of the latest wave are still alive or waiting to spawn
+    #[must_use]
+    pub fn enemies_remaining_in_wave(&self) -> u32 {
+        let alive = (self.spawn_queue.wave_enemies.iter())
+            .filter(|&&key| self.enemies.contains_key(key))
+            .count();
+
+        let spawned =
+            (self.spawner.as_ref()).map_or(0, |spawner| spawner.enemies_remaining(&self.enemies));
+
+        (alive + self.spawn_queue.queued_in_wave() + spawned) as u32
+    }
+
+    /// Whether the latest wave still has enemies left. A wave is cleared once all of its spawns
+    /// have appeared and died.
+    #[must_use]
+    pub fn is_wave_active(&self) -> bool {
+        self.enemies_remaining_in_wave() > 0
+    }
+
+    #[must_use]
+    pub fn time_until_next_wave(&self) -> Option<f64> {
+        self.spawn_queue.next_wave_delay
+    }
+
+    /// Saves everything needed to pick the game back up with [`Game::from_bytes`]. See
+    /// [`snapshot`] for what isn't saved.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        snapshot::encode(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::decode(bytes)
+    }
+
+    /// A hash of everything that affects how the game plays out from here, ignoring cosmetic
+    /// state and which keys entities are stored under. Games that play out the same keep equal
+    /// hashes.
+    pub fn state_hash(&self) -> Result<u64, SnapshotError> {
+        snapshot::gameplay_hash(self)
+    }
+
+    /// A game whose randomness all comes from `seed`
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Rng::new(seed),
+            cosmetic_rng: Rng::new(rng::mix(seed, Self::COSMETIC_STREAM)),
+            ..Self::default()
+        }
+    }
+
+    /// A game whose turrets and spawns are placed relative to `origin`
+    #[must_use]
+    pub fn with_origin(origin: Isometry2<f64>) -> Self {
+        let mut game = Self {
+            origin,
+            ..Self::default()
+        };
+        for turret in game.turrets.values_mut() {
+            turret.position = origin * turret.position;
+        }
+
+        game
+    }
+
+    /// Converts `position`, relative to the game's origin, into world space
+    #[must_use]
+    pub fn world_position(&self, position: Isometry2<f64>) -> Isometry2<f64> {
+        self.origin * position
+    }
+
+    /// Spawns an enemy of `kind` at `position` relative to the game's origin, after the usual
+    /// warning
+    pub fn schedule_spawn(&mut self, position: Isometry2<f64>, kind: &'static EnemyKind) {
+        self.spawn_queue
+            .schedule(self.world_position(position), kind);
+    }
+
+    /// Points `camera` at the game's origin
+    pub fn center_camera(&self, camera: &mut Camera2D) {
+        camera.target = utils::point2_f64_to_vec2(self.origin.translation.vector.into());
+    }
+
+    /// Gathers the statistics for the run so far, to be reported once it ends
+    #[must_use]
+    pub fn run_summary(&self) -> RunSummary {
+        RunSummary {
+            seed: self.seed,
+            duration: self.stats.duration,
+            score: self.score.points,
+            kills_by_kind: (self.stats.kills_by_kind.iter())
+                .map(|(name, &kills)| (name.clone(), kills))
+                .collect(),
+            shots_fired: self.stats.shots_fired,
+            accuracy: self.stats.accuracy(),
+            max_combo: self.stats.max_combo,
         }
     }
 
     pub fn tick_input(&mut self, dt: f64) {
-        self.turret.input.tick(dt);
+        let dt = Self::sanitize_dt(dt);
+
+        self.primary_turret_mut().input.tick(dt);
+
+        if input::is_key_pressed(Parry::KEY) {
+            self.start_parry();
+        }
+
+        if let Some(virtual_cursor) = &mut self.virtual_cursor {
+            virtual_cursor.tick(VirtualCursor::keyboard_deflection(), dt);
+        }
+    }
+
+    /// Runs `ticks` ticks without a window or drawing, asking `input` for the input on each tick
+    pub fn simulate(&mut self, ticks: usize, mut input: impl FnMut(u64) -> InputState, dt: f64) {
+        for _ in 0..ticks {
+            let input = input(self.tick_count);
+
+            self.primary_turret_mut().input.tick_with(input.shoot, dt);
+            if input.parry {
+                self.start_parry();
+            }
+            self.tick_with_input(input.mouse_position, input.camera_bounds, dt);
+        }
     }
 
     pub fn tick(&mut self, camera: &mut Camera2D, dt: f64) {
-        self.turret
-            .tick(utils::mouse_position(camera), &mut self.projectiles, dt);
+        self.pixels_per_unit = utils::pixels_per_unit(camera);
 
-        let camera_bounds = utils::bounds_of_camera(camera);
+        self.tick_with_input(
+            self.aim_position(camera),
+            utils::bounds_of_camera(camera),
+            dt,
+        );
+    }
 
-        self.projectiles.retain(|_, projectile| {
-            projectile.tick(&mut self.enemies, &mut self.particles, dt);
-            !projectile.should_delete()
-                && camera_bounds.is_colliding(&projectile.shape, projectile.position)
-        });
+    /// Where the player is aiming in world space, before any reticle snapping
+    #[must_use]
+    pub fn aim_position(&self, camera: &Camera2D) -> Point2<f64> {
+        match &self.virtual_cursor {
+            Some(virtual_cursor) => virtual_cursor.position,
+            None => utils::mouse_position(camera),
+        }
+    }
 
-        self.enemies.retain(|_, enemy| {
-            enemy.tick(dt);
-            !enemy.should_delete()
-        });
+    /// Where the primary turret aims for a raw aim point of `mouse_world`, after
+    /// [`Game::reticle`] snaps it
+    #[must_use]
+    pub fn reticle_position(&self, mouse_world: Point2<f64>) -> Point2<f64> {
+        let Some(reticle) = &self.reticle else {
+            return mouse_world;
+        };
 
-        self.particles.retain(|_, particle| {
-            particle.tick(dt);
-            !particle.should_delete()
-        });
+        let targets = (self.enemies.values())
+            .filter(|enemy| !enemy.should_delete())
+            .map(|enemy| enemy.position.translation.vector.into());
+
+        reticle.snap(mouse_world, targets)
+    }
+
+    /// Like [`Game::tick`], but without reading from the window. Projectiles outside of
+    /// `camera_bounds`, centered on the game's origin, are removed.
+    ///
+    /// `dt` is passed through [`Game::sanitize_dt`] first, and non-positive steps are skipped
+    /// entirely.
+    pub fn tick_with_input(&mut self, mouse_position: Point2<f64>, camera_bounds: Shape, dt: f64) {
+        let dt = Self::sanitize_dt(dt);
+
+        if dt == 0.0 {
+            return;
+        }
+
+        if self.is_game_over() {
+            self.tick_effects(dt);
+            return;
+        }
+
+        self.primary_turret_mut().target = self.reticle_position(mouse_position);
+
+        let projectile_count = self.projectiles.len();
+        for turret in self.turrets.values_mut() {
+            turret.tick(
+                &mut self.projectiles,
+                &mut self.spawn_order,
+                &mut self.cosmetic_rng,
+                dt,
+            );
+        }
+        self.stats.shots_fired += (self.projectiles.len() - projectile_count) as u32;
+        self.stats.duration += dt;
+
+        let primary_position = self.primary_turret().position;
+
+        if let Some(ghost_recording) = &mut self.ghost_recording {
+            ghost_recording.record(self.tick_count, primary_position);
+        }
+
+        self.grid.rebuild(&self.enemies);
+
+        if self.stable_order {
+            let enemy_order = keys_in_spawn_order(&self.enemies, |enemy| enemy.spawn_index);
+
+            for key in keys_in_spawn_order(&self.projectiles, |projectile| projectile.spawn_index) {
+                let projectile = &mut self.projectiles[key];
+                let hits = projectile.enemies_hit.len();
+
+                projectile.tick(
+                    &mut self.enemies,
+                    &mut self.particles,
+                    &mut self.trail_zones,
+                    &ProjectileContext {
+                        explosion: &self.explosion,
+                        enemy_order: Some(&enemy_order),
+                        tick: self.tick_count,
+                        arena: self.arena,
+                        grid: Some(&self.grid),
+                    },
+                    &mut self.cosmetic_rng,
+                    dt,
+                );
+
+                if projectile.enemies_hit.len() > hits {
+                    add_impact_flash(&mut self.flash, projectile);
+
+                    if hits == 0 {
+                        self.stats.shots_hit += 1;
+                    }
+                }
+            }
+
+            self.projectiles.retain(|_, projectile| {
+                keep_projectile(
+                    projectile,
+                    self.arena.as_ref(),
+                    &camera_bounds,
+                    &self.origin,
+                )
+            });
+        } else {
+            self.projectiles.retain(|_, projectile| {
+                let hits = projectile.enemies_hit.len();
+
+                projectile.tick(
+                    &mut self.enemies,
+                    &mut self.particles,
+                    &mut self.trail_zones,
+                    &ProjectileContext {
+                        explosion: &self.explosion,
+                        enemy_order: None,
+                        tick: self.tick_count,
+                        arena: self.arena,
+                        grid: Some(&self.grid),
+                    },
+                    &mut self.cosmetic_rng,
+                    dt,
+                );
+
+                if projectile.enemies_hit.len() > hits {
+                    add_impact_flash(&mut self.flash, projectile);
+
+                    if hits == 0 {
+                        self.stats.shots_hit += 1;
+                    }
+                }
+
+                keep_projectile(
+                    projectile,
+                    self.arena.as_ref(),
+                    &camera_bounds,
+                    &self.origin,
+                )
+            });
+        }
+
+        // Enemies move, spawn and die from here on
+        self.grid.stale = true;
+
+        self.collide_projectiles_with_turret();
+
+        let excess_trail_zones = self.trail_zones.len().saturating_sub(Self::MAX_TRAIL_ZONES);
+        self.trail_zones.drain(..excess_trail_zones);
+
+        self.trail_zones.retain_mut(|trail_zone| {
+            trail_zone.tick(
+                &mut self.enemies,
+                &mut self.particles,
+                &self.explosion,
+                self.tick_count,
+                &mut self.cosmetic_rng,
+                dt,
+            );
+            !trail_zone.should_delete()
+        });
+
+        let spawn_distance = camera_bounds.bounding_radius() + AutoSpawner::SPAWN_MARGIN;
+        let turret_position: Point2<f64> = self.primary_turret().position.translation.vector.into();
+
+        if let Some(auto_spawner) = &mut self.auto_spawner {
+            auto_spawner.tick(
+                &mut self.spawn_queue,
+                turret_position,
+                spawn_distance,
+                &mut self.rng,
+                dt,
+            );
+        }
+
+        if let Some(spawner) = &mut self.spawner {
+            let wave = spawner.wave;
+
+            spawner.tick(
+                dt,
+                &mut self.enemies,
+                &mut self.spawn_order,
+                &camera_bounds,
+                &self.origin,
+                &mut self.rng,
+            );
+
+            // The spawner's waves are scored and reported like any other
+            if spawner.wave != wave {
+                self.spawn_queue.start_wave();
+            }
+        }
+
+        (self.spawn_queue).tick(&mut self.enemies, &mut self.spawn_order, dt);
+
+        let mut enemy_context = EnemyContext {
+            turret_position,
+            enemy_positions: (self.enemies.iter())
+                .map(|(key, enemy)| (key, enemy.position.translation.vector.into()))
+                .collect(),
+        };
+
+        if self.double_buffered_enemies {
+            // Breaks ties in nearest enemy searches the same way whatever the storage order
+            enemy_context
+                .enemy_positions
+                .sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        }
+
+        self.score.tick(dt);
+
+        self.damage_indicators.retain_mut(|indicator| {
+            indicator.tick(dt);
+            !indicator.should_delete()
+        });
+
+        let enemy_order = if self.stable_order || self.double_buffered_enemies {
+            keys_in_spawn_order(&self.enemies, |enemy| enemy.spawn_index)
+        } else {
+            self.enemies.keys().collect()
+        };
+        let tick_seed = (self.double_buffered_enemies)
+            .then(|| rng::mix(self.cosmetic_rng.rand().into(), self.tick_count));
+
+        for key in enemy_order {
+            let enemy = &mut self.enemies[key];
+
+            if let Some(tick_seed) = tick_seed {
+                let mut rng = Rng::new(rng::mix(tick_seed, enemy.state_seed()));
+                enemy.tick(&enemy_context, &mut rng, dt);
+            } else {
+                enemy.tick(&enemy_context, &mut self.cosmetic_rng, dt);
+            }
+
+            if let Some(arena) = &self.arena {
+                arena.wrap(&mut enemy.position);
+            }
+
+            if enemy.should_delete() {
+                let enemy = self.remove_enemy(key).unwrap();
+
+                self.stats.on_kill(enemy.name.clone(), &self.score);
+                self.score.on_kill(enemy.properties.maximum_health);
+
+                if enemy.pending_explosion.is_some() {
+                    self.explosion_queue.push(enemy);
+                }
+            }
+        }
+
+        self.separate_enemies();
+
+        self.run_queued_explosions(&camera_bounds);
+
+        self.collide_enemies_with_turret();
+
+        self.check_wave_cleared();
+
+        self.tick_effects(dt);
+    }
+
+    /// The part of a tick that keeps going after the game is over
+    fn tick_effects(&mut self, dt: f64) {
+        self.flash.a = tween::exp_decay(self.flash.a as f64, 0.0, Self::FLASH_DECAY, dt) as f32;
+
+        self.particles.retain(|_, particle| {
+            particle.tick(dt);
+            !particle.should_delete()
+        });
+
+        // For region queries between ticks
+        self.grid.rebuild(&self.enemies);
+
+        self.tick_count += 1;
+
+        if cfg!(debug_assertions) {
+            self.check_invariants();
+        }
+    }
+
+    /// Nudges overlapping circular enemies apart by half of their overlap each, less for enemies
+    /// slowed by a recent hit. Stops early once nothing overlaps.
+    ///
+    /// With [`Game::double_buffered_enemies`], each pass works out every push from where the
+    /// enemies were at the start of the pass before moving any of them, so the outcome doesn't
+    /// depend on the order pairs are visited in.
+    fn separate_enemies(&mut self) {
+        let mut keys = (self.enemies.iter())
+            .filter(|(_, enemy)| matches!(enemy.shape, Shape::Circle { .. }))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        if self.stable_order || self.double_buffered_enemies {
+            keys.sort_unstable_by_key(|&key| self.enemies[key].spawn_index);
+        }
+
+        for _ in 0..self.separation_iterations {
+            let separated_any = if self.double_buffered_enemies {
+                self.separate_enemies_from_snapshot(&keys)
+            } else {
+                self.separate_enemies_in_order(&keys)
+            };
+
+            if !separated_any {
+                break;
+            }
+        }
+    }
+
+    /// One separation pass that moves each pair as soon as it's checked, returning whether
+    /// anything moved
+    fn separate_enemies_in_order(&mut self, keys: &[EnemyKey]) -> bool {
+        let mut separated_any = false;
+
+        for (i, &key_a) in keys.iter().enumerate() {
+            for &key_b in &keys[i + 1..] {
+                let [a, b] = self.enemies.get_disjoint_mut([key_a, key_b]).unwrap();
+
+                let Some(push) = separation_push(a, b) else {
+                    continue;
+                };
+
+                let (push_a, push_b) = (push * a.speed_multiplier(), push * b.speed_multiplier());
+                a.position.translation.vector -= push_a;
+                b.position.translation.vector += push_b;
+                separated_any = true;
+            }
+        }
+
+        separated_any
+    }
+
+    /// One separation pass that only moves enemies once every pair has been checked, returning
+    /// whether anything moved
+    fn separate_enemies_from_snapshot(&mut self, keys: &[EnemyKey]) -> bool {
+        let mut pushes = vec![Vector2::zeros(); keys.len()];
+        let mut separated_any = false;
+
+        for (i, &key_a) in keys.iter().enumerate() {
+            for (j, &key_b) in keys.iter().enumerate().skip(i + 1) {
+                let (a, b) = (&self.enemies[key_a], &self.enemies[key_b]);
+
+                let Some(push) = separation_push(a, b) else {
+                    continue;
+                };
+
+                pushes[i] -= push * a.speed_multiplier();
+                pushes[j] += push * b.speed_multiplier();
+                separated_any = true;
+            }
+        }
+
+        for (&key, push) in keys.iter().zip(pushes) {
+            self.enemies[key].position.translation.vector += push;
+        }
+
+        separated_any
+    }
+
+    /// Projectiles that reach a turret and can damage it are reflected during that turret's parry
+    /// if they came from enemies. Otherwise they damage the game's health and are removed. Each
+    /// turret ignores its own shots.
+    fn collide_projectiles_with_turret(&mut self) {
+        for turret_key in self.turrets.keys().collect::<Vec<_>>() {
+            let turret = &self.turrets[turret_key];
+            let object = turret.object();
+            let source = turret.source;
+            let parrying = turret.parry.is_some_and(|parry| parry.is_active());
+
+            let colliding = (self.projectiles.iter())
+                .filter(|(_, projectile)| {
+                    projectile.source != source
+                        && self.can_damage_turret(projectile)
+                        && projectile.bounding_circle_overlaps(&object)
+                        && projectile.is_colliding(&object)
+                })
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
+
+            for key in colliding {
+                let projectile = &mut self.projectiles[key];
+
+                if parrying && projectile.faction == Faction::Enemy {
+                    projectile.reflect(source);
+                } else {
+                    let position = projectile.position.translation.vector.into();
+
+                    self.projectiles.remove(key);
+                    self.damage_turret(Self::PROJECTILE_DAMAGE, position);
+                }
+            }
+        }
+    }
+
+    /// Enemies that reach any turret damage the game's health and die
+    fn collide_enemies_with_turret(&mut self) {
+        let turrets = (self.turrets.values())
+            .map(Turret::object)
+            .collect::<Vec<_>>();
+
+        let colliding = (self.enemies.iter())
+            .filter(|(_, enemy)| {
+                turrets.iter().any(|turret| {
+                    enemy.bounding_circle_overlaps(turret) && enemy.is_colliding(turret)
+                })
+            })
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        for key in colliding {
+            let mut enemy = self.remove_enemy(key).unwrap();
+            let position = enemy.position.translation.vector.into();
+
+            enemy.die(
+                position,
+                enemy.linear_velocity,
+                &self.explosion,
+                &mut self.particles,
+                Enemy::explosion_seed(key, self.tick_count),
+                &mut self.cosmetic_rng,
+            );
+            self.damage_turret(enemy.contact_damage(), position);
+
+            if enemy.pending_explosion.is_some() {
+                self.explosion_queue.push(enemy);
+            }
+        }
+    }
+
+    /// Takes `damage` from the turret's health, coming from `source`, and ends the game if none is
+    /// left. Nothing happens while the turret is invincible.
+    pub fn damage_turret(&mut self, damage: u32, source: Point2<f64>) {
+        if self.invincible || self.is_game_over() {
+            return;
+        }
+
+        self.health = self.health.saturating_sub(damage);
+        self.on_turret_damaged(source);
+
+        if self.health == 0 {
+            self.state = GameState::GameOver;
+            self.events.push(GameEvent::GameOver);
+        }
+    }
+
+    #[must_use]
+    pub fn is_game_over(&self) -> bool {
+        self.state == GameState::GameOver
+    }
+
+    /// Takes every event since the last call, oldest first
+    pub fn drain_events(&mut self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Runs as many queued explosions as [`ExplosionParameters::maximum_per_tick`] allows,
+    /// starting with the ones inside `camera_bounds`
+    fn run_queued_explosions(&mut self, camera_bounds: &Shape) {
+        let limit = self.explosion.maximum_per_tick.unwrap_or(usize::MAX);
+
+        // Stable, so deaths on each side of the edge keep the order they happened in
+        self.explosion_queue.sort_by_key(|enemy| {
+            !camera_bounds.is_colliding(&enemy.shape, self.origin.inv_mul(&enemy.position))
+        });
+
+        let count = limit.min(self.explosion_queue.len());
+        for enemy in self.explosion_queue.drain(..count) {
+            enemy.run_pending_explosion(
+                &self.explosion,
+                &mut self.particles,
+                &mut self.cosmetic_rng,
+            );
+        }
+    }
+
+    /// Panics if the game is in an inconsistent state. Only called automatically in debug builds.
+    ///
+    /// Keys in `enemies_hit` are allowed to refer to enemies that have since been removed, as
+    /// projectiles remember every enemy they have killed.
+    pub fn check_invariants(&self) {
+        assert!(
+            self.turrets.contains_key(self.primary_turret_key),
+            "game has no primary turret",
+        );
+
+        for (key, turret) in &self.turrets {
+            assert!(
+                turret
+                    .position
+                    .translation
+                    .vector
+                    .iter()
+                    .all(|x| x.is_finite())
+                    && turret.position.rotation.angle().is_finite(),
+                "turret {key:?} has a non-finite position: {:?}",
+                turret.position,
+            );
+            assert!(
+                (self.turrets.iter())
+                    .all(|(other_key, other)| other_key == key || other.source != turret.source),
+                "turret {key:?} shares its source id {:?} with another turret",
+                turret.source,
+            );
+        }
+
+        for (key, enemy) in &self.enemies {
+            assert!(
+                enemy.transform.is_finite(),
+                "enemy {key:?} has a non-finite transform: {:?}",
+                enemy.transform,
+            );
+            assert!(
+                enemy.health <= enemy.properties.maximum_health,
+                "enemy {key:?} has {} health, more than its maximum of {}",
+                enemy.health,
+                enemy.properties.maximum_health,
+            );
+        }
+
+        for (key, projectile) in &self.projectiles {
+            assert!(
+                projectile.transform.is_finite(),
+                "projectile {key:?} has a non-finite transform: {:?}",
+                projectile.transform,
+            );
+
+            for (list, enemies) in [
+                ("enemies_colliding", &projectile.enemies_colliding),
+                ("enemies_intersecting", &projectile.enemies_intersecting),
+            ] {
+                for enemy in enemies {
+                    assert!(
+                        self.enemies.contains_key(*enemy),
+                        "projectile {key:?} has a removed enemy {enemy:?} in `{list}`",
+                    );
+                }
+            }
+        }
+
+        for (key, particle) in &self.particles {
+            assert!(
+                particle.transform.is_finite(),
+                "particle {key:?} has a non-finite transform: {:?}",
+                particle.transform,
+            );
+        }
+    }
+}
+
+/// The keys of `map` sorted by the spawn index of their values
+fn keys_in_spawn_order<K: Key, V>(
+    map: &HopSlotMap<K, V>,
+    spawn_index: impl Fn(&V) -> u64,
+) -> Vec<K> {
+    let mut keys = map.keys().collect::<Vec<_>>();
+    keys.sort_unstable_by_key(|&key| spawn_index(&map[key]));
+    keys
+}
+
+/// How far `b` should be pushed away from `a`, and `a` from `b` in the opposite direction, to
+/// resolve half of their overlap each, or `None` if they don't overlap
+fn separation_push(a: &Enemy, b: &Enemy) -> Option<Vector2<f64>> {
+    let collision = (a.shape).collision(&b.shape, a.position.inv_mul(&b.position))?;
+
+    Some((a.position.rotation * collision.normal) * vector![collision.depth / 2.0, 0.0])
+}
+
+/// Adds `projectile`'s impact flash, if it has one, on top of `flash`
+fn add_impact_flash(flash: &mut Color, projectile: &Projectile) {
+    if let Some(impact_flash) = projectile.impact_flash {
+        *flash = Color {
+            a: (flash.a + impact_flash.a).min(1.0),
+            ..impact_flash
+        };
+    }
+}
+
+/// Wraps `projectile` around the arena if it should, and decides whether to keep it. Projectiles
+/// that don't wrap are removed once they leave `camera_bounds` centered on `origin`.
+fn keep_projectile(
+    projectile: &mut Projectile,
+    arena: Option<&WrapArena>,
+    camera_bounds: &Shape,
+    origin: &Isometry2<f64>,
+) -> bool {
+    if projectile.should_delete() {
+        return false;
+    }
+
+    match arena {
+        Some(arena) if arena.wrap_projectiles => {
+            if arena.wrap(&mut projectile.position) {
+                projectile.wraps += 1;
+            }
+
+            projectile.wraps <= arena.maximum_projectile_wraps
+        }
+        _ => camera_bounds.is_colliding(&projectile.shape, origin.inv_mul(&projectile.position)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        f64::consts::{FRAC_PI_2, PI},
+        rc::Rc,
+    };
+
+    use nalgebra::{Isometry2, point, vector};
+
+    use super::*;
+    use crate::{
+        enemy::ENEMY_KINDS,
+        projectile::{PROJECTILE_KINDS, ProjectileKind, ProjectileProperties},
+        spawner::{Spawner, Wave},
+    };
+
+    const DT: f64 = 1.0 / 120.0;
+
+    fn camera_bounds() -> Shape {
+        Shape::Rectangle {
+            half_size: vector![20.0, 20.0],
+        }
+    }
+
+    fn tick(game: &mut Game, ticks: usize) {
+        for _ in 0..ticks {
+            game.tick_with_input(point![0.0, 10.0], camera_bounds(), DT);
+        }
+    }
+
+    fn ticks_for(seconds: f64) -> usize {
+        (seconds / DT).ceil() as usize + 1
+    }
+
+    /// Inserts an enemy of `kind` that is past its spawn immunity and doesn't move
+    fn stationary_enemy(game: &mut Game, kind: usize, position: Isometry2<f64>) -> EnemyKey {
+        let mut enemy = Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(position, &ENEMY_KINDS[kind])
+        };
+        enemy.properties.speed = 0.0;
+
+        game.insert_enemy(enemy)
+    }
+
+    #[test]
+    fn wave_accessors_follow_a_scripted_wave() {
+        let mut game = Game::with_seed(1);
+
+        assert_eq!(game.current_wave(), 0);
+        assert!(!game.is_wave_active());
+
+        game.spawn_queue.schedule_wave([
+            (Isometry2::new(vector![30.0, 0.0], PI), &ENEMY_KINDS[0]),
+            (Isometry2::new(vector![-30.0, 0.0], 0.0), &ENEMY_KINDS[0]),
+        ]);
+
+        assert_eq!(game.current_wave(), 1);
+        assert_eq!(game.enemies_remaining_in_wave(), 2);
+        assert!(game.is_wave_active());
+
+        tick(&mut game, ticks_for(SpawnQueue::LEAD_TIME));
+
+        assert_eq!(game.enemies.len(), 2);
+        assert_eq!(game.enemies_remaining_in_wave(), 2);
+
+        let keys = game.enemies.keys().collect::<Vec<_>>();
+        game.enemies.remove(keys[0]);
+
+        assert_eq!(game.enemies_remaining_in_wave(), 1);
+        assert!(game.is_wave_active());
+        assert_eq!(game.time_until_next_wave(), None);
+
+        game.enemies.remove(keys[1]);
+        tick(&mut game, 1);
+
+        assert_eq!(game.enemies_remaining_in_wave(), 0);
+        assert!(!game.is_wave_active());
+        assert_eq!(game.time_until_next_wave(), Some(SpawnQueue::WAVE_BREAK));
+
+        tick(&mut game, ticks_for(SpawnQueue::WAVE_BREAK));
+
+        assert_eq!(game.time_until_next_wave(), Some(0.0));
+    }
+
+    #[test]
+    fn enemies_reaching_the_turret_end_the_game() {
+        let mut game = Game {
+            health: 2,
+            ..Game::with_seed(3)
+        };
+
+        for (x, y) in [(1.5, 0.0), (0.0, 1.5)] {
+            game.enemies.insert(Enemy {
+                time_since_spawn: 1.0,
+                ..Enemy::new(
+                    Isometry2::new(vector![x, y], f64::atan2(-y, -x)),
+                    &ENEMY_KINDS[0],
+                )
+            });
+        }
+        let far = game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![15.0, 0.0], PI),
+            &ENEMY_KINDS[0],
+        ));
+
+        tick(&mut game, ticks_for(1.0));
+
+        assert!(game.is_game_over());
+        assert_eq!(game.health, 0);
+        assert_eq!(game.enemies.len(), 1);
+        assert!(!game.particles.is_empty());
+
+        let far_position = game.enemies[far].position;
+        let particle_positions = position_bits(game.particles.values().map(|p| p.position));
+        tick(&mut game, 10);
+
+        assert_eq!(game.enemies[far].position, far_position);
+        assert_ne!(
+            position_bits(game.particles.values().map(|p| p.position)),
+            particle_positions,
+        );
+    }
+
+    #[test]
+    fn turret_damage_halves_the_score_multiplier() {
+        let mut game = Game::with_seed(4);
+        for _ in 0..4 {
+            game.score.on_kill(1);
+        }
+
+        game.damage_turret(1, point![5.0, 0.0]);
+        assert_eq!(game.score.multiplier, 1.0);
+
+        for _ in 0..12 {
+            game.score.on_kill(1);
+        }
+        game.invincible = true;
+        game.damage_turret(1, point![5.0, 0.0]);
+        assert_eq!(game.score.multiplier, 4.0);
+
+        game.invincible = false;
+        game.damage_turret(1, point![5.0, 0.0]);
+        assert_eq!(game.score.multiplier, 2.0);
+    }
+
+    #[test]
+    fn damage_indicators_point_at_attackers_and_fade() {
+        let mut game = Game::with_seed(5);
+
+        for (x, y) in [(3.0, 0.0), (0.0, -3.0)] {
+            game.enemies.insert(Enemy {
+                time_since_spawn: 1.0,
+                ..Enemy::new(
+                    Isometry2::new(vector![x, y], f64::atan2(-y, -x)),
+                    &ENEMY_KINDS[0],
+                )
+            });
+        }
+
+        let mut ticks = 0;
+        while !game.enemies.is_empty() {
+            tick(&mut game, 1);
+            ticks += 1;
+            assert!(ticks < ticks_for(2.0));
+        }
+
+        let mut angles = (game.damage_indicators.iter())
+            .map(|indicator| indicator.direction.angle())
+            .collect::<Vec<_>>();
+        angles.sort_by(f64::total_cmp);
+
+        assert_eq!(angles.len(), 2);
+        assert!((angles[0] + PI / 2.0).abs() < 1e-6);
+        assert!(angles[1].abs() < 1e-6);
+
+        let opacity = game.damage_indicators[0].opacity();
+        tick(&mut game, ticks_for(DamageIndicator::LIFETIME / 2.0));
+        assert!(game.damage_indicators[0].opacity() < opacity);
+
+        tick(&mut game, ticks_for(DamageIndicator::LIFETIME / 2.0));
+        assert!(game.damage_indicators.is_empty());
+    }
+
+    fn health_after_crossfire(friendly_fire: bool) -> u32 {
+        let mut game = Game {
+            friendly_fire,
+            ..Game::with_seed(6)
+        };
+        game.add_turret(Turret {
+            position: Isometry2::new(vector![0.0, 6.0], 0.0),
+            ..Default::default()
+        });
+
+        let source = game.primary_turret().source;
+        game.projectiles.insert(Projectile {
+            source,
+            ..Projectile::new(
+                Isometry2::new(vector![0.0, 2.0], PI / 2.0),
+                &PROJECTILE_KINDS[0],
+                &mut Rng::new(0),
+            )
+        });
+
+        tick(&mut game, ticks_for(1.0));
+        game.health
+    }
+
+    #[test]
+    fn friendly_fire_decides_whether_shots_hurt_other_turrets() {
+        assert_eq!(health_after_crossfire(false), Game::STARTING_HEALTH);
+        assert_eq!(
+            health_after_crossfire(true),
+            Game::STARTING_HEALTH - Game::PROJECTILE_DAMAGE,
+        );
+    }
+
+    #[test]
+    fn spawner_waves_count_as_game_waves() {
+        let mut game = Game {
+            spawner: Some(Spawner::new([
+                Wave {
+                    enemies: vec![(0, 2)],
+                    spawn_interval: 0.5,
+                },
+                Wave {
+                    enemies: vec![(1, 1)],
+                    spawn_interval: 0.5,
+                },
+            ])),
+            invincible: true,
+            ..Game::with_seed(2)
+        };
+
+        tick(&mut game, 1);
+
+        assert_eq!(game.current_wave(), 1);
+        assert_eq!(game.enemies_remaining_in_wave(), 2);
+        assert_eq!(game.spawner.as_ref().unwrap().remaining_waves(), 1);
+
+        tick(&mut game, ticks_for(0.5));
+        game.enemies.clear();
+        tick(&mut game, 1);
+
+        assert_eq!(game.current_wave(), 1);
+        assert!(!game.is_wave_active());
+        assert!((game.drain_events()).any(|event| matches!(event, GameEvent::WaveCleared { .. })));
+
+        tick(&mut game, ticks_for(SpawnQueue::WAVE_BREAK));
+
+        assert_eq!(game.current_wave(), 2);
+        assert_eq!(game.enemies_remaining_in_wave(), 1);
+        assert_eq!(game.spawner.as_ref().unwrap().remaining_waves(), 0);
+    }
+
+    #[test]
+    fn run_summary_counts_a_scripted_run() {
+        let mut game = Game::with_seed(77);
+
+        game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::new(vector![8.0, 0.0], PI), &ENEMY_KINDS[0])
+        });
+
+        // Held just long enough for the first shot once the turret has charged
+        let shoot_ticks = ticks_for(Turret::default().projectile_kind.shoot_cooldown) as u64;
+
+        game.simulate(
+            ticks_for(2.0),
+            |tick| InputState {
+                mouse_position: point![8.0, 0.0],
+                shoot: tick < shoot_ticks,
+                parry: false,
+                camera_bounds: camera_bounds(),
+            },
+            DT,
+        );
+
+        let summary = game.run_summary();
+
+        assert_eq!(summary.seed, 77);
+        assert_eq!(summary.shots_fired, 1);
+        assert_eq!(summary.kills_by_kind, vec![("Red Circle".into(), 1)]);
+        assert_eq!(summary.accuracy, 1.0);
+        assert_eq!(summary.max_combo, 1);
+        assert!(game.enemies.is_empty());
+    }
+
+    /// Plays a one enemy wave, with the enemy either killed off far away or left to crash into
+    /// the turret, returning the events from the tick the wave was cleared
+    fn clear_wave(crash_into_turret: bool) -> Vec<GameEvent> {
+        let mut game = Game::with_seed(5);
+        let distance = if crash_into_turret { 3.0 } else { 30.0 };
+
+        game.spawn_queue
+            .schedule_wave([(Isometry2::new(vector![distance, 0.0], PI), &ENEMY_KINDS[0])]);
+
+        while game.is_wave_active() {
+            if !crash_into_turret && let Some(key) = game.enemies.keys().next() {
+                game.enemies.remove(key);
+            }
+
+            tick(&mut game, 1);
+        }
+
+        game.drain_events().collect()
+    }
+
+    #[test]
+    fn undamaged_wave_is_perfect() {
+        let events = clear_wave(false);
+
+        assert_eq!(
+            events,
+            [GameEvent::WaveCleared {
+                perfect: true,
+                bonus: Score::PERFECT_WAVE_BONUS + Score::FAST_WAVE_BONUS,
+            }]
+        );
+    }
+
+    #[test]
+    fn damaged_wave_is_not_perfect() {
+        let events = clear_wave(true);
+
+        assert_eq!(
+            events,
+            [GameEvent::WaveCleared {
+                perfect: false,
+                bonus: Score::FAST_WAVE_BONUS,
+            }]
+        );
+    }
+
+    #[test]
+    fn drained_events_are_only_reported_once() {
+        let mut game = Game::with_seed(5);
+        game.events.push(GameEvent::GameOver);
+
+        assert_eq!(
+            game.drain_events().collect::<Vec<_>>(),
+            [GameEvent::GameOver]
+        );
+        assert_eq!(game.drain_events().count(), 0);
+    }
+
+    /// Six red circles clustered around `(10, 0)`, facing away from the turret
+    fn clustered_game() -> Game {
+        let mut game = Game::with_seed(9);
+
+        for (x, y) in [
+            (9.0, 0.0),
+            (10.0, 0.0),
+            (11.0, 0.0),
+            (9.5, 1.0),
+            (10.5, 1.0),
+            (10.0, -1.0),
+        ] {
+            game.enemies.insert(Enemy::new(
+                Isometry2::new(vector![x, y], 0.0),
+                &ENEMY_KINDS[0],
+            ));
+        }
+
+        game
+    }
+
+    #[test]
+    fn region_queries_count_a_cluster() {
+        let game = clustered_game();
+
+        let count =
+            |center, radius, membership| game.count_enemies_in_circle(center, radius, membership);
+
+        assert_eq!(count(point![10.0, 0.0], 5.0, RegionMembership::Center), 6);
+        assert_eq!(count(point![10.0, 0.5], 0.2, RegionMembership::Center), 0);
+        assert_eq!(count(point![10.0, 0.5], 0.2, RegionMembership::Shape), 1);
+        assert_eq!(count(point![-10.0, 0.0], 5.0, RegionMembership::Shape), 0);
+
+        let left = BoundingBox {
+            min: point![5.0, -5.0],
+            max: point![9.25, 5.0],
+        };
+        assert_eq!(
+            game.enemies_in_aabb(left, RegionMembership::Center).len(),
+            1
+        );
+        assert_eq!(game.enemies_in_aabb(left, RegionMembership::Shape).len(), 2);
+    }
+
+    #[test]
+    fn region_queries_use_the_grid_rebuilt_at_the_end_of_each_tick() {
+        let mut game = clustered_game();
+        tick(&mut game, 1);
+        assert!(!game.grid.stale);
+
+        let count = |game: &Game, center| {
+            game.count_enemies_in_circle(center, 3.0, RegionMembership::Center)
+        };
+        assert_eq!(count(&game, point![10.0, 0.0]), 6);
+
+        // Enemies moved directly aren't seen until the grid is marked stale
+        let key = game.enemies.keys().next().unwrap();
+        game.enemies[key].object.position.translation.vector = vector![-20.0, 0.0];
+        assert_eq!(count(&game, point![-20.0, 0.0]), 0);
+
+        game.grid.stale = true;
+        assert_eq!(count(&game, point![-20.0, 0.0]), 1);
+        assert_eq!(count(&game, point![10.0, 0.0]), 5);
+
+        // Changes made through the game mark it stale themselves
+        tick(&mut game, 1);
+        game.remove_enemy(key);
+        game.spawn_enemy(0, Isometry2::new(vector![30.0, 0.0], 0.0));
+        assert!(game.grid.stale);
+        assert_eq!(count(&game, point![-20.0, 0.0]), 0);
+        assert_eq!(count(&game, point![30.0, 0.0]), 1);
+    }
+
+    /// Two red circles side by side at the same depth in front of a shot that loses half its
+    /// damage on each hit, so that which one takes the full hit only depends on the order
+    /// collisions are resolved in. With `reuse_slots`, the second enemy takes a slot freed before
+    /// the first was inserted, so its key sorts first.
+    fn tied_targets(reuse_slots: bool) -> (Game, [EnemyKey; 2]) {
+        let mut game = Game {
+            stable_order: true,
+            ..Game::with_seed(3)
+        };
+
+        let at = |y| Isometry2::new(vector![5.0, y], 0.0);
+
+        let placeholder = reuse_slots.then(|| stationary_enemy(&mut game, 0, at(-20.0)));
+        let first = stationary_enemy(&mut game, 0, at(0.3));
+        if let Some(placeholder) = placeholder {
+            game.enemies.remove(placeholder);
+        }
+        let second = stationary_enemy(&mut game, 0, at(-0.3));
+
+        assert_eq!(first < second, !reuse_slots);
+
+        let kind = ProjectileKind {
+            properties: ProjectileProperties {
+                damage: 2,
+                pierce_falloff: 0.5,
+                ..PROJECTILE_KINDS[0].properties
+            },
+            ..PROJECTILE_KINDS[0].clone()
+        };
+
+        game.insert_projectile(Projectile::new(
+            Isometry2::new(vector![3.5, 0.0], 0.0),
+            &kind,
+            &mut Rng::new(0),
+        ));
+
+        (game, [first, second])
+    }
+
+    #[test]
+    fn stable_order_ignores_key_reuse() {
+        let health_after = |reuse_slots| {
+            let (mut game, keys) = tied_targets(reuse_slots);
+            tick(&mut game, 40);
+            keys.map(|key| game.enemies[key].health)
+        };
+
+        let fresh = health_after(false);
+        let reused = health_after(true);
+
+        assert_eq!(fresh, reused);
+        assert!(fresh[0] < fresh[1]);
+    }
+
+    /// A row of three overlapping red circles heading for the turret, created left to right or
+    /// right to left with a freed slot shuffling the keys. A single separation pass leaves them
+    /// overlapping, so the order that pass visits them in would show.
+    fn crowded_row(reversed: bool) -> Game {
+        let mut game = Game {
+            double_buffered_enemies: true,
+            separation_iterations: 1,
+            ..Game::with_seed(4)
+        };
+
+        let mut xs = [10.0, 10.6, 11.3];
+        if reversed {
+            xs.reverse();
+
+            let placeholder = game.enemies.insert(Enemy::new(
+                Isometry2::new(vector![-30.0, 0.0], 0.0),
+                &ENEMY_KINDS[0],
+            ));
+            game.enemies.remove(placeholder);
+        }
+
+        for x in xs {
+            game.enemies.insert(Enemy::new(
+                Isometry2::new(vector![x, 0.0], PI),
+                &ENEMY_KINDS[0],
+            ));
+        }
+
+        game
+    }
+
+    fn enemy_states(game: &Game) -> Vec<[u64; 4]> {
+        let mut states = (game.enemies.values())
+            .map(|enemy| {
+                [
+                    enemy.position.translation.x,
+                    enemy.position.translation.y,
+                    enemy.linear_velocity.x,
+                    enemy.linear_velocity.y,
+                ]
+                .map(f64::to_bits)
+            })
+            .collect::<Vec<_>>();
+        states.sort_unstable();
+        states
+    }
+
+    #[test]
+    fn double_buffered_enemies_ignore_update_order() {
+        let mut forwards = crowded_row(false);
+        let mut backwards = crowded_row(true);
+
+        tick(&mut forwards, 1);
+        tick(&mut backwards, 1);
+
+        assert_eq!(forwards.enemies.len(), 3);
+        assert_eq!(enemy_states(&forwards), enemy_states(&backwards));
+    }
+
+    /// Plays 20 seconds of an auto-spawning game from `seed`
+    fn auto_spawned_run(seed: u64) -> Game {
+        let mut game = auto_spawning_game(seed);
+        play_sweeping(&mut game, ticks_for(20.0));
+
+        game
+    }
+
+    fn auto_spawning_game(seed: u64) -> Game {
+        Game {
+            auto_spawner: Some(AutoSpawner::default()),
+            ..Game::with_seed(seed)
+        }
+    }
+
+    /// Sweeps the aim around while shooting the whole time
+    fn play_sweeping(game: &mut Game, ticks: usize) {
+        game.simulate(
+            ticks,
+            |tick| {
+                let angle = tick as f64 * DT;
+                InputState {
+                    mouse_position: point![angle.cos(), angle.sin()] * 10.0,
+                    shoot: true,
+                    parry: false,
+                    camera_bounds: camera_bounds(),
+                }
+            },
+            DT,
+        );
+    }
+
+    fn position_bits(positions: impl Iterator<Item = Isometry2<f64>>) -> Vec<[u64; 3]> {
+        positions
+            .map(|position| {
+                [
+                    position.translation.x,
+                    position.translation.y,
+                    position.rotation.angle(),
+                ]
+                .map(f64::to_bits)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn games_with_the_same_seed_play_out_the_same() {
+        let (first, second) = (auto_spawned_run(31), auto_spawned_run(31));
+
+        assert!(!first.stats.kills_by_kind.is_empty());
+        assert!(!first.particles.is_empty());
+        assert_eq!(first.enemies.len(), second.enemies.len());
+        assert_eq!(first.particles.len(), second.particles.len());
+        assert_eq!(
+            position_bits(first.enemies.values().map(|enemy| enemy.position)),
+            position_bits(second.enemies.values().map(|enemy| enemy.position)),
+        );
+        assert_eq!(
+            position_bits(first.particles.values().map(|particle| particle.position)),
+            position_bits(second.particles.values().map(|particle| particle.position)),
+        );
+        assert_eq!(first.health, second.health);
+
+        let other = auto_spawned_run(32);
+        assert_ne!(
+            position_bits(first.enemies.values().map(|enemy| enemy.position)),
+            position_bits(other.enemies.values().map(|enemy| enemy.position)),
+        );
+    }
+
+    #[test]
+    fn cosmetic_seed_does_not_change_gameplay() {
+        let mut plain = auto_spawning_game(31);
+        let mut reseeded = Game {
+            cosmetic_rng: Rng::new(12345),
+            ..auto_spawning_game(31)
+        };
+
+        for _ in 0..20 {
+            play_sweeping(&mut plain, ticks_for(1.0));
+            play_sweeping(&mut reseeded, ticks_for(1.0));
+
+            assert_eq!(plain.state_hash(), reseeded.state_hash());
+        }
+
+        assert!(!plain.stats.kills_by_kind.is_empty());
+        assert_ne!(
+            position_bits(plain.particles.values().map(|particle| particle.position)),
+            position_bits(
+                reseeded
+                    .particles
+                    .values()
+                    .map(|particle| particle.position)
+            ),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "has a removed enemy")]
+    fn invariant_check_catches_removed_enemies_in_projectiles() {
+        let mut game = Game::with_seed(6);
+        let enemy = game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![10.0, 0.0], PI),
+            &ENEMY_KINDS[0],
+        ));
+        let projectile = game.projectiles.insert(Projectile::new(
+            Isometry2::identity(),
+            &PROJECTILE_KINDS[0],
+            &mut game.cosmetic_rng,
+        ));
+
+        game.check_invariants();
+
+        game.projectiles[projectile].enemies_colliding.push(enemy);
+        game.enemies.remove(enemy);
+        game.check_invariants();
+    }
+
+    fn marching_game() -> Game {
+        let mut game = Game::with_seed(7);
+        game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::new(vector![10.0, 0.0], PI), &ENEMY_KINDS[0])
+        });
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 5.0], 0.0),
+            &PROJECTILE_KINDS[0],
+            &mut game.cosmetic_rng,
+        ));
+
+        game
+    }
+
+    #[test]
+    fn bad_time_steps_do_not_teleport_anything() {
+        let mut game = marching_game();
+        let state = game.state_hash();
+
+        for dt in [0.0, -1.0, f64::NAN] {
+            game.tick_with_input(point![0.0, 10.0], camera_bounds(), dt);
+        }
+        assert_eq!(game.state_hash(), state);
+        assert_eq!(game.tick_count, 0);
+
+        let mut spiked = marching_game();
+        spiked.tick_with_input(point![0.0, 10.0], camera_bounds(), 10.0);
+        game.tick_with_input(point![0.0, 10.0], camera_bounds(), Game::MAX_DT);
+
+        assert_eq!(spiked.state_hash(), game.state_hash());
+        assert!(
+            spiked
+                .enemies
+                .values()
+                .all(|enemy| enemy.position.translation.x > 9.0)
+        );
+    }
+
+    /// Render targets live on the GPU, so this needs the OpenGL context of a real window, and
+    /// macroquad has no headless backend to fall back on. Run it on a machine with a display with
+    /// `cargo test captured_frames_show_the_scene -- --ignored`.
+    #[test]
+    #[ignore = "needs a display to open a window"]
+    fn captured_frames_show_the_scene() {
+        let drawn_pixels = Rc::new(Cell::new(None));
+
+        macroquad::Window::new("capture test", {
+            let drawn_pixels = drawn_pixels.clone();
+
+            async move {
+                let mut game = Game::with_seed(10);
+                game.enemies.insert(Enemy::new(
+                    Isometry2::new(vector![3.0, 0.0], PI),
+                    &ENEMY_KINDS[0],
+                ));
+
+                let target = macroquad::texture::render_target(32, 32);
+                let camera = Camera2D {
+                    zoom: [2.0 / 10.0, -2.0 / 10.0].into(),
+                    ..Default::default()
+                };
+                let image = game.capture_image(&target, &camera);
+
+                let background = Game::BACKGROUND_COLOR;
+                drawn_pixels.set(Some(
+                    (0..image.width() as u32)
+                        .flat_map(|x| (0..image.height() as u32).map(move |y| (x, y)))
+                        .filter(|&(x, y)| image.get_pixel(x, y) != background)
+                        .count(),
+                ));
+            }
+        });
+
+        assert!(drawn_pixels.get().is_some_and(|pixels| pixels > 0));
+    }
+
+    #[test]
+    fn simulating_matches_a_manual_loop() {
+        let input = |tick: u64| InputState {
+            mouse_position: point![(tick as f64 * 0.05).cos(), (tick as f64 * 0.05).sin()] * 10.0,
+            shoot: tick % 30 < 15,
+            parry: tick == 40,
+            camera_bounds: camera_bounds(),
+        };
+
+        let mut simulated = auto_spawning_game(13);
+        simulated.simulate(ticks_for(5.0), input, DT);
+
+        let mut manual = auto_spawning_game(13);
+        for _ in 0..ticks_for(5.0) {
+            let input = input(manual.tick_count);
+
+            manual.primary_turret_mut().input.tick_with(input.shoot, DT);
+            if input.parry {
+                manual.start_parry();
+            }
+            manual.tick_with_input(input.mouse_position, input.camera_bounds, DT);
+        }
+
+        assert_eq!(simulated.tick_count, ticks_for(5.0) as u64);
+        assert!(simulated.stats.shots_fired > 0);
+        assert_eq!(simulated.state_hash(), manual.state_hash());
+    }
+
+    #[test]
+    fn sandbox_controls_spawn_clear_and_protect() {
+        let mut game = Game::with_seed(14);
+        let position = Isometry2::new(vector![8.0, 0.0], PI);
+
+        let key = game.spawn_enemy(2, position).unwrap();
+        assert_eq!(game.enemies[key].name, ENEMY_KINDS[2].name);
+        assert_eq!(game.enemies[key].position, position);
+        assert!(game.spawn_enemy(ENEMY_KINDS.len(), position).is_none());
+
+        game.spawn_queue.schedule(position, &ENEMY_KINDS[0]);
+        game.projectiles.insert(Projectile::new(
+            Isometry2::identity(),
+            &PROJECTILE_KINDS[0],
+            &mut game.cosmetic_rng,
+        ));
+
+        game.clear_enemies();
+        assert!(game.enemies.is_empty());
+        assert!(game.spawn_queue.telegraphs.is_empty());
+        assert_eq!(game.projectiles.len(), 1);
+
+        game.set_invincible(true);
+        game.damage_turret(1, point![5.0, 0.0]);
+        assert_eq!(game.health, Game::STARTING_HEALTH);
+
+        game.set_invincible(false);
+        game.damage_turret(1, point![5.0, 0.0]);
+        assert_eq!(game.health, Game::STARTING_HEALTH - 1);
+
+        let enemy = Enemy::new(position, &ENEMY_KINDS[0]);
+        enemy.explode_with_seed(
+            point![8.0, 0.0],
+            vector![-5.0, 0.0],
+            &game.explosion,
+            &mut game.particles,
+            14,
+        );
+        game.enemies.insert(enemy);
+        game.trail_zones.push(TrailZone::new(
+            point![2.0, 0.0],
+            &PROJECTILE_KINDS[4].properties.trail.unwrap(),
+            None,
+        ));
+        assert!(!game.particles.is_empty());
+        assert!(!game.damage_indicators.is_empty());
+
+        game.clear_all();
+        assert!(game.enemies.is_empty());
+        assert!(game.projectiles.is_empty());
+        assert!(game.particles.is_empty());
+        assert!(game.trail_zones.is_empty());
+        assert!(game.damage_indicators.is_empty());
+    }
+
+    #[test]
+    fn spawn_grid_places_enemies_row_by_row() {
+        let mut game = Game::with_seed(19);
+        let origin = Isometry2::new(vector![5.0, 1.0], FRAC_PI_2);
+
+        let keys = game.spawn_grid(1, origin, vector![2.0, 3.0], 3, 2).unwrap();
+        assert_eq!(keys.len(), 6);
+        assert_eq!(game.enemies.len(), 6);
+
+        // Local x is world y and local y is world -x when facing up
+        let expected = [
+            (5.0, 1.0),
+            (5.0, 3.0),
+            (5.0, 5.0),
+            (2.0, 1.0),
+            (2.0, 3.0),
+            (2.0, 5.0),
+        ];
+        for (key, (x, y)) in keys.into_iter().zip(expected) {
+            let enemy = &game.enemies[key];
+
+            assert!((enemy.position.translation.vector - vector![x, y]).norm() < 1e-12);
+            assert_eq!(enemy.direction, origin.rotation);
+            assert_eq!(enemy.name, ENEMY_KINDS[1].name);
+        }
+
+        assert!(
+            game.spawn_grid(ENEMY_KINDS.len(), origin, vector![1.0, 1.0], 2, 2)
+                .is_none()
+        );
+        assert_eq!(game.enemies.len(), 6);
+    }
+
+    fn wrapping_game() -> Game {
+        Game {
+            arena: Some(WrapArena {
+                size: vector![20.0, 20.0],
+                wrap_projectiles: false,
+                maximum_projectile_wraps: 0,
+            }),
+            ..Game::with_seed(21)
+        }
+    }
+
+    #[test]
+    fn enemies_wrap_around_the_arena_and_get_hit_across_the_seam() {
+        let mut game = wrapping_game();
+        let crossing = game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::new(vector![9.5, 5.0], 0.0), &ENEMY_KINDS[0])
+        });
+
+        tick(&mut game, ticks_for(0.5));
+
+        let position = game.enemies[crossing].position.translation;
+        assert!(position.x < -8.0);
+        assert!((position.y - 5.0).abs() < 1e-9);
+
+        let mut game = wrapping_game();
+        let straddling = stationary_enemy(&mut game, 0, Isometry2::new(vector![9.9, -3.0], 0.0));
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![-6.0, -3.0], PI),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, ticks_for(0.5));
+
+        assert!(
+            (game.enemies.get(straddling))
+                .is_none_or(|enemy| enemy.health < enemy.properties.maximum_health)
+        );
+    }
+
+    #[test]
+    fn distant_enemies_widen_the_framing_until_removed() {
+        let mut game = Game::with_seed(1);
+        game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![5.0, 0.0], PI),
+            &ENEMY_KINDS[0],
+        ));
+        let (center, half_size) = game.framing();
+
+        let distant = game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![0.0, 30.0], -FRAC_PI_2),
+            &ENEMY_KINDS[0],
+        ));
+        let (wide_center, wide_half_size) = game.framing();
+        assert!(wide_half_size.y > half_size.y + 10.0);
+        assert_eq!(wide_half_size.x, half_size.x);
+        assert!(wide_center.y > center.y);
+
+        // Enemies too far from every turret are left out of the shot
+        game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![0.0, -100.0], FRAC_PI_2),
+            &ENEMY_KINDS[0],
+        ));
+        assert_eq!(game.framing(), (wide_center, wide_half_size));
+
+        game.enemies.remove(distant);
+        assert_eq!(game.framing(), (center, half_size));
+    }
+
+    #[test]
+    fn particles_are_drawn_in_their_own_layer() {
+        let mut game = Game::with_seed(1);
+        let enemy = Enemy::new(Isometry2::new(vector![5.0, 0.0], PI), &ENEMY_KINDS[0]);
+        enemy.explode_with_seed(
+            point![5.0, 0.0],
+            vector![10.0, 0.0],
+            &game.explosion,
+            &mut game.particles,
+            1,
+        );
+        let fragments = game.particles.len();
+        assert!(fragments > 0);
+        assert_eq!(
+            game.particles_in_layer(ParticleLayer::AboveEntities)
+                .count(),
+            fragments
+        );
+        assert_eq!(
+            game.particles_in_layer(ParticleLayer::BehindEntities)
+                .count(),
+            0
+        );
+
+        // Glitter left behind moving projectiles is ambient
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 1.0], -FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+        tick(&mut game, 10);
+
+        let drawn = [ParticleLayer::BehindEntities, ParticleLayer::AboveEntities]
+            .map(|layer| game.particles_in_layer(layer).count());
+        assert!(drawn[0] > 0);
+        assert_eq!(drawn.iter().sum::<usize>(), game.particles.len());
+    }
+
+    #[test]
+    fn collisions_are_predicted_without_moving_anything() {
+        let mut game = Game::with_seed(1);
+        let enemy = stationary_enemy(&mut game, 3, Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2));
+        let shoot = |game: &mut Game, x: f64| {
+            game.projectiles.insert(Projectile::new(
+                Isometry2::new(vector![x, 2.0], FRAC_PI_2),
+                &PROJECTILE_KINDS[0],
+                &mut Rng::new(0),
+            ))
+        };
+        let hitting = shoot(&mut game, 0.0);
+        // Passes just outside the corners of the spinning square
+        shoot(&mut game, 1.1);
+
+        let hash = game.state_hash().unwrap();
+        assert_eq!(game.predict_collisions(0.0), []);
+        assert_eq!(
+            game.predict_collisions(0.1),
+            [(hitting.into(), enemy.into())]
+        );
+        assert_eq!(game.state_hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn fast_projectiles_are_predicted_to_hit_enemies_they_would_pass_through() {
+        let mut game = Game::with_seed(1);
+        let enemy = stationary_enemy(&mut game, 0, Isometry2::new(vector![0.0, 4.0], 0.0));
+
+        let mut kind = PROJECTILE_KINDS[0].clone();
+        kind.properties.speed = 200.0;
+        let projectile = game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 2.0], FRAC_PI_2),
+            &kind,
+            &mut Rng::new(0),
+        ));
+
+        // The projectile starts in front of the enemy and ends up well past it
+        let end = game.projectiles[projectile].predicted_object(0.05).position;
+        assert!(end.translation.y > 8.0);
+
+        assert_eq!(
+            game.predict_collisions(0.05),
+            [(projectile.into(), enemy.into())]
+        );
+    }
+
+    /// The alpha of the screen flash each tick after shooting `kind` at a red square just ahead
+    fn flash_after_shooting(kind: &ProjectileKind, reduced_flashing: bool) -> Vec<f32> {
+        let mut game = Game {
+            reduced_flashing,
+            ..Game::with_seed(1)
+        };
+        stationary_enemy(&mut game, 3, Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2));
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 2.0], FRAC_PI_2),
+            kind,
+            &mut Rng::new(0),
+        ));
+
+        (0..240)
+            .map(|_| {
+                tick(&mut game, 1);
+                game.screen_flash().a
+            })
+            .collect()
+    }
+
+    #[test]
+    fn heavy_hits_flash_the_screen_then_fade() {
+        let heavy = PROJECTILE_KINDS
+            .iter()
+            .find(|kind| kind.impact_flash.is_some())
+            .unwrap();
+
+        let flash = flash_after_shooting(heavy, false);
+        let brightest = flash.iter().copied().fold(0.0, f32::max);
+        assert!(brightest > 0.1);
+        assert!(*flash.last().unwrap() < 0.01);
+
+        let peak = flash.iter().position(|&alpha| alpha == brightest).unwrap();
+        assert!(flash[peak..].windows(2).all(|pair| pair[1] <= pair[0]));
+
+        assert!(
+            flash_after_shooting(heavy, true)
+                .iter()
+                .all(|&alpha| alpha == 0.0)
+        );
+        assert!(
+            flash_after_shooting(&PROJECTILE_KINDS[0], false)
+                .iter()
+                .all(|&alpha| alpha == 0.0)
+        );
+    }
+
+    #[test]
+    fn shot_previews_list_hits_and_kills_in_order() {
+        let mut game = Game::with_seed(1);
+        let mut line_up = |position: Vector2<f64>, kind: usize, damage: u32| {
+            let key = stationary_enemy(&mut game, kind, Isometry2::new(position, PI));
+            game.enemies[key].hit(damage, None, 0);
+
+            key
+        };
+        // Placed out of order, so hits have to be sorted by distance
+        let far = line_up(vector![0.0, 10.0], 3, 7);
+        let near = line_up(vector![0.0, 4.0], 0, 0);
+        let middle = line_up(vector![0.0, 7.0], 4, 0);
+        line_up(vector![5.0, 7.0], 0, 0);
+
+        let mut kind = PROJECTILE_KINDS[0].clone();
+        kind.properties.pierce_falloff = 0.5;
+        let muzzle = Isometry2::new(vector![0.0, 1.0], FRAC_PI_2);
+
+        let hash = game.state_hash().unwrap();
+        let preview = game.preview_shot(muzzle, &kind);
+        assert_eq!(game.state_hash().unwrap(), hash);
+
+        let hit = |enemy: EnemyKey, damage, kills| PreviewHit {
+            enemy: enemy.into(),
+            damage,
+            kills,
+        };
+        assert_eq!(
+            preview.hits,
+            [hit(near, 4, true), hit(middle, 2, false), hit(far, 1, true)]
+        );
+
+        // The real shot does what was previewed
+        game.projectiles
+            .insert(Projectile::new(muzzle, &kind, &mut Rng::new(0)));
+        tick(&mut game, 120);
+        let survivors = game.enemies.keys().map(EntityId::from).collect::<Vec<_>>();
+        assert!(preview.kills().all(|enemy| !survivors.contains(&enemy)));
+        assert_eq!(survivors.len(), 2);
+        assert_eq!(game.enemies[middle].health, 10);
+    }
+
+    #[test]
+    fn explosions_past_the_limit_wait_for_later_ticks() {
+        let mut game = Game {
+            invincible: true,
+            explosion: ExplosionParameters {
+                maximum_per_tick: Some(3),
+                ..ExplosionParameters::default()
+            },
+            ..Game::with_seed(27)
+        };
+
+        for index in 0..10 {
+            stationary_enemy(
+                &mut game,
+                3,
+                Isometry2::new(vector![0.0, 0.0], index as f64),
+            );
+        }
+
+        tick(&mut game, 1);
+        assert!(game.enemies.is_empty());
+        assert_eq!(game.explosion_queue.len(), 10);
+
+        let mut queued = game.explosion_queue.len();
+        let mut particles = game.particles.len();
+        while queued > 0 {
+            tick(&mut game, 1);
+
+            assert!(queued - game.explosion_queue.len() <= 3);
+            assert!(game.particles.len() > particles);
+            queued = game.explosion_queue.len();
+            particles = game.particles.len();
+        }
+    }
+
+    #[test]
+    fn games_away_from_the_world_origin_spawn_and_collide_around_their_own() {
+        let origin = Isometry2::new(vector![100.0, -50.0], FRAC_PI_2);
+
+        let mut game = Game {
+            spawner: Some(Spawner::new([Wave {
+                enemies: vec![(0, 8)],
+                spawn_interval: 0.0,
+            }])),
+            ..Game::with_origin(origin)
+        };
+        assert!(
+            game.turrets
+                .values()
+                .all(|turret| turret.position == origin)
+        );
+
+        tick(&mut game, 1);
+        assert_eq!(game.enemies.len(), 8);
+        for enemy in game.enemies.values() {
+            let local = origin.inv_mul(&enemy.position);
+
+            assert!(!camera_bounds().is_colliding(&enemy.shape, local));
+            assert!(local.translation.vector.norm() < 30.0);
+        }
+
+        let mut game = Game::with_origin(origin);
+        let position = game.world_position(Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2));
+        let key = stationary_enemy(&mut game, 3, position);
+        game.projectiles.insert(Projectile::new(
+            game.world_position(Isometry2::new(vector![0.0, 2.0], FRAC_PI_2)),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, 30);
+        assert!(game.enemies[key].health < game.enemies[key].properties.maximum_health);
+
+        game.enemies[key].properties.speed = ENEMY_KINDS[3].properties.speed;
+        tick(&mut game, ticks_for(5.0));
+        assert!(game.enemies.is_empty());
+        assert!(game.health < Game::STARTING_HEALTH);
+    }
+
+    #[test]
+    fn entity_ids_resolve_until_their_entity_is_removed() {
+        let mut game = Game::with_seed(4);
+        let positions = [
+            Isometry2::new(vector![3.0, 0.0], 0.0),
+            Isometry2::new(vector![-3.0, 1.0], PI),
+        ];
+        let keys =
+            positions.map(|position| game.enemies.insert(Enemy::new(position, &ENEMY_KINDS[0])));
+        let projectile = game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 2.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+
+        for (key, position) in keys.into_iter().zip(positions) {
+            let id = EntityId::from(key);
+
+            assert_eq!(game.object(id).unwrap().position, position);
+            assert_eq!(game.transform(id).unwrap().position, position);
+        }
+        assert_eq!(
+            game.transform(projectile.into()).unwrap().position,
+            game.projectiles[projectile].position
+        );
+
+        game.enemies.remove(keys[0]);
+        game.projectiles.remove(projectile);
+
+        assert!(game.object(keys[0].into()).is_none());
+        assert!(game.transform(keys[0].into()).is_none());
+        assert!(game.transform(projectile.into()).is_none());
+        assert_eq!(game.object(keys[1].into()).unwrap().position, positions[1]);
+    }
+
+    #[test]
+    fn the_reticle_snaps_to_enemies_within_the_threshold() {
+        let mut game = Game {
+            reticle: Some(ReticleSnapping::default()),
+            ..Game::with_seed(8)
+        };
+        game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![5.0, 5.0], 0.0),
+            &ENEMY_KINDS[0],
+        ));
+        game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![6.0, 7.0], 0.0),
+            &ENEMY_KINDS[0],
+        ));
+
+        assert_eq!(game.reticle_position(point![5.5, 5.8]), point![5.0, 5.0]);
+        assert_eq!(game.reticle_position(point![5.9, 6.5]), point![6.0, 7.0]);
+        assert_eq!(game.reticle_position(point![9.0, 9.0]), point![9.0, 9.0]);
+
+        game.reticle = Some(ReticleSnapping {
+            strength: 0.5,
+            grid_spacing: Some(2.0),
+            ..ReticleSnapping::default()
+        });
+        assert_eq!(game.reticle_position(point![5.0, 6.0]), point![5.0, 5.5]);
+        assert_eq!(game.reticle_position(point![9.0, 9.0]), point![9.5, 9.5]);
+
+        game.reticle = None;
+        assert_eq!(game.reticle_position(point![5.5, 5.8]), point![5.5, 5.8]);
+    }
+
+    #[test]
+    fn overlapping_circles_are_pushed_apart() {
+        let mut game = Game::with_seed(9);
+        let place = |game: &mut Game, kind: usize, x: f64, y: f64| {
+            stationary_enemy(game, kind, Isometry2::new(vector![x, y], FRAC_PI_2))
+        };
+
+        let circles = [place(&mut game, 0, 5.0, 0.0), place(&mut game, 0, 5.4, 0.0)];
+        let squares = [
+            place(&mut game, 3, -5.0, 0.0),
+            place(&mut game, 3, -5.4, 0.0),
+        ];
+        let hit = [place(&mut game, 0, 0.0, 8.0), place(&mut game, 0, 0.4, 8.0)];
+        game.enemies[hit[0]].time_since_hit = 0.0;
+
+        let distance = |game: &Game, [a, b]: [EnemyKey; 2]| {
+            nalgebra::distance(
+                &game.enemies[a].position.translation.vector.into(),
+                &game.enemies[b].position.translation.vector.into(),
+            )
+        };
+        let x = |game: &Game, key: EnemyKey| game.enemies[key].position.translation.x;
+
+        tick(&mut game, 1);
+
+        assert!(distance(&game, circles) > 1.0 - 1e-6);
+        assert!((distance(&game, squares) - 0.4).abs() < 1e-9);
+
+        let (moved_hit, moved_unhit) = (-x(&game, hit[0]), x(&game, hit[1]) - 0.4);
+        assert!(moved_unhit > 0.0);
+        assert!(moved_hit < moved_unhit * 0.1);
+    }
+
+    #[test]
+    fn shadows_are_cast_under_every_enemy_when_enabled() {
+        let mut game = Game::with_seed(10);
+        for (index, x) in [-4.0, 0.0, 4.0, 8.0].into_iter().enumerate() {
+            game.enemies.insert(Enemy::new(
+                Isometry2::new(vector![x, 6.0], index as f64),
+                &ENEMY_KINDS[index],
+            ));
+        }
+        assert!(game.shadow_primitives().is_empty());
+
+        game.shadows = true;
+        let shadows = game.shadow_primitives();
+        assert_eq!(shadows.len(), game.turrets.len() + game.enemies.len());
+
+        let casters = (game.turrets.values())
+            .map(|turret| (turret.shape, turret.position))
+            .chain((game.enemies.values()).map(|enemy| (enemy.shape, enemy.position)));
+
+        for (shadow, (shape, position)) in shadows.iter().zip(casters) {
+            let offset = shadow.position.translation.vector - position.translation.vector;
+
+            assert!((offset - Shadow::OFFSET).norm() < 1e-9);
+            assert_eq!(shadow.position.rotation, position.rotation);
+
+            let scale = shadow.shape.bounding_radius() / shape.bounding_radius();
+            assert!((scale - 1.0).abs() <= Shadow::PULSE_AMOUNT + 1e-9);
+        }
+    }
+
+    /// A game with an enemy projectile `1.5` above the turret flying down at it, after enough
+    /// ticks for it to arrive
+    fn incoming_shot(parry: bool) -> Game {
+        let mut game = Game::with_seed(11);
+        let key = game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 1.5], -FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+        game.projectiles[key].faction = Faction::Enemy;
+        game.projectiles[key].source = SourceId(99);
+
+        if parry {
+            game.start_parry();
+        }
+        tick(&mut game, 12);
+
+        game
+    }
+
+    #[test]
+    fn parried_enemy_shots_are_reflected_instead_of_hurting() {
+        let unparried = incoming_shot(false);
+        assert!(unparried.health < Game::STARTING_HEALTH);
+        assert!(unparried.projectiles.is_empty());
+
+        let parried = incoming_shot(true);
+        assert_eq!(parried.health, Game::STARTING_HEALTH);
+
+        let projectile = parried.projectiles.values().next().unwrap();
+        assert_eq!(projectile.faction, Faction::Player);
+        assert_eq!(projectile.source, parried.primary_turret().source);
+        assert!((projectile.direction.angle() - FRAC_PI_2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn every_turret_aims_at_its_own_target_and_can_be_reached() {
+        let mut game = Game::with_seed(14);
+        let primary = game.primary_turret_key;
+        let second = game.add_turret(Turret {
+            position: Isometry2::new(vector![10.0, 0.0], 0.0),
+            target: point![10.0, -5.0],
+            ..Default::default()
+        });
+        let enemy = game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(
+                Isometry2::new(vector![10.0, 3.0], -FRAC_PI_2),
+                &ENEMY_KINDS[0],
+            )
+        });
+
+        tick(&mut game, 60);
+
+        assert_eq!(game.primary_turret().source, game.turrets[primary].source);
+        assert_eq!(game.turrets[primary].target, point![0.0, 10.0]);
+        assert_eq!(game.turrets[second].target, point![10.0, -5.0]);
+        assert!((game.turrets[primary].position.rotation.angle() - FRAC_PI_2).abs() < 1e-3);
+        assert!((game.turrets[second].position.rotation.angle() + FRAC_PI_2).abs() < 1e-3);
+
+        tick(&mut game, ticks_for(5.0));
+        assert!(!game.enemies.contains_key(enemy));
+        assert!(game.health < Game::STARTING_HEALTH);
+    }
+
+    #[test]
+    fn added_turrets_get_their_own_sources_and_only_the_chosen_one_is_primary() {
+        let mut game = Game::default();
+        let first = game.primary_turret_key;
+        let second = game.add_turret(Turret::default());
+        game.turrets.remove(first);
+        let third = game.add_turret(Turret::default());
+        game.primary_turret_key = second;
+
+        assert_eq!(game.turrets.keys().next(), Some(third));
+        assert_eq!(game.primary_turret().source, game.turrets[second].source);
+        assert_ne!(game.turrets[second].source, game.turrets[third].source);
+        assert!((game.turrets.values()).all(|turret| turret.source != SourceId::default()));
+        game.check_invariants();
     }
 }
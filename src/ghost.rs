@@ -0,0 +1,91 @@
+use nalgebra::Isometry2;
+
+use crate::turret::Turret;
+
+/// The path of the turret over a run, stored only on the ticks where it moved
+#[derive(Clone, Debug, Default)]
+pub struct GhostRecording {
+    pub frames: Vec<(u64, Isometry2<f64>)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GhostPlayer {
+    pub recording: GhostRecording,
+    pub turret: Turret,
+}
+
+impl GhostRecording {
+    pub fn record(&mut self, tick: u64, position: Isometry2<f64>) {
+        if self
+            .frames
+            .last()
+            .is_some_and(|&(last_tick, last_position)| {
+                last_tick >= tick || last_position == position
+            })
+        {
+            return;
+        }
+
+        self.frames.push((tick, position));
+    }
+
+    /// Returns the most recently recorded position at or before `tick`
+    #[must_use]
+    pub fn sample(&self, tick: u64) -> Option<Isometry2<f64>> {
+        let index = self.frames.partition_point(|&(frame, _)| frame <= tick);
+
+        Some(self.frames.get(index.checked_sub(1)?)?.1)
+    }
+}
+
+impl GhostPlayer {
+    pub const OPACITY: f32 = 0.35;
+
+    pub fn new(recording: GhostRecording) -> Self {
+        Self {
+            recording,
+            turret: Turret {
+                time_since_shoot: f64::INFINITY,
+                time_since_recharged: f64::INFINITY,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn draw(&self, tick: u64) {
+        let Some(position) = self.recording.sample(tick) else {
+            return;
+        };
+
+        Turret {
+            position,
+            ..self.turret.clone()
+        }
+        .draw_with_opacity(Self::OPACITY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::vector;
+
+    use super::*;
+
+    #[test]
+    fn sampling_returns_the_recorded_position() {
+        let turned = Isometry2::rotation(1.0);
+        let moved = Isometry2::new(vector![2.0, 0.0], 1.0);
+
+        let mut recording = GhostRecording::default();
+        recording.record(3, turned);
+        recording.record(4, turned);
+        recording.record(7, moved);
+
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.sample(2), None);
+        assert_eq!(recording.sample(3), Some(turned));
+        assert_eq!(recording.sample(6), Some(turned));
+        assert_eq!(recording.sample(7), Some(moved));
+        assert_eq!(recording.sample(100), Some(moved));
+    }
+}
@@ -0,0 +1,263 @@
+//! Golden image checks for enemy explosions.
+//!
+//! Explosions are drawn on the CPU, so the check runs as part of `cargo test` without a window.
+//! It compares against the references in [`REFERENCE_DIRECTORY`], and after an intentional visual
+//! change `cargo test regenerate_golden_images -- --ignored` overwrites them.
+//! `assets/golden/README.md` covers the workflow.
+
+use std::{f64::consts::TAU, fs};
+
+use macroquad::{
+    color::{Color, colors},
+    texture::Image,
+};
+use nalgebra::{Isometry2, Point2, Vector2, point, vector};
+use slotmap::HopSlotMap;
+
+use crate::{
+    enemy::{ENEMY_KINDS, Enemy, EnemyKind, ExplosionParameters},
+    particle::Particle,
+};
+
+pub const REFERENCE_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/golden");
+
+pub const SEED: u64 = 0x60_1d_e4;
+pub const TARGET_SIZE: u32 = 128;
+/// The width and height of the captured area in world units
+pub const VIEW_SIZE: f64 = 8.0;
+/// How long the fragments are simulated before the capture
+pub const CAPTURE_TIME: f64 = 0.25;
+pub const CAPTURE_DT: f64 = 1.0 / 120.0;
+
+/// The largest difference in any channel for two pixels to be considered the same
+pub const CHANNEL_TOLERANCE: u8 = 8;
+/// The portion of pixels that are allowed to differ
+pub const PIXEL_TOLERANCE: f64 = 0.01;
+
+#[must_use]
+pub fn reference_path(kind: &EnemyKind) -> String {
+    format!(
+        "{REFERENCE_DIRECTORY}/{}.png",
+        kind.name.to_lowercase().replace(' ', "_")
+    )
+}
+
+/// Explodes `kind` with a fixed seed and renders the fragments partway through their lifetime
+#[must_use]
+pub fn render_explosion(kind: &EnemyKind) -> Image {
+    let enemy = Enemy::new(Isometry2::new(vector![0.0, 0.0], 0.125 * TAU), kind);
+
+    let mut particles = HopSlotMap::with_key();
+    enemy.explode_with_seed(
+        point![-0.5, 0.0],
+        vector![15.0, 0.0],
+        &ExplosionParameters::default(),
+        &mut particles,
+        SEED,
+    );
+
+    let mut time = 0.0;
+    while time < CAPTURE_TIME {
+        for (_, particle) in &mut particles {
+            particle.tick(CAPTURE_DT);
+        }
+
+        time += CAPTURE_DT;
+    }
+
+    let mut image = Image::gen_image_color(TARGET_SIZE as u16, TARGET_SIZE as u16, colors::BLACK);
+    for (_, particle) in &particles {
+        draw_particle(&mut image, particle);
+    }
+
+    image
+}
+
+/// Blends `particle` into `image`, which shows [`VIEW_SIZE`] world units centered on the origin
+/// with y pointing up. Each pixel takes the texel under its center, like nearest filtering.
+fn draw_particle(image: &mut Image, particle: &Particle) {
+    let texture = &particle.texture.image;
+    let (start, source_size) = match particle.start {
+        Some(start) => (start, particle.size),
+        None => (Point2::origin(), vector![texture.width(), texture.height()]),
+    };
+
+    let size = particle.drawn_size();
+    let tint = Color {
+        a: particle.opacity() as f32,
+        ..particle.color
+    };
+    let pixels_per_unit = TARGET_SIZE as f64 / VIEW_SIZE;
+
+    for y in 0..TARGET_SIZE {
+        for x in 0..TARGET_SIZE {
+            let world = point![x as f64 + 0.5, TARGET_SIZE as f64 - y as f64 - 0.5]
+                / pixels_per_unit
+                - Vector2::repeat(VIEW_SIZE / 2.0);
+
+            // Where the pixel lands on the particle, from (0, 0) to (1, 1)
+            let local = particle.position.inverse_transform_point(&world);
+            let uv = local.coords.component_div(&size) + Vector2::repeat(0.5);
+            if !uv.iter().all(|t| (0.0..1.0).contains(t)) {
+                continue;
+            }
+
+            let texel = texture.get_pixel(
+                (start.x + (uv.x * source_size.x as f64) as usize) as u32,
+                (start.y + (uv.y * source_size.y as f64) as usize) as u32,
+            );
+            let alpha = texel.a * tint.a;
+            let below = image.get_pixel(x, y);
+
+            let blend =
+                |source: f32, tint: f32, below: f32| source * tint * alpha + below * (1.0 - alpha);
+            image.set_pixel(
+                x,
+                y,
+                Color::new(
+                    blend(texel.r, tint.r, below.r),
+                    blend(texel.g, tint.g, below.g),
+                    blend(texel.b, tint.b, below.b),
+                    1.0,
+                ),
+            );
+        }
+    }
+}
+
+/// The portion of pixels that differ between the two images, or `None` if their sizes differ
+#[must_use]
+pub fn difference(a: &Image, b: &Image) -> Option<f64> {
+    if (a.width, a.height) != (b.width, b.height) {
+        return None;
+    }
+
+    let different_pixels = (a.get_image_data().iter())
+        .zip(b.get_image_data())
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(b.iter())
+                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+
+    Some(different_pixels as f64 / a.get_image_data().len().max(1) as f64)
+}
+
+pub fn regenerate() -> Result<(), String> {
+    fs::create_dir_all(REFERENCE_DIRECTORY).map_err(|error| error.to_string())?;
+
+    for kind in ENEMY_KINDS.iter() {
+        // `export_png` flips the rows of render target read backs, so the rows are flipped here
+        // first to save the image the way it's loaded
+        let mut image = render_explosion(kind);
+        let row_length = image.width() * 4;
+        let rows = image
+            .bytes
+            .chunks_exact(row_length)
+            .rev()
+            .flatten()
+            .copied();
+        image.bytes = rows.collect();
+
+        image.export_png(&reference_path(kind));
+    }
+
+    Ok(())
+}
+
+/// Returns a description of every explosion that doesn't match its reference
+pub fn check() -> Result<(), Vec<String>> {
+    let mut failures = Vec::new();
+
+    for kind in ENEMY_KINDS.iter() {
+        let path = reference_path(kind);
+
+        let reference = match fs::read(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| {
+                Image::from_file_with_format(&bytes, None).map_err(|error| error.to_string())
+            }) {
+            Ok(reference) => reference,
+            Err(error) => {
+                failures.push(format!(
+                    "{}: could not load {path} ({error}), regenerate the references to add it",
+                    kind.name
+                ));
+                continue;
+            }
+        };
+
+        match difference(&render_explosion(kind), &reference) {
+            Some(difference) if difference <= PIXEL_TOLERANCE => (),
+            Some(difference) => failures.push(format!(
+                "{}: {:.1}% of pixels differ from {path}",
+                kind.name,
+                difference * 100.0
+            )),
+            None => failures.push(format!("{}: size differs from {path}", kind.name)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::color::Color;
+
+    use super::*;
+
+    #[test]
+    fn references_are_named_after_their_kind() {
+        assert!(reference_path(&ENEMY_KINDS[0]).ends_with("assets/golden/red_circle.png"));
+        assert!(reference_path(&ENEMY_KINDS[4]).ends_with("assets/golden/purple_square.png"));
+    }
+
+    #[test]
+    fn explosions_match_their_golden_images() {
+        if let Err(failures) = check() {
+            panic!("golden images differ:\n{}", failures.join("\n"));
+        }
+    }
+
+    #[test]
+    fn explosions_draw_something() {
+        for kind in ENEMY_KINDS.iter() {
+            let image = render_explosion(kind);
+            let black = [0, 0, 0, 255];
+
+            assert!(image.get_image_data().iter().any(|&pixel| pixel != black));
+        }
+    }
+
+    /// Overwrites the references after an intentional change to how explosions look
+    #[test]
+    #[ignore = "only run to regenerate the references"]
+    fn regenerate_golden_images() {
+        regenerate().unwrap();
+    }
+
+    #[test]
+    fn difference_counts_pixels_outside_the_channel_tolerance() {
+        let black = Image::gen_image_color(10, 10, colors::BLACK);
+        let mut image = black.clone();
+
+        let slightly_off = Color::from_rgba(CHANNEL_TOLERANCE, 0, 0, 255);
+        image.set_pixel(0, 0, slightly_off);
+        assert_eq!(difference(&black, &image), Some(0.0));
+
+        let off = Color::from_rgba(CHANNEL_TOLERANCE + 1, 0, 0, 255);
+        for x in 0..5 {
+            image.set_pixel(x, 1, off);
+        }
+        assert_eq!(difference(&black, &image), Some(0.05));
+
+        let smaller = Image::gen_image_color(10, 5, colors::BLACK);
+        assert_eq!(difference(&black, &smaller), None);
+    }
+}
@@ -0,0 +1,69 @@
+use macroquad::{color::Color, math::Vec2, shapes, window};
+use nalgebra::UnitComplex;
+
+/// Points from the turret towards whatever last damaged it, from the edge of the screen
+#[derive(Clone, Copy, Debug)]
+pub struct DamageIndicator {
+    pub direction: UnitComplex<f64>,
+    pub time_left: f64,
+}
+
+impl DamageIndicator {
+    pub const LIFETIME: f64 = 1.0;
+    pub const COLOR: Color = Color::from_hex(0xff3030);
+
+    /// How far in from the edge of the screen the tip of the indicator is drawn, in pixels
+    pub const EDGE_MARGIN: f32 = 24.0;
+    /// In pixels
+    pub const LENGTH: f32 = 36.0;
+    /// In pixels
+    pub const WIDTH: f32 = 48.0;
+
+    pub fn new(direction: UnitComplex<f64>) -> Self {
+        Self {
+            direction,
+            time_left: Self::LIFETIME,
+        }
+    }
+
+    pub fn tick(&mut self, dt: f64) {
+        self.time_left -= dt;
+    }
+
+    #[must_use]
+    pub fn opacity(&self) -> f64 {
+        (self.time_left / Self::LIFETIME).clamp(0.0, 1.0)
+    }
+
+    /// Draws the indicator in screen space against the edge of the screen, pointing along
+    /// `screen_direction` from the middle of the screen. [`DamageIndicator::direction`] is in
+    /// world space, so it has to be converted with the camera first.
+    pub fn draw_at_screen_edge(&self, screen_direction: Vec2) {
+        let direction = screen_direction.normalize_or_zero();
+        if direction == Vec2::ZERO {
+            return;
+        }
+
+        let center = Vec2::new(window::screen_width(), window::screen_height()) / 2.0;
+        let reach = center - Self::EDGE_MARGIN;
+        let distance = (reach.x / direction.x.abs()).min(reach.y / direction.y.abs());
+
+        let tip = center + direction * distance;
+        let base = tip - direction * Self::LENGTH;
+        let side = direction.perp() * Self::WIDTH / 2.0;
+
+        shapes::draw_triangle(
+            tip,
+            base + side,
+            base - side,
+            Color {
+                a: self.opacity() as f32,
+                ..Self::COLOR
+            },
+        );
+    }
+
+    pub fn should_delete(&self) -> bool {
+        self.time_left <= 0.0
+    }
+}
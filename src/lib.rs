@@ -0,0 +1,28 @@
+pub mod arena;
+pub mod behavior;
+pub mod collision;
+pub mod content;
+pub mod delta;
+pub mod enemy;
+pub mod game;
+pub mod ghost;
+#[cfg(test)]
+mod golden;
+pub mod indicator;
+pub mod object;
+pub mod particle;
+pub mod projectile;
+pub mod rng;
+pub mod save;
+pub mod score;
+pub mod shape;
+pub mod snapshot;
+pub mod spatial;
+pub mod spawn;
+pub mod spawner;
+pub mod stats;
+pub mod tether;
+pub mod trail;
+pub mod turret;
+pub mod tween;
+pub mod utils;
@@ -1,27 +1,30 @@
-pub mod enemy;
-pub mod game;
-pub mod object;
-pub mod particle;
-pub mod projectile;
-pub mod shape;
-pub mod turret;
-pub mod utils;
-
 use std::f64::consts::TAU;
 
 use macroquad::{
     camera::{self, Camera2D},
+    color::{Color, colors},
     input::{self, KeyCode},
+    shapes, text,
     window::{self, Conf},
 };
 use nalgebra::{Isometry2, vector};
 
-use crate::{
+use electro_shoot::{
     enemy::{ENEMY_KINDS, Enemy},
     game::Game,
+    spawn::AutoSpawner,
+    turret::ReticleSnapping,
+    utils::{self, FrameTimer},
 };
 
 const START_IN_FULLSCREEN: bool = true;
+/// Rotates the view counterclockwise by this many degrees, for portrait monitors and rotated
+/// installs
+const VIEW_ROTATION: f32 = 0.0;
+const TITLE_FONT_SIZE: f32 = 48.0;
+const SEED: u64 = 1234980;
+const RESTART_KEY: KeyCode = KeyCode::R;
+const GAME_OVER_OVERLAY_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
 
 fn config() -> Conf {
     Conf {
@@ -33,22 +36,19 @@ fn config() -> Conf {
 
 #[macroquad::main(config)]
 async fn main() {
-    macroquad::rand::srand(1234980);
-
     let mut fullscreen = START_IN_FULLSCREEN;
 
     let screen_height = 36.0;
     let mut camera = Camera2D {
         zoom: [-2.0 / screen_height as f32; 2].into(),
+        rotation: VIEW_ROTATION,
         ..Default::default()
     };
 
-    let mut game = Game::default();
+    let mut game = new_game(SEED);
+    let mut frame_timer = FrameTimer::default();
 
-    game.enemies.insert(Enemy::new(
-        Isometry2::new(vector![25.0, 0.0], 0.5 * TAU),
-        &ENEMY_KINDS[4],
-    ));
+    game.center_camera(&mut camera);
 
     loop {
         if input::is_key_pressed(KeyCode::F11) {
@@ -59,12 +59,108 @@ async fn main() {
         utils::update_camera_aspect_ratio(&mut camera);
         camera::set_camera(&camera);
 
-        game.tick_input(macroquad::time::get_frame_time() as f64);
+        frame_timer.record(macroquad::time::get_frame_time() as f64);
+
+        game.tick_input(frame_timer.frame_time());
 
         game.tick(&mut camera, 1.0 / 120.0);
 
+        // Nothing reacts to events here yet, so they're dropped instead of piling up
+        game.events.clear();
+
         game.draw();
 
+        if game.reticle.is_some() {
+            let aim_position = game.aim_position(&camera);
+            ReticleSnapping::draw(game.reticle_position(aim_position), aim_position);
+        }
+
+        camera::set_default_camera();
+        shapes::draw_rectangle(
+            0.0,
+            0.0,
+            window::screen_width(),
+            window::screen_height(),
+            game.screen_flash(),
+        );
+
+        draw_damage_indicators(&game, &camera);
+
+        if game.is_game_over() {
+            draw_game_over(&game);
+
+            if input::is_key_pressed(RESTART_KEY) {
+                game = new_game(game.seed.wrapping_add(1));
+                // A shot held through the game over screen waits for a fresh press
+                game.primary_turret_mut().input.cancel();
+            }
+        }
+
         window::next_frame().await;
     }
 }
+
+fn new_game(seed: u64) -> Game {
+    let mut game = Game {
+        auto_spawner: Some(AutoSpawner::default()),
+        reticle: Some(ReticleSnapping::default()),
+        ..Game::with_seed(seed)
+    };
+
+    game.insert_enemy(Enemy::new(
+        game.world_position(Isometry2::new(vector![25.0, 0.0], 0.5 * TAU)),
+        &ENEMY_KINDS[4],
+    ));
+
+    game
+}
+
+/// Draws each damage indicator at the edge of the screen, pointing the way it points in the world
+fn draw_damage_indicators(game: &Game, camera: &Camera2D) {
+    let turret = game.primary_turret().position.translation.vector;
+    let center = camera.world_to_screen(utils::vector2_f64_to_vec2(turret));
+
+    for indicator in &game.damage_indicators {
+        let ahead = turret + indicator.direction * vector![1.0, 0.0];
+        let screen_direction = camera.world_to_screen(utils::vector2_f64_to_vec2(ahead)) - center;
+
+        indicator.draw_at_screen_edge(screen_direction);
+    }
+}
+
+/// Darkens the screen and shows the final score along with how to start over
+fn draw_game_over(game: &Game) {
+    shapes::draw_rectangle(
+        0.0,
+        0.0,
+        window::screen_width(),
+        window::screen_height(),
+        GAME_OVER_OVERLAY_COLOR,
+    );
+
+    let lines = [
+        ("Game over".to_owned(), TITLE_FONT_SIZE),
+        (
+            format!("Score  {}", game.score.points),
+            TITLE_FONT_SIZE / 2.0,
+        ),
+        (
+            format!("Press {RESTART_KEY:?} to restart"),
+            TITLE_FONT_SIZE / 2.0,
+        ),
+    ];
+
+    let mut y = window::screen_height() / 2.0 - TITLE_FONT_SIZE;
+    for (line, font_size) in lines {
+        let size = text::measure_text(&line, None, font_size as u16, 1.0);
+        text::draw_text(
+            &line,
+            (window::screen_width() - size.width) / 2.0,
+            y,
+            font_size,
+            colors::WHITE,
+        );
+
+        y += font_size * 1.5;
+    }
+}
@@ -3,7 +3,10 @@ use std::ops::{Deref, DerefMut};
 use macroquad::{color::colors, shapes};
 use nalgebra::{Isometry2, Point2, UnitComplex, Vector2, vector};
 
-use crate::shape::Shape;
+use crate::{
+    collision::{self, Manifold},
+    shape::Shape,
+};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Object {
@@ -27,6 +30,40 @@ impl Transform {
             .append_rotation_wrt_center_mut(&UnitComplex::new(self.angular_velocity * dt));
     }
 
+    /// Blends from `self` at `t = 0.0` to `other` at `t = 1.0`, turning the short way around.
+    /// Values of `t` outside of that range extrapolate.
+    #[must_use]
+    pub fn interpolated(&self, other: &Self, t: f64) -> Self {
+        let translation = self
+            .position
+            .translation
+            .vector
+            .lerp(&other.position.translation.vector, t);
+        let rotation = self.position.rotation
+            * UnitComplex::new(self.position.rotation.angle_to(&other.position.rotation) * t);
+
+        Self {
+            position: Isometry2::from_parts(translation.into(), rotation),
+            linear_velocity: self.linear_velocity.lerp(&other.linear_velocity, t),
+            angular_velocity: self.angular_velocity
+                + (other.angular_velocity - self.angular_velocity) * t,
+        }
+    }
+
+    /// The world position of this transform when `position` is relative to `parent`. Nested
+    /// parents can be handled by passing in the parent's own world position.
+    #[must_use]
+    pub fn world_from(&self, parent: &Isometry2<f64>) -> Isometry2<f64> {
+        parent * self.position
+    }
+
+    /// The world velocity of this transform when it is relative to `parent`, ignoring any motion
+    /// of the parent itself
+    #[must_use]
+    pub fn world_linear_velocity_from(&self, parent: &Isometry2<f64>) -> Vector2<f64> {
+        parent.rotation * self.linear_velocity
+    }
+
     pub fn draw(&self) {
         shapes::draw_circle(
             self.position.translation.x as f32,
@@ -35,6 +72,18 @@ impl Transform {
             colors::MAGENTA,
         );
     }
+
+    #[must_use]
+    pub fn is_finite(&self) -> bool {
+        self.position
+            .translation
+            .vector
+            .iter()
+            .all(|x| x.is_finite())
+            && self.position.rotation.angle().is_finite()
+            && self.linear_velocity.iter().all(|x| x.is_finite())
+            && self.angular_velocity.is_finite()
+    }
 }
 
 impl Object {
@@ -42,9 +91,23 @@ impl Object {
         self.shape.draw_outline(self.position, 0.1, colors::MAGENTA);
     }
 
+    /// A cheap check that rules out collisions between objects whose bounding circles don't
+    /// touch. Returning `true` doesn't mean the objects are colliding.
+    #[must_use]
+    pub fn bounding_circle_overlaps(&self, other: &Self) -> bool {
+        let reach = self.shape.bounding_radius() + other.shape.bounding_radius();
+
+        self.linear_offset_to(other).norm_squared() <= reach * reach
+    }
+
     #[must_use]
     pub fn is_colliding(&self, other: &Self) -> bool {
-        self.shape.is_colliding(&other.shape, self.offset_to(other))
+        collision::overlaps(&self.shape, &self.position, &other.shape, &other.position)
+    }
+
+    #[must_use]
+    pub fn overlap_manifold(&self, other: &Self) -> Option<Manifold> {
+        collision::overlap_manifold(&self.shape, &self.position, &other.shape, &other.position)
     }
 
     #[must_use]
@@ -79,3 +142,94 @@ impl DerefMut for Object {
         &mut self.transform
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    use super::*;
+    use crate::rng::Rng;
+
+    fn local(x: f64, y: f64, angle: f64) -> Transform {
+        Transform {
+            position: Isometry2::new(vector![x, y], angle),
+            linear_velocity: vector![1.0, 0.0],
+            angular_velocity: 0.0,
+        }
+    }
+
+    fn assert_close(a: Isometry2<f64>, b: Isometry2<f64>) {
+        assert!((a.translation.vector - b.translation.vector).norm() < 1e-12);
+        assert!(a.rotation.angle_to(&b.rotation).abs() < 1e-12);
+    }
+
+    #[test]
+    fn children_combine_their_parents_translation_and_rotation() {
+        let parent = Isometry2::new(vector![5.0, 1.0], FRAC_PI_2);
+        let child = local(2.0, 0.0, 0.25);
+
+        assert_close(
+            child.world_from(&parent),
+            Isometry2::new(vector![5.0, 3.0], FRAC_PI_2 + 0.25),
+        );
+        assert!((child.world_linear_velocity_from(&parent) - vector![0.0, 1.0]).norm() < 1e-12);
+
+        let grandchild = local(0.0, 1.0, 0.0);
+        assert_close(
+            grandchild.world_from(&child.world_from(&parent)),
+            Isometry2::new(
+                vector![
+                    5.0 - (FRAC_PI_2 + 0.25).sin(),
+                    3.0 + (FRAC_PI_2 + 0.25).cos()
+                ],
+                FRAC_PI_2 + 0.25,
+            ),
+        );
+
+        assert_close(child.world_from(&Isometry2::identity()), child.position);
+    }
+
+    #[test]
+    fn bounding_circle_check_never_rules_out_a_real_collision() {
+        let shapes = [
+            Shape::Point,
+            Shape::Circle { radius: 0.7 },
+            Shape::Rectangle {
+                half_size: vector![1.5, 0.2],
+            },
+            Shape::Rectangle {
+                half_size: vector![0.6, 0.6],
+            },
+        ];
+        let object = |shape, x, y, angle| Object {
+            shape,
+            transform: local(x, y, angle),
+        };
+        let mut rng = Rng::new(30);
+        let mut collisions = 0;
+
+        for _ in 0..2000 {
+            let a = object(
+                shapes[rng.gen_range(0, shapes.len())],
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(0.0, TAU),
+            );
+            let b = object(
+                shapes[rng.gen_range(0, shapes.len())],
+                rng.gen_range(-3.0, 3.0),
+                rng.gen_range(-3.0, 3.0),
+                rng.gen_range(0.0, TAU),
+            );
+
+            if a.is_colliding(&b) {
+                collisions += 1;
+                assert!(a.bounding_circle_overlaps(&b), "{a:?} {b:?}");
+            }
+        }
+        assert!(collisions > 100);
+
+        let far = object(shapes[2], 4.0, 0.0, FRAC_PI_2);
+        assert!(!object(shapes[3], 0.0, 0.0, 0.5).bounding_circle_overlaps(&far));
+    }
+}
@@ -1,13 +1,20 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
 use macroquad::{
     color::Color,
     math::Rect,
-    texture::{self, DrawTextureParams, Texture2D},
+    texture::{self, DrawTextureParams},
 };
 use nalgebra::{Point2, Vector2};
 
-use crate::{object::Transform, utils};
+use crate::{
+    object::Transform,
+    tween,
+    utils::{self, TextureEntry},
+};
 
 #[derive(Clone, Debug)]
 pub struct Particle {
@@ -18,13 +25,31 @@ pub struct Particle {
     pub time_since_creation: f64,
     pub maximum_lifetime: f64,
 
-    pub texture: Texture2D,
+    pub texture: Arc<TextureEntry>,
 
     pub start: Option<Point2<usize>>,
     pub size: Vector2<usize>,
+    /// World units per texture pixel
+    pub pixel_scale: f64,
+    pub layer: ParticleLayer,
+    /// Scales the particle down to nothing over its lifetime
+    pub shrink: bool,
+    /// Shapes how the particle fades out. Takes the portion of its lifetime that has passed and
+    /// returns how far it has faded, from `0.0` for opaque to `1.0` for invisible.
+    pub fade_curve: fn(f64) -> f64,
+}
+
+/// Where a particle is drawn relative to enemies and projectiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ParticleLayer {
+    BehindEntities,
+    #[default]
+    AboveEntities,
 }
 
 impl Particle {
+    pub const DEFAULT_PIXEL_SCALE: f64 = 0.1;
+
     pub fn tick(&mut self, dt: f64) {
         self.time_since_creation += dt;
 
@@ -34,20 +59,32 @@ impl Particle {
             self.transform.position.translation.vector =
                 self.transform.position.translation.vector.lerp(
                     &target_position.coords,
-                    utils::exp_decay(0.0, 1.0, decay_speed, dt),
+                    tween::exp_decay(0.0, 1.0, decay_speed, dt),
                 );
         }
     }
 
+    /// The size the particle is drawn at in world units, after shrinking
+    #[must_use]
+    pub fn drawn_size(&self) -> Vector2<f64> {
+        let size = self.size.map(|x| x as f64) * self.pixel_scale;
+
+        if self.shrink {
+            size * (1.0 - self.time_since_creation / self.maximum_lifetime).max(0.0)
+        } else {
+            size
+        }
+    }
+
     pub fn draw(&self) {
-        let size = self.size.map(|x| x as f64) * 0.1;
+        let size = self.drawn_size();
 
         texture::draw_texture_ex(
-            &self.texture,
+            self.texture.texture(),
             (self.position.translation.x - size.x / 2.0) as f32,
             (self.position.translation.y - size.y / 2.0) as f32,
             Color {
-                a: (1.0 - self.time_since_creation / self.maximum_lifetime) as f32,
+                a: self.opacity() as f32,
                 ..self.color
             },
             DrawTextureParams {
@@ -66,6 +103,13 @@ impl Particle {
         );
     }
 
+    #[must_use]
+    pub fn opacity(&self) -> f64 {
+        let progress = (self.time_since_creation / self.maximum_lifetime).clamp(0.0, 1.0);
+
+        1.0 - (self.fade_curve)(progress)
+    }
+
     pub fn should_delete(&self) -> bool {
         self.time_since_creation >= self.maximum_lifetime
     }
@@ -1,25 +1,32 @@
 use std::{
-    f64::consts::TAU,
+    f64::consts::{FRAC_PI_2, PI, TAU},
     ops::{Deref, DerefMut},
 };
 
 use macroquad::{
-    color::Color,
+    color::{Color, colors},
     shapes::{self, DrawRectangleParams},
 };
-use nalgebra::{Isometry2, UnitComplex, Vector2, point, vector};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2, point, vector};
 use slotmap::HopSlotMap;
 
 use crate::{
-    enemy::Enemy,
+    arena::WrapArena,
+    collision,
+    enemy::{Enemy, ExplosionParameters},
     game::{EnemyKey, ParticleKey},
     object::{Object, Transform},
-    particle::Particle,
+    particle::{Particle, ParticleLayer},
+    rng::Rng,
     shape::Shape,
-    utils::{self, GLITTER_TEXTURES},
+    spatial::SpatialGrid,
+    tether::{Tether, TetherProperties, TetherPull},
+    trail::{TrailProperties, TrailZone},
+    tween,
+    utils::{self, BoundingBox, GLITTER_TEXTURES, RingBuffer},
 };
 
-pub static PROJECTILE_KINDS: [ProjectileKind; 3] = [
+pub static PROJECTILE_KINDS: [ProjectileKind; 9] = [
     ProjectileKind {
         name: "Classic",
         properties: ProjectileProperties {
@@ -28,10 +35,26 @@ pub static PROJECTILE_KINDS: [ProjectileKind; 3] = [
             piercing: true,
             speed: 15.0,
             particle_distance: 1.0,
-            hit_particle_radius: 2,
+            hit_particle_count: 4,
             hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.0,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
         },
         shoot_cooldown: 1.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
     },
     ProjectileKind {
         name: "Rapid",
@@ -41,10 +64,26 @@ pub static PROJECTILE_KINDS: [ProjectileKind; 3] = [
             piercing: false,
             speed: 30.0,
             particle_distance: 3.0,
-            hit_particle_radius: 1,
+            hit_particle_count: 2,
             hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.0,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
         },
         shoot_cooldown: 1.0 / 3.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
     },
     ProjectileKind {
         name: "Slow",
@@ -54,27 +93,286 @@ pub static PROJECTILE_KINDS: [ProjectileKind; 3] = [
             piercing: true,
             speed: 6.0,
             particle_distance: 0.8,
-            hit_particle_radius: 3,
+            hit_particle_count: 6,
             hit_particle_distance: 0.8 * 2.0 / 3.0,
+            hit_particle_jitter: 0.0,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
         },
         shoot_cooldown: 5.0 / 3.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
+    },
+    ProjectileKind {
+        name: "Grenade",
+        properties: ProjectileProperties {
+            size: vector![0.4, 0.4],
+            damage: 4,
+            piercing: true,
+            speed: 8.0,
+            particle_distance: 0.8,
+            hit_particle_count: 4,
+            hit_particle_distance: 0.6,
+            hit_particle_jitter: 0.1,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: Some(3.0),
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 2.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: Some(Color::new(1.0, 1.0, 1.0, 0.15)),
+    },
+    ProjectileKind {
+        name: "Fuse",
+        properties: ProjectileProperties {
+            size: vector![0.4, 0.2],
+            damage: 1,
+            piercing: true,
+            speed: 10.0,
+            particle_distance: 1.0,
+            hit_particle_count: 2,
+            hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.0,
+            trail: Some(TrailProperties {
+                interval: 0.5,
+                radius: 0.4,
+                damage_per_second: 3.0,
+                lifetime: 2.0,
+            }),
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 4.0 / 3.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
+    },
+    ProjectileKind {
+        name: "Mortar",
+        properties: ProjectileProperties {
+            size: vector![0.5, 0.3],
+            damage: 6,
+            piercing: false,
+            speed: 14.0,
+            particle_distance: 1.0,
+            hit_particle_count: 4,
+            hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.2,
+            trail: None,
+            gravity: vector![0.0, -12.0],
+            tether: None,
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 3.0 / 2.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: false,
+        impact_flash: Some(Color::new(1.0, 0.9, 0.7, 0.35)),
+    },
+    ProjectileKind {
+        name: "Harpoon",
+        properties: ProjectileProperties {
+            size: vector![0.6, 0.15],
+            damage: 2,
+            piercing: false,
+            speed: 20.0,
+            particle_distance: 1.0,
+            hit_particle_count: 4,
+            hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.0,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: Some(TetherProperties {
+                stiffness: 4.0,
+                damping: 2.0,
+                duration: 2.0,
+                break_length: 30.0,
+            }),
+            ricochet: None,
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 2.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: false,
+        impact_flash: None,
+    },
+    ProjectileKind {
+        name: "Ricochet",
+        properties: ProjectileProperties {
+            size: vector![0.3, 0.3],
+            damage: 3,
+            piercing: false,
+            speed: 18.0,
+            particle_distance: 1.0,
+            hit_particle_count: 2,
+            hit_particle_distance: 0.8,
+            hit_particle_jitter: 0.0,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: Some(RicochetProperties {
+                bounces: 3,
+                range: 12.0,
+            }),
+            homing: None,
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 1.0,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
+    },
+    ProjectileKind {
+        name: "Seeker",
+        properties: ProjectileProperties {
+            size: vector![0.5, 0.25],
+            damage: 3,
+            piercing: false,
+            speed: 12.0,
+            particle_distance: 0.5,
+            hit_particle_count: 4,
+            hit_particle_distance: 0.6,
+            hit_particle_jitter: 0.1,
+            trail: None,
+            gravity: vector![0.0, 0.0],
+            tether: None,
+            ricochet: None,
+            homing: Some(4.0),
+            explosion_radius: None,
+            pierce_falloff: 1.0,
+            overcharge_bonus: 1.0,
+            overcharge_distance: 2.0,
+            hit_cooldown: 0.5,
+            trail_particles_per_segment: 1,
+            trail_particle_spread: 0.0,
+        },
+        shoot_cooldown: 0.8,
+        fire_pattern: FirePattern::SINGLE,
+        auto_fire: true,
+        impact_flash: None,
     },
 ];
 
 #[derive(Clone, Debug)]
 pub struct Projectile {
+    /// From [`SpawnOrder::allocate`](crate::utils::SpawnOrder::allocate) when the projectile was
+    /// added to its game, or zero if it was inserted into [`Game::projectiles`](crate::game::Game::projectiles)
+    /// directly
+    pub spawn_index: u64,
     pub object: Object,
     pub direction: UnitComplex<f64>,
 
     pub properties: ProjectileProperties,
+    pub faction: Faction,
+    pub source: SourceId,
+    pub impact_flash: Option<Color>,
 
     pub enemies_colliding: Vec<EnemyKey>,
     pub enemies_intersecting: Vec<EnemyKey>,
     pub enemies_hit: Vec<EnemyKey>,
+    /// Enemies hit within the last `hit_cooldown` seconds, with how long ago
+    pub recent_hits: Vec<(EnemyKey, f64)>,
     pub time_since_collision: f64,
     pub time_since_exit: f64,
 
     pub distance_since_particle: f64,
+    pub distance_since_trail: f64,
+    /// How far the projectile has moved since it was fired
+    pub distance_travelled: f64,
+    /// The velocity gained from `gravity` since the projectile was fired
+    pub fall_velocity: Vector2<f64>,
+    /// Where the projectile was fired from
+    pub origin: Point2<f64>,
+    /// The enemy this projectile has latched onto, if any
+    pub tether: Option<Tether>,
+    /// Where the projectile was at the end of each of its latest ticks
+    pub history: RingBuffer<Isometry2<f64>, { Projectile::HISTORY_LENGTH }>,
+    pub bounces_left: u32,
+    /// The enemy this projectile was just redirected away from, waiting to pick a new target
+    pub ricochet_from: Option<EnemyKey>,
+    /// The enemy this projectile has been redirected towards
+    pub ricochet_target: Option<EnemyKey>,
+    /// How many times this projectile has wrapped around the arena
+    pub wraps: u32,
+    /// Set once a projectile with an `explosion_radius` has blown up
+    pub exploded: bool,
+}
+
+/// Who fired a projectile
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Faction {
+    #[default]
+    Player,
+    Enemy,
+}
+
+/// Identifies whoever fired a projectile, such as a particular turret. Each game hands these out
+/// with [`Game::allocate_source_id`](crate::game::Game::allocate_source_id), and never the default
+/// one, which marks projectiles without a particular source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct SourceId(pub u32);
+
+/// Everything from the rest of the game a projectile needs while ticking
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileContext<'a> {
+    pub explosion: &'a ExplosionParameters,
+    /// The order to check enemies for collisions in, or `None` for storage order
+    pub enemy_order: Option<&'a [EnemyKey]>,
+    pub tick: u64,
+    pub arena: Option<WrapArena>,
+    /// Narrows down which enemies are checked for collisions. Without one, or in a wrapping
+    /// arena, every enemy is checked.
+    pub grid: Option<&'a SpatialGrid>,
 }
 
 #[derive(Clone, Debug)]
@@ -83,6 +381,37 @@ pub struct ProjectileKind {
     pub properties: ProjectileProperties,
 
     pub shoot_cooldown: f64,
+    pub fire_pattern: FirePattern,
+    /// Whether holding the shoot button keeps firing, rather than needing a press for each shot
+    pub auto_fire: bool,
+    /// Tints the whole screen briefly whenever a projectile of this kind hits something
+    pub impact_flash: Option<Color>,
+}
+
+/// How many projectiles are fired per shot, and how they are spread out
+#[derive(Clone, Copy, Debug)]
+pub struct FirePattern {
+    pub pellets: usize,
+    /// The angle between the outermost pellets in radians
+    pub spread: f64,
+}
+
+impl FirePattern {
+    pub const SINGLE: Self = Self {
+        pellets: 1,
+        spread: 0.0,
+    };
+
+    /// Where the pellet at `index` sits across the pattern, from `-0.5` to `0.5`. Always `0.0` for
+    /// a single pellet.
+    #[must_use]
+    pub fn pellet_fraction(&self, index: usize) -> f64 {
+        if self.pellets <= 1 {
+            0.0
+        } else {
+            index as f64 / (self.pellets - 1) as f64 - 0.5
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -94,8 +423,44 @@ pub struct ProjectileProperties {
     pub speed: f64,
     pub particle_distance: f64,
 
-    pub hit_particle_radius: usize,
+    /// How many particles spread out from a hit, alternating between each side
+    pub hit_particle_count: usize,
+    /// The spacing between hit particles on each side
     pub hit_particle_distance: f64,
+    /// The largest random offset added to where each hit particle ends up
+    pub hit_particle_jitter: f64,
+
+    pub trail: Option<TrailProperties>,
+    /// A constant acceleration applied on top of the projectile's speed, making it arc
+    pub gravity: Vector2<f64>,
+    pub tether: Option<TetherProperties>,
+    pub ricochet: Option<RicochetProperties>,
+    /// How fast the projectile turns towards the nearest enemy, in radians per second, if it homes
+    pub homing: Option<f64>,
+    /// Makes the projectile blow up on its first hit, damaging every enemy within this radius of
+    /// the hit instead of piercing
+    pub explosion_radius: Option<f64>,
+    /// The portion of damage a piercing projectile keeps after each enemy it hits
+    pub pierce_falloff: f64,
+    /// Multiplies the damage of hits within `overcharge_distance` of where the projectile was fired
+    pub overcharge_bonus: f64,
+    pub overcharge_distance: f64,
+    /// How long after hitting an enemy the projectile can't damage it again, even if it leaves
+    /// and comes back
+    pub hit_cooldown: f64,
+    /// How many glitter particles are left behind every `particle_distance`
+    pub trail_particles_per_segment: usize,
+    /// The largest random sideways offset of each glitter particle from the projectile's path
+    pub trail_particle_spread: f64,
+}
+
+/// How a projectile redirects itself towards another enemy after a hit
+#[derive(Clone, Copy, Debug)]
+pub struct RicochetProperties {
+    /// How many times the projectile can redirect itself
+    pub bounces: u32,
+    /// How far away the next enemy can be
+    pub range: f64,
 }
 
 impl ProjectileProperties {
@@ -106,17 +471,64 @@ impl ProjectileProperties {
     pub fn distance_to_back(&self) -> f64 {
         self.size.x / 2.0
     }
+
+    /// The portion of damage still dealt after `hits` enemies, from piercing falloff
+    #[must_use]
+    pub fn damage_ratio(&self, hits: usize) -> f64 {
+        if self.piercing {
+            self.pierce_falloff.powi(hits as i32)
+        } else {
+            1.0
+        }
+    }
+
+    /// The damage multiplier for a hit after travelling `distance_travelled`, from overcharge
+    #[must_use]
+    pub fn overcharge_multiplier(&self, distance_travelled: f64) -> f64 {
+        if distance_travelled < self.overcharge_distance {
+            self.overcharge_bonus
+        } else {
+            1.0
+        }
+    }
+
+    /// The damage dealt to the next enemy after `hits` enemies and `distance_travelled` units,
+    /// never less than one
+    #[must_use]
+    pub fn damage_after_hits(&self, hits: usize, distance_travelled: f64) -> u32 {
+        let multiplier = self.damage_ratio(hits) * self.overcharge_multiplier(distance_travelled);
+
+        ((self.damage as f64 * multiplier).round() as u32).max(1)
+    }
 }
 
 impl Projectile {
     pub const COLOR: Color = Color::from_hex(0x00ffff);
+    /// The middle and top of the damage tint, after [`Projectile::COLOR`] for the weakest shots
+    pub const MEDIUM_DAMAGE_COLOR: Color = colors::WHITE;
+    pub const HIGH_DAMAGE_COLOR: Color = Color::from_hex(0xffff40);
+    /// Shots dealing at least this much damage get the full [`Projectile::HIGH_DAMAGE_COLOR`]
+    pub const HIGH_DAMAGE: u32 = 8;
     pub const COLLISION_SPEED_MULTIPLIER: f64 = 0.25;
     pub const COLLISION_OPACITY: f64 = 0.75;
+    pub const EXIT_FADE_DECAY: f64 = 15.0;
+    /// How far exit particles travel relative to hit particles
+    pub const EXIT_PARTICLE_DISTANCE: f64 = 0.5;
+    /// How many particles ring the blast of a projectile with an `explosion_radius`
+    pub const BLAST_PARTICLE_COUNT: usize = 16;
 
     pub const PARTICLE_JITTER: usize = 3;
+    /// How many past positions the streak behind each projectile is drawn through
+    pub const HISTORY_LENGTH: usize = 8;
+    pub const STREAK_THICKNESS: f64 = 0.05;
+    pub const STREAK_OPACITY: f64 = 0.5;
+    /// Streak segments longer than this are skipped, like when the projectile wraps around the
+    /// arena
+    pub const STREAK_BREAK_DISTANCE: f64 = 2.0;
 
-    pub fn new(position: Isometry2<f64>, kind: &ProjectileKind) -> Self {
+    pub fn new(position: Isometry2<f64>, kind: &ProjectileKind, rng: &mut Rng) -> Self {
         Self {
+            spawn_index: 0,
             object: Object {
                 shape: Shape::Rectangle {
                     half_size: kind.properties.size / 2.0,
@@ -129,13 +541,31 @@ impl Projectile {
             },
             direction: position.rotation,
             properties: kind.properties,
+            faction: Faction::Player,
+            source: SourceId::default(),
+            impact_flash: kind.impact_flash,
             enemies_colliding: Vec::new(),
             enemies_intersecting: Vec::new(),
             enemies_hit: Vec::new(),
+            recent_hits: Vec::new(),
             time_since_collision: f64::INFINITY,
             time_since_exit: f64::INFINITY,
             distance_since_particle: kind.properties.particle_distance
-                - macroquad::rand::gen_range(0, Self::PARTICLE_JITTER) as f64 * 0.1,
+                - rng.gen_range(0, Self::PARTICLE_JITTER) as f64 * 0.1,
+            distance_since_trail: 0.0,
+            distance_travelled: 0.0,
+            fall_velocity: vector![0.0, 0.0],
+            origin: position.translation.vector.into(),
+            tether: None,
+            history: RingBuffer::new(),
+            bounces_left: kind
+                .properties
+                .ricochet
+                .map_or(0, |ricochet| ricochet.bounces),
+            ricochet_from: None,
+            ricochet_target: None,
+            wraps: 0,
+            exploded: false,
         }
     }
 
@@ -143,137 +573,681 @@ impl Projectile {
         &mut self,
         enemies: &mut HopSlotMap<EnemyKey, Enemy>,
         particles: &mut HopSlotMap<ParticleKey, Particle>,
+        trail_zones: &mut Vec<TrailZone>,
+        context: &ProjectileContext,
+        rng: &mut Rng,
         dt: f64,
     ) {
         if self.should_delete() {
             return;
         }
 
+        if self.tether.is_some() {
+            self.tick_tether(enemies, dt);
+            return;
+        }
+
+        if let Some(turn_rate) = self.properties.homing {
+            self.home(enemies, context, turn_rate * dt);
+        }
+
         // Motion
-        let speed_multiplier = if self.enemies_colliding.is_empty() {
-            1.0
-        } else {
-            Self::COLLISION_SPEED_MULTIPLIER
-        };
+        let speed_multiplier = self.speed_multiplier();
 
         let speed = self.properties.speed * speed_multiplier;
 
-        self.object.linear_velocity = self.direction * vector![speed, 0.0];
+        self.fall_velocity += self.properties.gravity * dt;
+        self.object.linear_velocity = self.direction * vector![speed, 0.0] + self.fall_velocity;
+
+        // Point along the arc
+        if self.fall_velocity != Vector2::zeros() && self.object.linear_velocity != Vector2::zeros()
+        {
+            self.object.position.rotation = UnitComplex::new(
+                self.object
+                    .linear_velocity
+                    .y
+                    .atan2(self.object.linear_velocity.x),
+            );
+        }
 
+        let previous_position = self.position;
         self.object.tick(dt);
+        self.history.push(self.position);
+
+        let distance = self.object.linear_velocity.norm() * dt;
+        self.distance_travelled += distance;
 
         // Particles
-        self.distance_since_particle += speed * dt;
+        self.distance_since_particle += distance;
         while self.distance_since_particle >= self.properties.particle_distance {
             self.distance_since_particle -= self.properties.particle_distance;
 
+            for _ in 0..self.properties.trail_particles_per_segment {
+                // Spread evenly along this tick's path, however far it went
+                let progress = if distance > 0.0 {
+                    1.0 - self.distance_since_particle / distance
+                } else {
+                    1.0
+                };
+                let mut position = self.position_of_particle_between(
+                    previous_position,
+                    progress.clamp(0.0, 1.0),
+                    -self.properties.distance_to_back() + 0.1,
+                    rng,
+                );
+
+                let spread = self.properties.trail_particle_spread;
+                if spread > 0.0 {
+                    position.translation.vector +=
+                        self.position.rotation * vector![0.0, rng.gen_range(-spread, spread)];
+                }
+
+                particles.insert(Particle {
+                    transform: Transform {
+                        position,
+                        linear_velocity: vector![0.0, 0.0],
+                        angular_velocity: 0.0,
+                    },
+                    target_position: None,
+                    color: Color::from_hex(0x00ffff),
+                    time_since_creation: 0.0,
+                    maximum_lifetime: 2.0 / 3.0,
+                    texture: GLITTER_TEXTURES[rng.gen_range(0, GLITTER_TEXTURES.len())].clone(),
+                    start: None,
+                    size: vector![2, 2],
+                    pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+                    layer: ParticleLayer::BehindEntities,
+                    shrink: false,
+                    fade_curve: tween::linear,
+                });
+            }
+        }
+
+        // Trail
+        if let Some(trail) = self.properties.trail {
+            self.distance_since_trail += distance;
+            while self.distance_since_trail >= trail.interval {
+                self.distance_since_trail -= trail.interval;
+
+                let position = self.position
+                    * point![
+                        -self.properties.distance_to_back() - self.distance_since_trail,
+                        0.0
+                    ];
+
+                trail_zones.push(TrailZone::new(position, &trail, Some(self.source)));
+            }
+        }
+
+        // Collisions
+        self.time_since_collision += dt;
+
+        self.recent_hits.retain_mut(|(_, time_since_hit)| {
+            *time_since_hit += dt;
+            *time_since_hit < self.properties.hit_cooldown
+        });
+
+        for key in self.collision_order(enemies, context) {
+            self.collide_with(
+                key,
+                &mut enemies[key],
+                particles,
+                context,
+                speed_multiplier,
+                rng,
+            );
+
+            if let Some(radius) = self.properties.explosion_radius
+                && !self.enemies_hit.is_empty()
+            {
+                self.detonate(radius, enemies, particles, context, rng);
+                return;
+            }
+        }
+
+        if let Some(from) = self.ricochet_from.take() {
+            self.ricochet(from, enemies, context);
+        }
+
+        let mut exits = 0;
+
+        self.enemies_colliding.retain(|&key| {
+            let Some(enemy) = enemies.get(key).filter(|enemy| !enemy.should_delete()) else {
+                return false;
+            };
+            let enemy = &Self::nearest_enemy_object(&self.object, enemy, context);
+
+            let colliding = self.object.shape.is_colliding(
+                &enemy.shape,
+                Isometry2::new(
+                    -vector![
+                        self.properties.distance_to_front() + self.properties.distance_to_back(),
+                        0.0
+                    ],
+                    0.0,
+                ) * self.object.offset_to(enemy),
+            );
+
+            if !colliding {
+                exits += 1;
+            }
+
+            colliding
+        });
+
+        for _ in 0..exits {
+            self.add_exit_particles(particles, rng);
+        }
+
+        self.enemies_intersecting.retain(|&key| {
+            enemies.get(key).is_some_and(|enemy| {
+                !enemy.should_delete()
+                    && self.object.is_colliding(&Self::nearest_enemy_object(
+                        &self.object,
+                        enemy,
+                        context,
+                    ))
+            })
+        });
+
+        if self.enemies_colliding.is_empty() {
+            self.time_since_exit += dt;
+        } else {
+            self.time_since_exit = 0.0;
+        }
+    }
+
+    /// Projectiles slow down while passing through enemies
+    #[must_use]
+    pub fn speed_multiplier(&self) -> f64 {
+        if self.enemies_colliding.is_empty() {
+            1.0
+        } else {
+            Self::COLLISION_SPEED_MULTIPLIER
+        }
+    }
+
+    /// Where the projectile will be after its next tick of `dt`, assuming nothing else about it
+    /// changes during that tick
+    #[must_use]
+    pub fn predicted_object(&self, dt: f64) -> Object {
+        let mut object = self.object;
+
+        if self.tether.is_none() {
+            object.linear_velocity = self.direction
+                * vector![self.properties.speed * self.speed_multiplier(), 0.0]
+                + self.fall_velocity
+                + self.properties.gravity * dt;
+        }
+
+        object.tick(dt);
+        object
+    }
+
+    /// Follows the tethered enemy and pulls it towards the anchor until the tether breaks
+    fn tick_tether(&mut self, enemies: &mut HopSlotMap<EnemyKey, Enemy>, dt: f64) {
+        let (Some(tether), Some(properties)) = (&mut self.tether, self.properties.tether) else {
+            return;
+        };
+
+        tether.time_left -= dt;
+
+        let Some(enemy) = enemies
+            .get_mut(tether.enemy)
+            .filter(|enemy| !enemy.should_delete())
+        else {
+            self.tether = None;
+            return;
+        };
+
+        if tether.should_break(enemy.position.translation.vector.into(), &properties) {
+            enemy.tether = None;
+            self.tether = None;
+            return;
+        }
+
+        enemy.tether = Some(TetherPull {
+            anchor: tether.anchor,
+            stiffness: properties.stiffness,
+            damping: properties.damping,
+        });
+
+        self.object.position.translation = enemy.position.translation;
+        self.object.linear_velocity = enemy.linear_velocity;
+    }
+
+    /// Turns by up to `maximum_turn` radians towards the nearest living enemy it isn't already
+    /// passing through
+    fn home(
+        &mut self,
+        enemies: &HopSlotMap<EnemyKey, Enemy>,
+        context: &ProjectileContext,
+        maximum_turn: f64,
+    ) {
+        let target = (enemies.iter())
+            .filter(|&(key, enemy)| {
+                !enemy.should_delete()
+                    && !enemy.is_immune()
+                    && !self.enemies_colliding.contains(&key)
+            })
+            .map(|(_, enemy)| {
+                (self.object).linear_offset_to(&Self::nearest_enemy_object(
+                    &self.object,
+                    enemy,
+                    context,
+                ))
+            })
+            .min_by(|a, b| a.norm_squared().total_cmp(&b.norm_squared()));
+
+        let Some(offset) = target.filter(|offset| *offset != Vector2::zeros()) else {
+            return;
+        };
+
+        let turn = self
+            .direction
+            .angle_to(&UnitComplex::new(offset.y.atan2(offset.x)))
+            .clamp(-maximum_turn, maximum_turn);
+
+        self.direction *= UnitComplex::new(turn);
+        self.object.position.rotation = self.direction;
+    }
+
+    /// Damages every living enemy within `radius` of the front of the projectile that it hasn't
+    /// already hit, leaves a ring of particles, and marks the projectile for deletion
+    fn detonate(
+        &mut self,
+        radius: f64,
+        enemies: &mut HopSlotMap<EnemyKey, Enemy>,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        context: &ProjectileContext,
+        rng: &mut Rng,
+    ) {
+        let center = self.position * point![self.properties.distance_to_front(), 0.0];
+        let blast = Shape::Circle { radius };
+        let blast_position = Isometry2::translation(center.x, center.y);
+
+        let candidates = match context.grid {
+            Some(grid) => (grid.query_region(BoundingBox::around(center, vector![radius, radius])))
+                .collect::<Vec<_>>(),
+            None => enemies.keys().collect(),
+        };
+
+        for key in candidates {
+            let Some(enemy) = enemies.get_mut(key) else {
+                continue;
+            };
+            if enemy.should_delete() || enemy.is_immune() || self.enemies_hit.contains(&key) {
+                continue;
+            }
+
+            let enemy_object = Self::nearest_enemy_object(&self.object, enemy, context);
+            if !collision::overlaps(
+                &blast,
+                &blast_position,
+                &enemy_object.shape,
+                &enemy_object.position,
+            ) {
+                continue;
+            }
+
+            enemy.hit(self.properties.damage, Some(self.source), context.tick);
+            if enemy.should_delete() {
+                enemy.die(
+                    center,
+                    self.linear_velocity,
+                    context.explosion,
+                    particles,
+                    Enemy::explosion_seed(key, context.tick),
+                    rng,
+                );
+            }
+
+            self.enemies_hit.push(key);
+        }
+
+        for i in 0..Self::BLAST_PARTICLE_COUNT {
+            let angle = i as f64 / Self::BLAST_PARTICLE_COUNT as f64 * TAU;
+            let target_position = center + UnitComplex::new(angle) * vector![radius, 0.0];
+
             particles.insert(Particle {
                 transform: Transform {
-                    position: self.position_of_particle(
-                        -self.properties.distance_to_back() - self.distance_since_particle + 0.1,
-                    ),
+                    position: Isometry2::from_parts(center.into(), UnitComplex::new(angle)),
                     linear_velocity: vector![0.0, 0.0],
                     angular_velocity: 0.0,
                 },
-                target_position: None,
+                target_position: Some((target_position, 10.0)),
                 color: Color::from_hex(0x00ffff),
                 time_since_creation: 0.0,
                 maximum_lifetime: 2.0 / 3.0,
-                texture: GLITTER_TEXTURES[macroquad::rand::gen_range(0, GLITTER_TEXTURES.len())]
-                    .texture
-                    .clone(),
+                texture: GLITTER_TEXTURES[rng.gen_range(0, GLITTER_TEXTURES.len())].clone(),
                 start: None,
                 size: vector![2, 2],
+                pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+                layer: ParticleLayer::AboveEntities,
+                shrink: false,
+                fade_curve: tween::linear,
             });
         }
 
-        // Collisions
-        self.time_since_collision += dt;
+        self.exploded = true;
+    }
 
-        for (key, enemy) in &mut *enemies {
-            if !(self.enemies_intersecting.contains(&key) || self.enemies_colliding.contains(&key))
-                && self.object.is_colliding(&enemy.object)
-            {
-                enemy.hit(self.properties.damage);
-                if enemy.should_delete() {
-                    enemy.explode(
-                        self.position.translation
-                            * point![self.properties.distance_to_front(), 0.0],
-                        self.linear_velocity / speed_multiplier,
-                        particles,
-                    );
-                } else {
-                    self.enemies_colliding.push(key);
-                    self.enemies_intersecting.push(key);
+    /// Re-aims at the nearest living enemy within range other than `from`, if there is one
+    fn ricochet(
+        &mut self,
+        from: EnemyKey,
+        enemies: &HopSlotMap<EnemyKey, Enemy>,
+        context: &ProjectileContext,
+    ) {
+        let Some(ricochet) = self.properties.ricochet else {
+            return;
+        };
+
+        let target = enemies
+            .iter()
+            .filter(|&(key, enemy)| key != from && !enemy.should_delete() && !enemy.is_immune())
+            .map(|(key, enemy)| {
+                let offset = Self::nearest_enemy_object(&self.object, enemy, context)
+                    .position
+                    .translation
+                    .vector
+                    - self.position.translation.vector;
+
+                (key, offset)
+            })
+            .filter(|(_, offset)| offset.norm() <= ricochet.range)
+            .min_by(|(_, a), (_, b)| a.norm().total_cmp(&b.norm()));
+
+        let Some((key, offset)) = target else {
+            return;
+        };
+
+        self.bounces_left -= 1;
+        self.ricochet_target = Some(key);
+        self.direction = UnitComplex::new(offset.y.atan2(offset.x));
+        self.object.position.rotation = self.direction;
+    }
+
+    /// The order to check enemies for collisions in, nearest first along the direction of travel,
+    /// so that pierce falloff and kill credit go to whatever the projectile would reach first.
+    /// Ties keep the context's enemy order, or storage order without one.
+    fn collision_order(
+        &self,
+        enemies: &HopSlotMap<EnemyKey, Enemy>,
+        context: &ProjectileContext,
+    ) -> Vec<EnemyKey> {
+        let forward = self.position.rotation * vector![1.0, 0.0];
+
+        let depth_of = |key| {
+            let object = Self::nearest_enemy_object(&self.object, &enemies[key], context);
+            (object.position.translation.vector - self.position.translation.vector).dot(&forward)
+        };
+
+        let mut order: Vec<_> = match (context.grid, context.arena, context.enemy_order) {
+            (Some(grid), None, enemy_order) => {
+                let half_size = vector![1.0, 1.0] * self.shape.bounding_radius();
+                let bounds =
+                    BoundingBox::around(self.position.translation.vector.into(), half_size);
+
+                let mut keys = (grid.query_region(bounds))
+                    .filter(|&key| enemies.contains_key(key))
+                    .collect::<Vec<_>>();
+                if enemy_order.is_some() {
+                    keys.sort_unstable_by_key(|&key| enemies[key].spawn_index);
                 }
 
-                self.add_hit_particles(particles);
-                self.enemies_hit.push(key);
-                self.time_since_collision = 0.0;
+                keys.into_iter().map(|key| (depth_of(key), key)).collect()
             }
+            (_, _, Some(enemy_order)) => (enemy_order.iter())
+                .copied()
+                .filter(|&key| enemies.contains_key(key))
+                .map(|key| (depth_of(key), key))
+                .collect(),
+            (_, _, None) => enemies.keys().map(|key| (depth_of(key), key)).collect(),
+        };
+        order.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        order.into_iter().map(|(_, key)| key).collect()
+    }
+
+    /// The enemy's object, moved across the arena's seams to wherever is closest to `object`
+    fn nearest_enemy_object(object: &Object, enemy: &Enemy, context: &ProjectileContext) -> Object {
+        match context.arena {
+            Some(arena) => arena.nearest_object(object, &enemy.object),
+            None => enemy.object,
         }
+    }
 
-        self.enemies_colliding.retain(|&key| {
-            enemies.get(key).is_some_and(|enemy| {
-                !enemy.should_delete()
-                    && self.object.shape.is_colliding(
-                        &enemy.shape,
-                        Isometry2::new(
-                            -vector![
-                                self.properties.distance_to_front()
-                                    + self.properties.distance_to_back(),
-                                0.0
-                            ],
-                            0.0,
-                        ) * self.object.offset_to(&enemy),
-                    )
-            })
-        });
+    fn collide_with(
+        &mut self,
+        key: EnemyKey,
+        enemy: &mut Enemy,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        context: &ProjectileContext,
+        speed_multiplier: f64,
+        rng: &mut Rng,
+    ) {
+        if enemy.is_immune()
+            || (self.enemies_intersecting.contains(&key) || self.enemies_colliding.contains(&key))
+            || self.recent_hits.iter().any(|&(recent, _)| recent == key)
+        {
+            return;
+        }
 
-        self.enemies_intersecting.retain(|&key| {
-            enemies
-                .get(key)
-                .is_some_and(|enemy| !enemy.should_delete() && self.object.is_colliding(&enemy))
-        });
+        let enemy_object = Self::nearest_enemy_object(&self.object, enemy, context);
+        if !self.object.bounding_circle_overlaps(&enemy_object)
+            || !self.object.is_colliding(&enemy_object)
+        {
+            return;
+        }
 
-        if self.enemies_colliding.is_empty() {
-            self.time_since_exit += dt;
+        let contact = self.position * point![self.properties.distance_to_front(), 0.0];
+        let velocity = self.linear_velocity / speed_multiplier;
+
+        enemy.hit(self.effective_damage(), Some(self.source), context.tick);
+        if enemy.should_delete() {
+            enemy.die(
+                contact,
+                velocity,
+                context.explosion,
+                particles,
+                Enemy::explosion_seed(key, context.tick),
+                rng,
+            );
         } else {
-            self.time_since_exit = 0.0;
+            enemy.spin_from_hit(contact, velocity);
+            self.enemies_colliding.push(key);
+            self.enemies_intersecting.push(key);
+
+            if let Some(properties) = &self.properties.tether
+                && self.tether.is_none()
+            {
+                self.tether = Some(Tether::new(key, self.origin, properties));
+            }
+        }
+
+        self.ricochet_target = None;
+        if self.bounces_left > 0 {
+            self.ricochet_from = Some(key);
         }
+
+        // Fan the particles out along the enemy's surface
+        let normal = (enemy_object.shape)
+            .collision(
+                &self.object.shape,
+                enemy_object.position.inv_mul(&self.position),
+            )
+            .map(|collision| enemy_object.position.rotation * collision.normal);
+
+        self.add_hit_particles(particles, normal, rng);
+        if self.is_overcharged() {
+            // A second fan along the direction of travel, making a cross
+            let along = self.position.rotation * UnitComplex::new(FRAC_PI_2);
+            self.add_hit_particles(particles, Some(along), rng);
+        }
+        self.enemies_hit.push(key);
+        self.recent_hits.push((key, 0.0));
+        self.time_since_collision = 0.0;
     }
 
-    pub fn draw(&self) {
-        let opacity = if self.enemies_colliding.is_empty() {
-            1.0
+    /// Turns the projectile around and hands it to the player, crediting `source`
+    pub fn reflect(&mut self, source: SourceId) {
+        self.faction = Faction::Player;
+        self.source = source;
+        self.direction *= UnitComplex::new(PI);
+        self.object.position.rotation = self.direction;
+        self.fall_velocity = -self.fall_velocity;
+    }
+
+    /// The portion of its damage the projectile still deals, after falloff from piercing enemies
+    #[must_use]
+    pub fn damage_ratio(&self) -> f64 {
+        self.properties.damage_ratio(self.enemies_hit.len())
+    }
+
+    /// The damage the next hit will deal, never less than one
+    #[must_use]
+    pub fn effective_damage(&self) -> u32 {
+        self.properties
+            .damage_after_hits(self.enemies_hit.len(), self.distance_travelled)
+    }
+
+    /// Whether a hit now would get the overcharge bonus
+    #[must_use]
+    pub fn is_overcharged(&self) -> bool {
+        self.properties.overcharge_bonus != 1.0
+            && self.distance_travelled < self.properties.overcharge_distance
+    }
+
+    /// Dimmed while passing through an enemy, fading back in after leaving it
+    #[must_use]
+    pub fn opacity(&self) -> f64 {
+        if self.enemies_colliding.is_empty() {
+            tween::exp_decay(
+                Self::COLLISION_OPACITY,
+                1.0,
+                Self::EXIT_FADE_DECAY,
+                self.time_since_exit,
+            )
         } else {
             Self::COLLISION_OPACITY
-        };
+        }
+    }
+
+    /// Draws the projectile, tinted by how much damage it deals if `tint_by_damage` is set
+    pub fn draw(&self, tint_by_damage: bool) {
+        // Spent piercing projectiles look thinner and dimmer
+        let damage_ratio = self.damage_ratio();
+        let opacity = self.opacity() * damage_ratio;
+
+        if let Some(tether) = &self.tether {
+            tether.draw(self.position.translation.vector.into(), opacity);
+        }
+
+        let color = self.color(tint_by_damage);
+
+        for (newer, older, fade) in self.streak_segments() {
+            shapes::draw_line(
+                newer.x as f32,
+                newer.y as f32,
+                older.x as f32,
+                older.y as f32,
+                Self::STREAK_THICKNESS as f32,
+                Color {
+                    a: (opacity * fade * Self::STREAK_OPACITY) as f32,
+                    ..color
+                },
+            );
+        }
 
         shapes::draw_rectangle_ex(
             self.position.translation.x as f32,
             self.position.translation.y as f32,
             self.properties.size.x as f32,
-            self.properties.size.y as f32,
+            (self.properties.size.y * damage_ratio) as f32,
             DrawRectangleParams {
                 offset: [0.5, 0.5].into(),
                 rotation: self.position.rotation.angle() as f32,
                 color: Color {
                     a: opacity as f32,
-                    ..utils::brighten_color(Self::COLOR, 1.0 - opacity)
+                    ..utils::brighten_color(color, 1.0 - opacity)
                 },
             },
         );
     }
 
-    pub fn add_hit_particles(&self, particles: &mut HopSlotMap<ParticleKey, Particle>) {
-        let start_position = self.position_of_particle(self.properties.distance_to_front() - 0.1);
+    /// The segments of the streak behind the projectile, from newest to oldest, as pairs of
+    /// past positions and how visible each segment is
+    pub fn streak_segments(&self) -> impl Iterator<Item = (Point2<f64>, Point2<f64>, f64)> {
+        let points = self
+            .history
+            .iter()
+            .map(|position| Point2::from(position.translation.vector));
+
+        points
+            .clone()
+            .zip(points.skip(1))
+            .enumerate()
+            .filter(|(_, (newer, older))| (newer - older).norm() <= Self::STREAK_BREAK_DISTANCE)
+            .map(|(index, (newer, older))| {
+                let fade = 1.0 - (index + 1) as f64 / Self::HISTORY_LENGTH as f64;
+
+                (newer, older, fade)
+            })
+    }
+
+    /// The projectile's base color, going from cyan through white to yellow as its effective
+    /// damage rises if `tint_by_damage` is set
+    #[must_use]
+    pub fn color(&self, tint_by_damage: bool) -> Color {
+        if !tint_by_damage {
+            return Self::COLOR;
+        }
+
+        let t = ((self.effective_damage().saturating_sub(1)) as f32
+            / (Self::HIGH_DAMAGE - 1) as f32)
+            .min(1.0);
+
+        if t < 0.5 {
+            utils::color_lerp(Self::COLOR, Self::MEDIUM_DAMAGE_COLOR, t * 2.0)
+        } else {
+            utils::color_lerp(
+                Self::MEDIUM_DAMAGE_COLOR,
+                Self::HIGH_DAMAGE_COLOR,
+                t * 2.0 - 1.0,
+            )
+        }
+    }
+
+    /// Spawns particles that fan out sideways from the front of the projectile, or along the
+    /// surface if a `normal` is given
+    pub fn add_hit_particles(
+        &self,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        normal: Option<UnitComplex<f64>>,
+        rng: &mut Rng,
+    ) {
+        let start_position =
+            self.position_of_particle(self.properties.distance_to_front() - 0.1, rng);
+        let fan_rotation = normal.unwrap_or(self.position.rotation);
+        let jitter = self.properties.hit_particle_jitter;
 
-        for target_position in (1..self.properties.hit_particle_radius + 1)
+        let target_positions = (1..)
             .map(|x| x as f64 * self.properties.hit_particle_distance)
             .flat_map(|x| [x, -x])
-            .map(|x| self.position.rotation * point![0.0, x] + start_position.translation.vector)
-        {
+            .take(self.properties.hit_particle_count)
+            .map(|x| {
+                let x = if jitter > 0.0 {
+                    x + rng.gen_range(-jitter, jitter)
+                } else {
+                    x
+                };
+
+                fan_rotation * point![0.0, x] + start_position.translation.vector
+            })
+            .collect::<Vec<_>>();
+
+        for target_position in target_positions {
             particles.insert(Particle {
                 transform: Transform {
                     position: start_position,
@@ -284,26 +1258,94 @@ impl Projectile {
                 color: Color::from_hex(0x00ffff),
                 time_since_creation: 0.0,
                 maximum_lifetime: 2.0 / 3.0,
-                texture: GLITTER_TEXTURES[macroquad::rand::gen_range(0, GLITTER_TEXTURES.len())]
-                    .texture
-                    .clone(),
+                texture: GLITTER_TEXTURES[rng.gen_range(0, GLITTER_TEXTURES.len())].clone(),
                 start: None,
                 size: vector![2, 2],
+                pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+                layer: ParticleLayer::AboveEntities,
+                shrink: false,
+                fade_curve: tween::linear,
             });
         }
     }
 
-    pub fn position_of_particle(&self, offset: f64) -> Isometry2<f64> {
+    /// Spawns a small burst from the back of the projectile as it leaves an enemy
+    pub fn add_exit_particles(
+        &self,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        rng: &mut Rng,
+    ) {
+        let start_position =
+            self.position_of_particle(-self.properties.distance_to_back() + 0.1, rng);
+        let distance = self.properties.hit_particle_distance * Self::EXIT_PARTICLE_DISTANCE;
+
+        for side in [1.0, -1.0] {
+            let target_position = start_position.translation.vector
+                + self.position.rotation * vector![-distance, side * distance];
+
+            particles.insert(Particle {
+                transform: Transform {
+                    position: start_position,
+                    linear_velocity: vector![0.0, 0.0],
+                    angular_velocity: 0.0,
+                },
+                target_position: Some((target_position.into(), 20.0)),
+                color: Self::COLOR,
+                time_since_creation: 0.0,
+                maximum_lifetime: 1.0 / 3.0,
+                texture: GLITTER_TEXTURES[rng.gen_range(0, GLITTER_TEXTURES.len())].clone(),
+                start: None,
+                size: vector![2, 2],
+                pixel_scale: Particle::DEFAULT_PIXEL_SCALE,
+                layer: ParticleLayer::AboveEntities,
+                shrink: false,
+                fade_curve: tween::linear,
+            });
+        }
+    }
+
+    pub fn position_of_particle(&self, offset: f64, rng: &mut Rng) -> Isometry2<f64> {
         let translation = self.position * point![offset, 0.0];
 
-        let rotation = self.position.rotation
-            * UnitComplex::new(macroquad::rand::gen_range(0, 3) as f64 / 4.0 * TAU);
+        let rotation =
+            self.position.rotation * UnitComplex::new(rng.gen_range(0usize, 3) as f64 / 4.0 * TAU);
 
         Isometry2::from_parts(translation.into(), rotation)
     }
 
+    /// Like [`Projectile::position_of_particle`], but `progress` of the way from
+    /// `previous_position` to the current position
+    pub fn position_of_particle_between(
+        &self,
+        previous_position: Isometry2<f64>,
+        progress: f64,
+        offset: f64,
+        rng: &mut Rng,
+    ) -> Isometry2<f64> {
+        let start = previous_position * point![offset, 0.0];
+        let end = self.position * point![offset, 0.0];
+
+        Isometry2::from_parts(
+            start.coords.lerp(&end.coords, progress).into(),
+            self.position_of_particle(offset, rng).rotation,
+        )
+    }
+
+    /// Stops tracking `key` as an enemy the projectile is passing through, without any exit
+    /// particles. Called when the enemy is removed from the game.
+    pub fn forget_enemy(&mut self, key: EnemyKey) {
+        self.enemies_colliding.retain(|&colliding| colliding != key);
+        self.enemies_intersecting
+            .retain(|&intersecting| intersecting != key);
+    }
+
     pub fn should_delete(&self) -> bool {
-        !(self.properties.piercing || self.enemies_hit.is_empty())
+        self.exploded
+            || !(self.properties.piercing
+                || self.enemies_hit.is_empty()
+                || self.tether.is_some()
+                || self.ricochet_from.is_some()
+                || self.ricochet_target.is_some())
     }
 }
 
@@ -320,3 +1362,706 @@ impl DerefMut for Projectile {
         &mut self.object
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use nalgebra::{Isometry2, point, vector};
+
+    use super::*;
+    use crate::{enemy::ENEMY_KINDS, game::Game};
+
+    const DT: f64 = 1.0 / 120.0;
+
+    fn tick(game: &mut Game, ticks: usize) {
+        let camera_bounds = Shape::Rectangle {
+            half_size: vector![20.0, 20.0],
+        };
+
+        for _ in 0..ticks {
+            game.tick_with_input(point![0.0, 10.0], camera_bounds, DT);
+        }
+    }
+
+    /// Inserts an enemy of `kind` that is past its spawn immunity and doesn't move
+    fn stationary_enemy(game: &mut Game, kind: usize, position: Isometry2<f64>) -> EnemyKey {
+        let mut enemy = Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(position, &ENEMY_KINDS[kind])
+        };
+        enemy.properties.speed = 0.0;
+
+        game.insert_enemy(enemy)
+    }
+
+    /// A game with a red square at `(0, 4)` heading for the turret that is past its spawn immunity,
+    /// and optionally a classic projectile flying up at it `lateral_offset` from its center
+    fn square_target(lateral_offset: Option<f64>) -> (Game, EnemyKey) {
+        let mut game = Game::with_seed(1);
+
+        let key = game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(
+                Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2),
+                &ENEMY_KINDS[3],
+            )
+        });
+
+        if let Some(offset) = lateral_offset {
+            game.projectiles.insert(Projectile::new(
+                Isometry2::new(vector![offset, 2.0], FRAC_PI_2),
+                &PROJECTILE_KINDS[0],
+                &mut Rng::new(0),
+            ));
+        }
+
+        (game, key)
+    }
+
+    fn angular_velocity_after_shot(lateral_offset: f64) -> (f64, f64) {
+        let (mut shot, key) = square_target(Some(lateral_offset));
+        let (mut unshot, _) = square_target(None);
+
+        tick(&mut shot, 20);
+        tick(&mut unshot, 20);
+
+        assert!(shot.enemies[key].health < shot.enemies[key].properties.maximum_health);
+
+        (
+            shot.enemies[key].angular_velocity,
+            unshot.enemies.values().next().unwrap().angular_velocity,
+        )
+    }
+
+    #[test]
+    fn edge_hit_spins_square() {
+        let (shot, unshot) = angular_velocity_after_shot(0.45);
+
+        assert!((shot - unshot).abs() > 1e-3);
+    }
+
+    #[test]
+    fn streak_follows_the_position_history() {
+        let mut game = Game::with_seed(1);
+        let key = game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 2.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, 3);
+        let segments = game.projectiles[key].streak_segments().collect::<Vec<_>>();
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(
+            segments[0].0,
+            Point2::from(game.projectiles[key].position.translation.vector),
+        );
+        assert!(segments.iter().all(|(newer, older, _)| newer.y > older.y));
+
+        tick(&mut game, 2 * Projectile::HISTORY_LENGTH);
+        let segments = game.projectiles[key].streak_segments().collect::<Vec<_>>();
+
+        assert_eq!(segments.len(), Projectile::HISTORY_LENGTH - 1);
+        assert!(segments.windows(2).all(|pair| pair[0].2 > pair[1].2));
+    }
+
+    #[test]
+    fn center_hit_on_rotated_shot_does_not_spin() {
+        let (shot, unshot) = angular_velocity_after_shot(0.0);
+
+        assert!((shot - unshot).abs() < 1e-9);
+    }
+
+    /// Where each hit particle of a classic projectile heads, across `normal`, sorted
+    fn hit_particle_offsets(count: usize, distance: f64, jitter: f64) -> Vec<f64> {
+        let mut projectile = Projectile::new(
+            Isometry2::new(vector![1.0, 1.0], 0.2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        );
+        projectile.properties.hit_particle_count = count;
+        projectile.properties.hit_particle_distance = distance;
+        projectile.properties.hit_particle_jitter = jitter;
+
+        let normal = UnitComplex::new(1.3);
+        let mut particles = HopSlotMap::with_key();
+        projectile.add_hit_particles(&mut particles, Some(normal), &mut Rng::new(17));
+
+        let mut offsets = (particles.values())
+            .map(|particle| {
+                let (target, _) = particle.target_position.unwrap();
+                let offset = normal.inverse() * (target - particle.position.translation.vector);
+                assert!(offset.x.abs() < 1e-12);
+                offset.y
+            })
+            .collect::<Vec<_>>();
+        offsets.sort_by(f64::total_cmp);
+
+        offsets
+    }
+
+    #[test]
+    fn hit_particles_follow_the_configured_fan() {
+        let offsets = hit_particle_offsets(5, 0.5, 0.0);
+        let expected = [-1.0, -0.5, 0.5, 1.0, 1.5];
+
+        assert_eq!(offsets.len(), expected.len());
+        for (offset, expected) in offsets.iter().zip(expected) {
+            assert!((offset - expected).abs() < 1e-12);
+        }
+
+        let jittered = hit_particle_offsets(5, 0.5, 0.1);
+        assert_ne!(jittered, offsets);
+        for (offset, expected) in jittered.iter().zip(expected) {
+            assert!((offset - expected).abs() <= 0.1);
+        }
+
+        assert!(hit_particle_offsets(0, 0.5, 0.0).is_empty());
+    }
+
+    #[test]
+    fn projectiles_fade_back_in_after_passing_through() {
+        let (mut game, _) = square_target(Some(0.0));
+        let mut opacities = Vec::new();
+
+        for _ in 0..90 {
+            tick(&mut game, 1);
+            let projectile = game.projectiles.values().next().unwrap();
+            opacities.push((
+                projectile.enemies_colliding.is_empty(),
+                projectile.opacity(),
+            ));
+        }
+
+        let entered = opacities.iter().position(|&(clear, _)| !clear).unwrap();
+        let exited = entered
+            + opacities[entered..]
+                .iter()
+                .position(|&(clear, _)| clear)
+                .unwrap();
+
+        assert!(
+            opacities[..entered]
+                .iter()
+                .all(|&(_, opacity)| opacity == 1.0)
+        );
+        assert!(
+            (opacities[entered..exited].iter())
+                .all(|&(_, opacity)| opacity == Projectile::COLLISION_OPACITY)
+        );
+
+        let fading = &opacities[exited..exited + 4];
+        assert!(fading[0].1 < 0.95);
+        assert!(fading.windows(2).all(|pair| pair[1].1 > pair[0].1));
+        assert!(opacities.last().unwrap().1 > 0.999);
+    }
+
+    #[test]
+    fn enemies_remember_who_hit_them_last() {
+        let (mut game, key) = square_target(Some(0.0));
+        assert_eq!(game.enemies[key].last_hit_source, None);
+        for projectile in game.projectiles.values_mut() {
+            projectile.source = SourceId(7);
+        }
+
+        loop {
+            let tick_count = game.tick_count;
+            tick(&mut game, 1);
+
+            let enemy = &game.enemies[key];
+            if enemy.health < enemy.properties.maximum_health {
+                assert_eq!(enemy.last_hit_source, Some(SourceId(7)));
+                assert_eq!(enemy.last_hit_tick, tick_count);
+                break;
+            }
+
+            assert!(tick_count < 60, "the projectile never hit");
+        }
+    }
+
+    #[test]
+    fn passing_through_leaves_exit_particles_behind() {
+        let (mut game, key) = square_target(Some(0.0));
+        let mut was_colliding = false;
+
+        for _ in 0..60 {
+            let particle_keys = game.particles.keys().collect::<Vec<_>>();
+            tick(&mut game, 1);
+
+            let projectile = game.projectiles.values().next().unwrap();
+            if projectile.enemies_colliding.is_empty() && was_colliding {
+                let new_particles = (game.particles.iter())
+                    .filter(|(key, _)| !particle_keys.contains(key))
+                    .map(|(_, particle)| particle)
+                    .collect::<Vec<_>>();
+                let back = projectile.position
+                    * point![-projectile.properties.distance_to_back() + 0.1, 0.0];
+                let distance = projectile.properties.hit_particle_distance
+                    * Projectile::EXIT_PARTICLE_DISTANCE;
+
+                assert!(game.enemies.contains_key(key));
+                assert_eq!(new_particles.len(), 2);
+
+                let mut sides = new_particles
+                    .iter()
+                    .map(|particle| {
+                        let (target, _) = particle.target_position.unwrap();
+                        let offset = projectile.position.rotation.inverse() * (target - back);
+
+                        assert!((offset.x + distance).abs() < 1e-9);
+                        offset.y.signum()
+                    })
+                    .collect::<Vec<_>>();
+                sides.sort_by(f64::total_cmp);
+                assert_eq!(sides, [-1.0, 1.0]);
+
+                return;
+            }
+
+            was_colliding |= !projectile.enemies_colliding.is_empty();
+        }
+
+        panic!("the projectile never passed through");
+    }
+
+    #[test]
+    fn mortar_shells_arc_along_a_parabola() {
+        let mortar = &PROJECTILE_KINDS[5];
+        let gravity = mortar.properties.gravity;
+        assert!(gravity.y < 0.0);
+
+        let mut game = Game::with_seed(22);
+        let key = game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![2.0, 0.0], 1.0),
+            mortar,
+            &mut Rng::new(0),
+        ));
+
+        let mut samples = Vec::new();
+        for _ in 0..40 {
+            tick(&mut game, 1);
+            let projectile = &game.projectiles[key];
+            samples.push((projectile.position, projectile.linear_velocity));
+        }
+
+        assert!(samples.windows(2).all(|pair| pair[1].1.y < pair[0].1.y));
+
+        for triple in samples.windows(3) {
+            let [(a, _), (b, _), (c, _)] = triple else {
+                unreachable!()
+            };
+            let first = b.translation.vector - a.translation.vector;
+            let second = c.translation.vector - b.translation.vector;
+
+            assert!((second.x - first.x).abs() < 1e-9);
+            assert!((second.y - first.y - gravity.y * DT * DT).abs() < 1e-9);
+        }
+
+        for (position, velocity) in samples {
+            let heading = position.rotation * vector![1.0, 0.0];
+            assert!((heading - velocity.normalize()).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn harpoons_drag_enemies_in_until_the_tether_breaks() {
+        let mut game = Game::with_seed(1);
+        let anchor = point![0.0, 4.0];
+        let key = game.enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(
+                Isometry2::new(vector![0.0, 7.0], FRAC_PI_2),
+                &ENEMY_KINDS[4],
+            )
+        });
+        let harpoon = game.projectiles.insert(Projectile::new(
+            Isometry2::new(anchor.coords, FRAC_PI_2),
+            &PROJECTILE_KINDS[6],
+            &mut Rng::new(0),
+        ));
+        let distance =
+            |game: &Game| (game.enemies[key].position.translation.vector - anchor.coords).norm();
+
+        while game.projectiles[harpoon].tether.is_none() {
+            tick(&mut game, 1);
+        }
+
+        // The enemy's own momentum carries it a little further before the spring takes over
+        let tethered_distance = distance(&game);
+        tick(&mut game, 10);
+        let mut last_distance = distance(&game);
+        for _ in 0..5 {
+            tick(&mut game, 10);
+            assert!(distance(&game) < last_distance);
+            last_distance = distance(&game);
+        }
+        assert!(last_distance < tethered_distance * 0.8);
+
+        tick(
+            &mut game,
+            (PROJECTILE_KINDS[6].properties.tether.unwrap().duration / DT) as usize,
+        );
+        assert!(!game.projectiles.contains_key(harpoon));
+        assert!(game.enemies[key].tether.is_none());
+
+        let released_distance = distance(&game);
+        tick(&mut game, 60);
+        assert!(distance(&game) > released_distance);
+    }
+
+    #[test]
+    fn ricochets_visit_each_enemy_in_turn() {
+        let mut game = Game::with_seed(1);
+        let targets = [vector![0.0, 6.0], vector![8.0, 6.0], vector![8.0, 12.0]]
+            .map(|position| stationary_enemy(&mut game, 4, Isometry2::new(position, PI)));
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 3.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[7],
+            &mut Rng::new(0),
+        ));
+
+        let mut visited = Vec::new();
+        for _ in 0..240 {
+            tick(&mut game, 1);
+
+            for key in targets {
+                let enemy = &game.enemies[key];
+                if enemy.health < enemy.properties.maximum_health && !visited.contains(&key) {
+                    visited.push(key);
+                }
+            }
+        }
+
+        assert_eq!(visited, targets);
+        assert!(game.projectiles.is_empty());
+    }
+
+    #[test]
+    fn freshly_spawned_enemies_are_immune_to_projectiles() {
+        let mut game = Game::with_seed(1);
+        let key = game.enemies.insert(Enemy::new(
+            Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2),
+            &ENEMY_KINDS[3],
+        ));
+        let shoot = |game: &mut Game| {
+            game.projectiles.insert(Projectile::new(
+                Isometry2::new(vector![0.0, 3.0], FRAC_PI_2),
+                &PROJECTILE_KINDS[0],
+                &mut Rng::new(0),
+            ))
+        };
+        let maximum_health = game.enemies[key].properties.maximum_health;
+
+        let early = shoot(&mut game);
+        while game.projectiles[early].position.translation.vector.y < 5.5 {
+            tick(&mut game, 1);
+        }
+        assert!(game.enemies[key].is_immune());
+        assert_eq!(game.enemies[key].health, maximum_health);
+
+        game.projectiles.remove(early);
+        while game.enemies[key].is_immune() {
+            tick(&mut game, 1);
+        }
+
+        let enemy_y = game.enemies[key].position.translation.vector.y;
+        let late = shoot(&mut game);
+        while game.projectiles[late].position.translation.vector.y < enemy_y + 1.5 {
+            tick(&mut game, 1);
+        }
+        assert!(game.enemies[key].health < maximum_health);
+    }
+
+    #[test]
+    fn piercing_projectiles_thin_out_as_their_damage_falls_off() {
+        let mut game = Game::with_seed(1);
+        let enemies = [4.0, 8.0].map(|y| {
+            let key = stationary_enemy(&mut game, 4, Isometry2::new(vector![0.0, y], PI));
+            let enemy = &mut game.enemies[key];
+            enemy.properties.maximum_health = 100;
+            enemy.health = 100;
+            key
+        });
+        let mut projectile = Projectile::new(
+            Isometry2::new(vector![0.0, 2.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        );
+        projectile.properties.damage = 8;
+        projectile.properties.pierce_falloff = 0.5;
+        let key = game.projectiles.insert(projectile);
+
+        let mut ratios = vec![game.projectiles[key].damage_ratio()];
+        while game.projectiles[key].position.translation.vector.y < 10.0 {
+            tick(&mut game, 1);
+
+            let ratio = game.projectiles[key].damage_ratio();
+            if ratio != *ratios.last().unwrap() {
+                ratios.push(ratio);
+            }
+        }
+        assert_eq!(ratios, [1.0, 0.5, 0.25]);
+
+        // The damage each enemy actually took follows the ratio the projectile is drawn with
+        let damage_taken = enemies.map(|key| 100 - game.enemies[key].health);
+        assert_eq!(damage_taken, [8, 4]);
+
+        let solid = ProjectileProperties {
+            piercing: false,
+            pierce_falloff: 0.5,
+            ..PROJECTILE_KINDS[0].properties
+        };
+        assert_eq!(solid.damage_ratio(2), 1.0);
+    }
+
+    /// The sideways offsets of the glitter left by a projectile flying straight up for 30 ticks
+    fn glitter_offsets(per_segment: usize, spread: f64) -> Vec<f64> {
+        let mut game = Game::with_seed(1);
+        let mut projectile = Projectile::new(
+            Isometry2::new(vector![0.0, 1.0], FRAC_PI_2),
+            &PROJECTILE_KINDS[0],
+            &mut Rng::new(0),
+        );
+        projectile.properties.trail_particles_per_segment = per_segment;
+        projectile.properties.trail_particle_spread = spread;
+        game.projectiles.insert(projectile);
+
+        tick(&mut game, 30);
+
+        (game.particles.values())
+            .map(|particle| particle.transform.position.translation.vector.x)
+            .collect()
+    }
+
+    #[test]
+    fn trail_segments_leave_the_configured_glitter() {
+        let single = glitter_offsets(1, 0.0);
+        assert!(single.len() >= 3);
+        assert!(single.iter().all(|&x| x.abs() < 1e-9));
+
+        let spread = glitter_offsets(3, 0.3);
+        assert_eq!(spread.len(), single.len() * 3);
+        assert!(spread.iter().all(|&x| x.abs() <= 0.3));
+        assert!(spread.iter().any(|&x| x.abs() > 0.05));
+    }
+
+    #[test]
+    fn damage_tint_only_shows_when_enabled() {
+        let with_damage = |damage| {
+            let mut projectile = Projectile::new(
+                Isometry2::identity(),
+                &PROJECTILE_KINDS[0],
+                &mut Rng::new(0),
+            );
+            projectile.properties.damage = damage;
+            projectile
+        };
+        let weak = with_damage(1);
+        let strong = with_damage(Projectile::HIGH_DAMAGE);
+
+        let is_close = |a: Color, b: Color| (a.to_vec() - b.to_vec()).abs().max_element() < 1e-6;
+        assert!(is_close(weak.color(true), Projectile::COLOR));
+        assert!(is_close(strong.color(true), Projectile::HIGH_DAMAGE_COLOR));
+
+        assert_eq!(weak.color(false), Projectile::COLOR);
+        assert_eq!(strong.color(false), Projectile::COLOR);
+    }
+
+    #[test]
+    fn fast_trails_are_spread_evenly_along_the_path() {
+        let kind = &PROJECTILE_KINDS[1];
+        let mut projectile = Projectile::new(
+            Isometry2::new(vector![0.0, 1.0], FRAC_PI_2),
+            kind,
+            &mut Rng::new(0),
+        );
+        projectile.distance_since_particle = 0.0;
+        let mut particles = HopSlotMap::with_key();
+
+        // Covers five particle distances in a single step
+        let dt = kind.properties.particle_distance * 5.0 / kind.properties.speed;
+        projectile.tick(
+            &mut HopSlotMap::with_key(),
+            &mut particles,
+            &mut Vec::new(),
+            &ProjectileContext {
+                explosion: &ExplosionParameters::default(),
+                enemy_order: None,
+                tick: 0,
+                arena: None,
+                grid: None,
+            },
+            &mut Rng::new(1),
+            dt,
+        );
+
+        let mut heights = (particles.values())
+            .map(|particle| particle.transform.position.translation.vector.y)
+            .collect::<Vec<_>>();
+        heights.sort_by(f64::total_cmp);
+
+        assert_eq!(heights.len(), 5);
+        for pair in heights.windows(2) {
+            let spacing = pair[1] - pair[0];
+            assert!((spacing - kind.properties.particle_distance).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn the_nearest_of_several_touched_enemies_is_hit_first() {
+        let mut game = Game::with_seed(1);
+        // Stored before the nearer one, so storage order alone would hit it first
+        let [far, near] = [5.0, 4.0]
+            .map(|y| stationary_enemy(&mut game, 4, Isometry2::new(vector![0.0, y], 0.0)));
+
+        // Long enough to reach into both enemies on its first tick
+        let mut kind = PROJECTILE_KINDS[0].clone();
+        kind.properties.size = vector![4.0, 0.2];
+        kind.properties.pierce_falloff = 0.5;
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 2.5], FRAC_PI_2),
+            &kind,
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, 1);
+
+        let damage_taken = |key| {
+            let enemy = &game.enemies[key];
+            enemy.properties.maximum_health - enemy.health
+        };
+        assert_eq!(damage_taken(near), kind.properties.damage);
+        assert_eq!(damage_taken(far), kind.properties.damage / 2);
+    }
+
+    #[test]
+    fn projectiles_only_hit_an_enemy_again_after_the_cooldown() {
+        let (mut game, key) = square_target(Some(0.0));
+        game.enemies[key].properties.speed = 0.0;
+
+        let send_back = |game: &mut Game| {
+            let projectile = game.projectiles.values_mut().next().unwrap();
+            projectile.object.position = Isometry2::new(vector![0.0, 2.0], FRAC_PI_2);
+            projectile.enemies_colliding.clear();
+            projectile.enemies_intersecting.clear();
+        };
+
+        tick(&mut game, 20);
+        let after_first_hit = game.enemies[key].health;
+        assert!(after_first_hit < game.enemies[key].properties.maximum_health);
+
+        send_back(&mut game);
+        tick(&mut game, 20);
+        assert_eq!(game.enemies[key].health, after_first_hit);
+
+        tick(&mut game, 30);
+        assert!(
+            game.projectiles
+                .values()
+                .next()
+                .unwrap()
+                .recent_hits
+                .is_empty()
+        );
+
+        send_back(&mut game);
+        tick(&mut game, 20);
+        assert!((game.enemies.get(key)).is_none_or(|enemy| enemy.health < after_first_hit));
+    }
+
+    /// The damage a purple square at `(0, 4)` takes from a classic projectile with double
+    /// overcharge, fired up at it from `start_y`
+    fn overcharged_damage(start_y: f64) -> u32 {
+        let mut game = Game::with_seed(3);
+        let key = stationary_enemy(&mut game, 4, Isometry2::new(vector![0.0, 4.0], -FRAC_PI_2));
+
+        let mut kind = PROJECTILE_KINDS[0].clone();
+        kind.properties.overcharge_bonus = 2.0;
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, start_y], FRAC_PI_2),
+            &kind,
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, 120);
+
+        let enemy = &game.enemies[key];
+        enemy.properties.maximum_health - enemy.health
+    }
+
+    #[test]
+    fn point_blank_hits_deal_overcharge_damage() {
+        let base = PROJECTILE_KINDS[0].properties.damage;
+
+        assert_eq!(overcharged_damage(2.5), base * 2);
+        assert_eq!(overcharged_damage(-6.0), base);
+
+        let properties = ProjectileProperties {
+            overcharge_bonus: 2.0,
+            pierce_falloff: 0.5,
+            ..PROJECTILE_KINDS[0].properties
+        };
+        assert_eq!(properties.damage_after_hits(1, 0.5), base);
+        assert_eq!(properties.damage_after_hits(1, 5.0), base / 2);
+    }
+
+    #[test]
+    fn homing_projectiles_turn_towards_the_nearest_enemy_at_their_turn_rate() {
+        let seeker = &PROJECTILE_KINDS[8];
+        let turn_rate = seeker.properties.homing.unwrap();
+
+        for kind in [seeker, &PROJECTILE_KINDS[0]] {
+            let mut game = Game::with_seed(6);
+            let key = stationary_enemy(&mut game, 4, Isometry2::new(vector![6.0, 3.0], PI));
+            stationary_enemy(&mut game, 4, Isometry2::new(vector![-12.0, 8.0], 0.0));
+            let projectile = game.projectiles.insert(Projectile::new(
+                Isometry2::new(vector![0.0, 1.0], FRAC_PI_2),
+                kind,
+                &mut Rng::new(0),
+            ));
+
+            tick(&mut game, 1);
+            let angle = game.projectiles[projectile].direction.angle();
+
+            if kind.properties.homing.is_some() {
+                assert!((angle - (FRAC_PI_2 - turn_rate * DT)).abs() < 1e-9);
+
+                tick(&mut game, 180);
+                assert!(
+                    game.enemies
+                        .get(key)
+                        .is_none_or(|enemy| { enemy.health < enemy.properties.maximum_health })
+                );
+            } else {
+                assert_eq!(angle, FRAC_PI_2);
+            }
+        }
+    }
+
+    #[test]
+    fn grenades_damage_everything_in_their_blast_then_disappear() {
+        let mut game = Game::with_seed(13);
+        let keys = [[0.0, 4.0], [2.0, 4.0], [0.0, 7.5], [6.0, 4.0]]
+            .map(|[x, y]| stationary_enemy(&mut game, 4, Isometry2::new(vector![x, y], PI)));
+
+        let grenade = &PROJECTILE_KINDS[3];
+        assert!(grenade.properties.piercing);
+        game.projectiles.insert(Projectile::new(
+            Isometry2::new(vector![0.0, 1.5], FRAC_PI_2),
+            grenade,
+            &mut Rng::new(0),
+        ));
+
+        tick(&mut game, 60);
+        assert!(game.projectiles.is_empty());
+
+        let damage = keys.map(|key| {
+            let enemy = &game.enemies[key];
+            enemy.properties.maximum_health - enemy.health
+        });
+        let blast = grenade.properties.damage;
+        assert_eq!(damage, [blast, blast, 0, 0]);
+    }
+}
@@ -0,0 +1,140 @@
+/// A small seedable PCG generator, matching the one behind `macroquad::rand`, but with state that
+/// can be owned, copied, and inspected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+}
+
+pub trait RandomRange: Sized {
+    /// `low` is inclusive, `high` is exclusive
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self;
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    const INCREMENT: u64 = 1442695040888963407;
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.rand();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.rand();
+
+        rng
+    }
+
+    /// Restores a generator from [`Rng::to_bytes`], continuing exactly where it left off
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self {
+            state: u64::from_le_bytes(bytes),
+        }
+    }
+
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.state.to_le_bytes()
+    }
+
+    pub fn rand(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(Self::INCREMENT);
+
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    /// A value in `0.0..1.0`
+    pub fn unit(&mut self) -> f64 {
+        self.rand() as f64 / (u32::MAX as f64 + 1.0)
+    }
+
+    pub fn gen_range<T: RandomRange>(&mut self, low: T, high: T) -> T {
+        T::gen_range(self, low, high)
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.gen_range(0, items.len()))
+        }
+    }
+
+    /// Picks an item with probability proportional to its weight. Items with a weight of zero or
+    /// less are never picked, and `None` is returned if every weight is.
+    pub fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f64)]) -> Option<&'a T> {
+        let total = items
+            .iter()
+            .map(|&(_, weight)| weight.max(0.0))
+            .sum::<f64>();
+
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = self.gen_range(0.0, total);
+
+        items
+            .iter()
+            .filter(|&&(_, weight)| weight > 0.0)
+            .find(|&&(_, weight)| {
+                target -= weight;
+                target < 0.0
+            })
+            .or_else(|| items.iter().rfind(|&&(_, weight)| weight > 0.0))
+            .map(|(item, _)| item)
+    }
+}
+
+/// Combines two values into a well-scrambled seed, so that nearby inputs give unrelated seeds
+#[must_use]
+pub fn mix(a: u64, b: u64) -> u64 {
+    // SplitMix64's finalizer
+    let mut x = a ^ b.rotate_left(32) ^ 0x9e37_79b9_7f4a_7c15;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+impl RandomRange for f64 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        low + (high - low) * rng.unit()
+    }
+}
+
+impl RandomRange for f32 {
+    fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+        low + (high - low) * rng.unit() as f32
+    }
+}
+
+macro_rules! impl_random_range_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl RandomRange for $ty {
+                fn gen_range(rng: &mut Rng, low: Self, high: Self) -> Self {
+                    if high <= low {
+                        return low;
+                    }
+
+                    let range = (high - low) as u64;
+                    let value = ((rng.rand() as u64) << 32) | rng.rand() as u64;
+                    low + (value % range) as Self
+                }
+            }
+        )*
+    };
+}
+
+impl_random_range_integer!(u32, u64, usize);
@@ -0,0 +1,332 @@
+//! Named save slots. Each slot holds a small metadata header for load menus, followed by the game
+//! itself from [`Game::to_bytes`].
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{game::Game, snapshot::SnapshotError};
+
+pub const MAGIC: [u8; 4] = *b"ESSV";
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_LENGTH: usize = 4 + 4 + 8 + 8 + 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveMetadata {
+    pub score: u64,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub wave: u32,
+}
+
+/// A slot from [`SaveManager::list_slots`], with its metadata or the reason it couldn't be read
+#[derive(Debug)]
+pub struct SaveSlot {
+    pub name: String,
+    pub metadata: Result<SaveMetadata, SaveError>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Missing,
+    InvalidSlotName,
+    Corrupt,
+    UnsupportedVersion(u32),
+    /// The game couldn't be saved, or the saved game couldn't be restored
+    Snapshot(SnapshotError),
+}
+
+#[derive(Clone, Debug)]
+pub enum SaveStorage {
+    Directory(PathBuf),
+    /// Used where there is no filesystem, such as on the web
+    Memory(BTreeMap<String, Vec<u8>>),
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveManager {
+    pub storage: SaveStorage,
+}
+
+impl SaveMetadata {
+    #[must_use]
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            score: game.score.points,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            wave: game.current_wave(),
+        }
+    }
+}
+
+impl Default for SaveManager {
+    fn default() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Self {
+                storage: SaveStorage::Memory(BTreeMap::new()),
+            }
+        } else {
+            Self {
+                storage: SaveStorage::Directory(PathBuf::from("saves")),
+            }
+        }
+    }
+}
+
+impl SaveManager {
+    pub const EXTENSION: &str = "sav";
+
+    pub fn list_slots(&self) -> Result<Vec<SaveSlot>, SaveError> {
+        let names = match &self.storage {
+            SaveStorage::Directory(directory) => match fs::read_dir(directory) {
+                Ok(entries) => entries
+                    .filter_map(|entry| {
+                        let path = entry.ok()?.path();
+
+                        if path.extension()? != Self::EXTENSION {
+                            return None;
+                        }
+
+                        Some(path.file_stem()?.to_str()?.to_owned())
+                    })
+                    .collect(),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(error) => return Err(SaveError::Io(error)),
+            },
+            SaveStorage::Memory(slots) => slots.keys().cloned().collect::<Vec<_>>(),
+        };
+
+        let mut slots = names
+            .into_iter()
+            .map(|name| SaveSlot {
+                metadata: self.metadata(&name),
+                name,
+            })
+            .collect::<Vec<_>>();
+        slots.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(slots)
+    }
+
+    pub fn save(&mut self, slot: &str, game: &Game) -> Result<(), SaveError> {
+        validate_slot_name(slot)?;
+
+        let metadata = SaveMetadata::from_game(game);
+        let payload = game.to_bytes().map_err(SaveError::Snapshot)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&metadata.score.to_le_bytes());
+        bytes.extend_from_slice(&metadata.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&metadata.wave.to_le_bytes());
+        bytes.extend_from_slice(&payload);
+
+        match &mut self.storage {
+            SaveStorage::Directory(directory) => {
+                fs::create_dir_all(&*directory).map_err(SaveError::Io)?;
+                fs::write(self.path_of(slot), bytes).map_err(SaveError::Io)
+            }
+            SaveStorage::Memory(slots) => {
+                slots.insert(slot.to_owned(), bytes);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn load(&self, slot: &str) -> Result<Game, SaveError> {
+        let (_, payload) = self.read(slot)?;
+
+        Game::from_bytes(&payload).map_err(SaveError::Snapshot)
+    }
+
+    /// Reads only what a load menu shows about `slot`
+    pub fn metadata(&self, slot: &str) -> Result<SaveMetadata, SaveError> {
+        self.read(slot).map(|(metadata, _)| metadata)
+    }
+
+    /// Reads `slot`'s metadata and the saved game's bytes
+    fn read(&self, slot: &str) -> Result<(SaveMetadata, Vec<u8>), SaveError> {
+        validate_slot_name(slot)?;
+
+        let bytes = match &self.storage {
+            SaveStorage::Directory(_) => match fs::read(self.path_of(slot)) {
+                Ok(bytes) => bytes,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                    return Err(SaveError::Missing);
+                }
+                Err(error) => return Err(SaveError::Io(error)),
+            },
+            SaveStorage::Memory(slots) => slots.get(slot).ok_or(SaveError::Missing)?.clone(),
+        };
+
+        if bytes.len() < HEADER_LENGTH || bytes[0..4] != MAGIC {
+            return Err(SaveError::Corrupt);
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        let metadata = SaveMetadata {
+            score: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            timestamp: u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+            wave: u32::from_le_bytes(bytes[24..28].try_into().unwrap()),
+        };
+
+        Ok((metadata, bytes[HEADER_LENGTH..].to_vec()))
+    }
+
+    pub fn delete(&mut self, slot: &str) -> Result<(), SaveError> {
+        validate_slot_name(slot)?;
+
+        match &mut self.storage {
+            SaveStorage::Directory(_) => match fs::remove_file(self.path_of(slot)) {
+                Ok(()) => Ok(()),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Err(SaveError::Missing),
+                Err(error) => Err(SaveError::Io(error)),
+            },
+            SaveStorage::Memory(slots) => slots.remove(slot).map(|_| ()).ok_or(SaveError::Missing),
+        }
+    }
+
+    fn path_of(&self, slot: &str) -> PathBuf {
+        match &self.storage {
+            SaveStorage::Directory(directory) => {
+                directory.join(format!("{slot}.{}", Self::EXTENSION))
+            }
+            SaveStorage::Memory(_) => PathBuf::from(slot),
+        }
+    }
+}
+
+/// Slot names become file names, so they are limited to a safe set of characters
+fn validate_slot_name(slot: &str) -> Result<(), SaveError> {
+    if !slot.is_empty()
+        && slot
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(SaveError::InvalidSlotName)
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{error}"),
+            SaveError::Missing => write!(f, "save slot does not exist"),
+            SaveError::InvalidSlotName => write!(f, "invalid save slot name"),
+            SaveError::Corrupt => write!(f, "save file is corrupt"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save format version {version}")
+            }
+            SaveError::Snapshot(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    fn memory() -> SaveManager {
+        SaveManager {
+            storage: SaveStorage::Memory(BTreeMap::new()),
+        }
+    }
+
+    fn game_with_score(points: u64) -> Game {
+        let mut game = Game::with_seed(points);
+        game.score.points = points;
+        game.spawn_queue.wave = 2;
+
+        game
+    }
+
+    #[test]
+    fn slots_are_saved_listed_loaded_and_deleted() {
+        let mut saves = memory();
+
+        saves.save("first", &game_with_score(100)).unwrap();
+        saves.save("second", &game_with_score(250)).unwrap();
+
+        let slots = saves.list_slots().unwrap();
+        let listed = (slots.iter())
+            .map(|slot| {
+                let metadata = slot.metadata.as_ref().unwrap();
+                (slot.name.as_str(), metadata.score, metadata.wave)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(listed, [("first", 100, 2), ("second", 250, 2)]);
+
+        let second = saves.load("second").unwrap();
+        assert_eq!(second.score.points, 250);
+        assert_eq!(second.seed, 250);
+        assert_eq!(
+            second.state_hash().unwrap(),
+            game_with_score(250).state_hash().unwrap(),
+        );
+
+        saves.delete("first").unwrap();
+        assert!(matches!(saves.load("first"), Err(SaveError::Missing)));
+        assert_eq!(saves.list_slots().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn bad_slots_are_reported() {
+        let mut saves = memory();
+        let SaveStorage::Memory(slots) = &mut saves.storage else {
+            unreachable!();
+        };
+        slots.insert("garbage".to_owned(), b"not a save".to_vec());
+
+        let mut old = MAGIC.to_vec();
+        old.extend_from_slice(&1u32.to_le_bytes());
+        old.resize(HEADER_LENGTH, 0);
+        slots.insert("old".to_owned(), old);
+
+        assert!(matches!(saves.load("garbage"), Err(SaveError::Corrupt)));
+        assert!(matches!(
+            saves.load("old"),
+            Err(SaveError::UnsupportedVersion(1)),
+        ));
+        assert!(matches!(saves.load("nothing"), Err(SaveError::Missing)));
+        assert!(matches!(
+            saves.save("../escape", &Game::default()),
+            Err(SaveError::InvalidSlotName),
+        ));
+    }
+
+    #[test]
+    fn directory_saves_survive_a_new_manager() {
+        let directory = std::env::temp_dir().join(format!("electro_shoot_saves_{}", process::id()));
+        let storage = SaveStorage::Directory(directory.clone());
+
+        SaveManager {
+            storage: storage.clone(),
+        }
+        .save("slot_1", &game_with_score(40))
+        .unwrap();
+
+        let saves = SaveManager { storage };
+        let loaded = saves.load("slot_1");
+        fs::remove_dir_all(directory).unwrap();
+
+        assert_eq!(loaded.unwrap().score.points, 40);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::tween;
+
+/// Points earned by killing enemies, multiplied by a combo that builds with quick kills
+#[derive(Clone, Copy, Debug)]
+pub struct Score {
+    pub points: u64,
+    pub multiplier: f64,
+    pub time_since_kill: f64,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self {
+            points: 0,
+            multiplier: 1.0,
+            time_since_kill: f64::INFINITY,
+        }
+    }
+}
+
+impl Score {
+    pub const MULTIPLIER_STEP: f64 = 0.25;
+    pub const MAXIMUM_MULTIPLIER: f64 = 8.0;
+
+    /// How long the multiplier is held after a kill before it starts decaying
+    pub const COMBO_TIME: f64 = 2.0;
+    pub const MULTIPLIER_DECAY: f64 = 1.0;
+
+    /// Bonus points per wave number for clearing a wave without the turret being damaged
+    pub const PERFECT_WAVE_BONUS: u64 = 100;
+    /// Bonus points per wave number for clearing a wave within its time target
+    pub const FAST_WAVE_BONUS: u64 = 50;
+
+    pub fn tick(&mut self, dt: f64) {
+        self.time_since_kill += dt;
+
+        if self.time_since_kill > Self::COMBO_TIME {
+            self.multiplier = tween::exp_decay(self.multiplier, 1.0, Self::MULTIPLIER_DECAY, dt);
+        }
+    }
+
+    pub fn on_kill(&mut self, base_points: u32) {
+        self.points += (base_points as f64 * self.multiplier).round() as u64;
+        self.multiplier = (self.multiplier + Self::MULTIPLIER_STEP).min(Self::MAXIMUM_MULTIPLIER);
+        self.time_since_kill = 0.0;
+    }
+
+    /// Halves the multiplier and ends the current combo, so it starts decaying immediately
+    pub fn on_turret_damaged(&mut self) {
+        self.multiplier = (self.multiplier / 2.0).max(1.0);
+        self.time_since_kill = self.time_since_kill.max(Self::COMBO_TIME);
+    }
+
+    /// Awards and returns the bonus for clearing wave number `wave`
+    pub fn on_wave_cleared(&mut self, wave: u32, perfect: bool, fast: bool) -> u64 {
+        let mut bonus = 0;
+
+        if perfect {
+            bonus += Self::PERFECT_WAVE_BONUS * wave as u64;
+        }
+        if fast {
+            bonus += Self::FAST_WAVE_BONUS * wave as u64;
+        }
+
+        self.points += bonus;
+        bonus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turret_damage_halves_the_multiplier_and_ends_the_combo() {
+        let mut score = Score::default();
+        for _ in 0..8 {
+            score.on_kill(1);
+        }
+        assert_eq!(score.multiplier, 3.0);
+
+        score.on_turret_damaged();
+        assert_eq!(score.multiplier, 1.5);
+        assert!(score.time_since_kill >= Score::COMBO_TIME);
+
+        score.tick(0.1);
+        assert!(score.multiplier < 1.5);
+
+        score.on_turret_damaged();
+        score.on_turret_damaged();
+        assert_eq!(score.multiplier, 1.0);
+    }
+
+    #[test]
+    fn the_multiplier_holds_during_a_combo_then_decays() {
+        let mut score = Score::default();
+        score.on_kill(4);
+        score.on_kill(4);
+
+        assert_eq!(score.points, 9);
+        assert_eq!(score.multiplier, 1.5);
+
+        score.tick(Score::COMBO_TIME - 0.1);
+        assert_eq!(score.multiplier, 1.5);
+
+        score.tick(0.2);
+        assert!(score.multiplier < 1.5 && score.multiplier > 1.0);
+    }
+}
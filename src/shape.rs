@@ -1,8 +1,13 @@
+use std::f64::consts::TAU;
+
 use macroquad::{
     color::Color,
+    math::Vec2,
     shapes::{self, DrawRectangleParams},
 };
-use nalgebra::{Isometry2, UnitComplex, Vector2, vector};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2, point, vector};
+
+use crate::collision;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Shape {
@@ -14,6 +19,21 @@ pub enum Shape {
     Rectangle {
         half_size: Vector2<f64>,
     },
+    /// A convex polygon with its vertices in counterclockwise order, scaled about the origin by
+    /// `scale`. The vertices are borrowed so that shapes stay cheap to copy.
+    Polygon {
+        vertices: &'static [Point2<f64>],
+        scale: f64,
+    },
+}
+
+/// How two overlapping shapes overlap, from [`Shape::collision`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collision {
+    /// The direction to push the other shape to separate it, in this shape's local space
+    pub normal: UnitComplex<f64>,
+    /// How far the other shape would have to be pushed along `normal` to stop overlapping
+    pub depth: f64,
 }
 
 impl Shape {
@@ -48,6 +68,146 @@ impl Shape {
             (Shape::Rectangle { half_size: size_a }, Shape::Rectangle { half_size: size_b }) => {
                 rectangle_rectangle(*size_a, *size_b, offset)
             }
+
+            (Shape::Polygon { .. }, _) => polygon_shape(self, other, offset),
+            (_, Shape::Polygon { .. }) => polygon_shape(other, self, offset.inverse()),
+        }
+    }
+
+    /// Like [`Shape::is_colliding`], but also finds the shortest way to separate the shapes
+    #[must_use]
+    pub fn collision(&self, other: &Self, offset: Isometry2<f64>) -> Option<Collision> {
+        let manifold = collision::overlap_manifold(self, &Isometry2::identity(), other, &offset)?;
+
+        Some(Collision {
+            normal: UnitComplex::new(manifold.normal.y.atan2(manifold.normal.x)),
+            depth: manifold.depth,
+        })
+    }
+
+    /// The corners of a rectangle or polygon in counterclockwise order, in local space. Points and
+    /// circles have none.
+    #[must_use]
+    pub fn corners(&self) -> Option<Vec<Point2<f64>>> {
+        match *self {
+            Shape::Point | Shape::Circle { .. } => None,
+            // Counterclockwise from the bottom right corner
+            Shape::Rectangle { half_size } => Some(vec![
+                point![half_size.x, -half_size.y],
+                point![half_size.x, half_size.y],
+                point![-half_size.x, half_size.y],
+                point![-half_size.x, -half_size.y],
+            ]),
+            Shape::Polygon { vertices, scale } => {
+                Some(vertices.iter().map(|vertex| vertex * scale).collect())
+            }
+        }
+    }
+
+    /// The radius of the smallest circle around the shape's center that contains it
+    #[must_use]
+    pub fn bounding_radius(&self) -> f64 {
+        match *self {
+            Shape::Point => 0.0,
+            Shape::Circle { radius } => radius,
+            Shape::Rectangle { half_size } => half_size.norm(),
+            Shape::Polygon { vertices, scale } => {
+                vertices
+                    .iter()
+                    .map(|vertex| vertex.coords.norm())
+                    .fold(0.0, f64::max)
+                    * scale
+            }
+        }
+    }
+
+    /// `n` points spaced evenly by distance along the edge of the shape, in local space. Points
+    /// give `n` copies of the origin.
+    #[must_use]
+    pub fn sample_boundary(&self, n: usize) -> Vec<Point2<f64>> {
+        match *self {
+            Shape::Point => vec![Point2::origin(); n],
+            Shape::Circle { radius } => (0..n)
+                .map(|i| {
+                    let angle = i as f64 / n as f64 * TAU;
+                    point![angle.cos(), angle.sin()] * radius
+                })
+                .collect(),
+            Shape::Rectangle { .. } | Shape::Polygon { .. } => {
+                let edges = edges(&self.corners().unwrap())
+                    .map(|(start, end)| (start, (end - start).normalize(), (end - start).norm()))
+                    .collect::<Vec<_>>();
+                let perimeter = edges.iter().map(|&(_, _, length)| length).sum::<f64>();
+
+                (0..n)
+                    .map(|i| {
+                        let mut distance = i as f64 / n as f64 * perimeter;
+
+                        for &(start, direction, length) in &edges {
+                            if distance < length {
+                                return start + direction * distance;
+                            }
+                            distance -= length;
+                        }
+
+                        edges[0].0
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Uniformly scales the shape about its origin
+    #[must_use]
+    pub fn scaled(&self, factor: f64) -> Self {
+        match *self {
+            Shape::Point => Shape::Point,
+            Shape::Circle { radius } => Shape::Circle {
+                radius: radius * factor,
+            },
+            Shape::Rectangle { half_size } => Shape::Rectangle {
+                half_size: half_size * factor,
+            },
+            Shape::Polygon { vertices, scale } => Shape::Polygon {
+                vertices,
+                scale: scale * factor,
+            },
+        }
+    }
+
+    pub fn draw_filled(&self, position: Isometry2<f64>, color: Color) {
+        match self {
+            Shape::Point => (),
+            Shape::Circle { radius } => shapes::draw_circle(
+                position.translation.x as f32,
+                position.translation.y as f32,
+                *radius as f32,
+                color,
+            ),
+            Shape::Rectangle { half_size } => shapes::draw_rectangle_ex(
+                position.translation.x as f32,
+                position.translation.y as f32,
+                half_size.x as f32 * 2.0,
+                half_size.y as f32 * 2.0,
+                DrawRectangleParams {
+                    offset: [0.5; 2].into(),
+                    rotation: position.rotation.angle() as f32,
+                    color,
+                },
+            ),
+            Shape::Polygon { .. } => {
+                let corners = (self.corners().unwrap().iter())
+                    .map(|corner| {
+                        let corner = position * corner;
+                        Vec2::new(corner.x as f32, corner.y as f32)
+                    })
+                    .collect::<Vec<_>>();
+
+                // A fan from the first corner, which covers any convex polygon
+                for pair in corners[1..].windows(2) {
+                    shapes::draw_triangle(corners[0], pair[0], pair[1], color);
+                }
+            }
         }
     }
 
@@ -78,10 +238,86 @@ impl Shape {
                     color,
                 },
             ),
+            Shape::Polygon { .. } => {
+                for (start, end) in edges(&self.corners().unwrap()) {
+                    let start = position * start;
+                    let end = position * end;
+
+                    shapes::draw_line(
+                        start.x as f32,
+                        start.y as f32,
+                        end.x as f32,
+                        end.y as f32,
+                        thickness as f32,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Each edge of a polygon as its start and end, wrapping around from the last corner to the first
+fn edges(corners: &[Point2<f64>]) -> impl Iterator<Item = (Point2<f64>, Point2<f64>)> + '_ {
+    corners
+        .iter()
+        .zip(corners.iter().cycle().skip(1))
+        .map(|(&start, &end)| (start, end))
+}
+
+/// `polygon` must be a polygon, and `offset` is the transformation from it to `other`
+fn polygon_shape(polygon: &Shape, other: &Shape, offset: Isometry2<f64>) -> bool {
+    let corners = polygon.corners().unwrap();
+
+    match *other {
+        Shape::Point => polygon_circle(&corners, 0.0, offset.translation.vector.into()),
+        Shape::Circle { radius } => {
+            polygon_circle(&corners, radius, offset.translation.vector.into())
+        }
+        Shape::Rectangle { .. } | Shape::Polygon { .. } => {
+            let other_corners = (other.corners().unwrap().iter())
+                .map(|corner| offset * corner)
+                .collect::<Vec<_>>();
+
+            polygon_polygon(&corners, &other_corners)
         }
     }
 }
 
+fn polygon_circle(corners: &[Point2<f64>], radius: f64, center: Point2<f64>) -> bool {
+    let inside = edges(corners).all(|(start, end)| (end - start).perp(&(center - start)) >= 0.0);
+
+    inside
+        || edges(corners).any(|(start, end)| {
+            let edge = end - start;
+            let along = ((center - start).dot(&edge) / edge.magnitude_squared()).clamp(0.0, 1.0);
+
+            nalgebra::distance_squared(&(start + edge * along), &center) <= radius.powi(2)
+        })
+}
+
+/// Separating axis test between two convex polygons in the same space
+fn polygon_polygon(corners_a: &[Point2<f64>], corners_b: &[Point2<f64>]) -> bool {
+    edges(corners_a)
+        .chain(edges(corners_b))
+        .all(|(start, end)| {
+            let axis = vector![start.y - end.y, end.x - start.x];
+
+            let (min_a, max_a) = project(corners_a, &axis);
+            let (min_b, max_b) = project(corners_b, &axis);
+
+            min_a <= max_b && min_b <= max_a
+        })
+}
+
+/// The range of distances along `axis` covered by `corners`, in multiples of the axis' length
+pub(crate) fn project(corners: &[Point2<f64>], axis: &Vector2<f64>) -> (f64, f64) {
+    corners.iter().map(|corner| corner.coords.dot(axis)).fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(min, max), distance| (min.min(distance), max.max(distance)),
+    )
+}
+
 fn circle_point(radius: f64, offset: Vector2<f64>) -> bool {
     offset.magnitude_squared() < radius.powi(2)
 }
@@ -137,3 +373,202 @@ fn bounding_box_of_rectangle(half_size: Vector2<f64>, rotation: UnitComplex<f64>
 
     vector![a.x.max(b.x), a.y.max(b.y)]
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::rng::Rng;
+
+    static TRIANGLE: [Point2<f64>; 3] = [point![1.0, 0.0], point![0.0, 1.0], point![-1.0, -1.0]];
+
+    fn shapes() -> [Shape; 4] {
+        [
+            Shape::Point,
+            Shape::Circle { radius: 1.5 },
+            Shape::Rectangle {
+                half_size: vector![2.0, 0.5],
+            },
+            Shape::Polygon {
+                vertices: &TRIANGLE,
+                scale: 2.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn scaling_multiplies_every_dimension() {
+        let [point, circle, rectangle, polygon] = shapes().map(|shape| shape.scaled(3.0));
+
+        assert!(matches!(point, Shape::Point));
+        assert!(matches!(circle, Shape::Circle { radius } if radius == 4.5));
+        assert!(matches!(
+            rectangle,
+            Shape::Rectangle { half_size } if half_size == vector![6.0, 1.5]
+        ));
+        assert_eq!(
+            polygon.corners().unwrap(),
+            vec![point![6.0, 0.0], point![0.0, 6.0], point![-6.0, -6.0]],
+        );
+    }
+
+    #[test]
+    fn scaling_by_one_changes_nothing() {
+        for shape in shapes() {
+            let scaled = shape.scaled(1.0);
+
+            assert_eq!(scaled.bounding_radius(), shape.bounding_radius());
+            assert_eq!(scaled.corners(), shape.corners());
+        }
+    }
+
+    #[test]
+    fn boundary_samples_are_evenly_spaced_along_the_edge() {
+        let spacings = |points: &[Point2<f64>]| {
+            (0..points.len())
+                .map(|i| (points[(i + 1) % points.len()] - points[i]).norm())
+                .collect::<Vec<_>>()
+        };
+
+        let circle = Shape::Circle { radius: 2.0 }.sample_boundary(12);
+        assert_eq!(circle.len(), 12);
+        assert!(
+            circle
+                .iter()
+                .all(|point| (point.coords.norm() - 2.0).abs() < 1e-9)
+        );
+        let chord = 2.0 * 2.0 * (TAU / 24.0).sin();
+        assert!(
+            spacings(&circle)
+                .iter()
+                .all(|spacing| (spacing - chord).abs() < 1e-9)
+        );
+
+        // A perimeter of 12 split into 24 puts a point on every corner
+        let rectangle = Shape::Rectangle {
+            half_size: vector![2.0, 1.0],
+        }
+        .sample_boundary(24);
+        assert_eq!(rectangle.len(), 24);
+        assert!(rectangle.iter().all(|point| {
+            let edge_distance = (point.x.abs() / 2.0).max(point.y.abs());
+            (edge_distance - 1.0).abs() < 1e-9
+        }));
+        assert!(
+            spacings(&rectangle)
+                .iter()
+                .all(|spacing| (spacing - 0.5).abs() < 1e-9)
+        );
+    }
+
+    static SQUARE: [Point2<f64>; 4] = [
+        point![-1.0, -1.0],
+        point![1.0, -1.0],
+        point![1.0, 1.0],
+        point![-1.0, 1.0],
+    ];
+
+    #[test]
+    fn square_polygons_collide_like_rectangles() {
+        let polygon = Shape::Polygon {
+            vertices: &SQUARE,
+            scale: 1.5,
+        };
+        let rectangle = Shape::Rectangle {
+            half_size: vector![1.5, 1.5],
+        };
+        let mut rng = Rng::new(21);
+
+        for _ in 0..1000 {
+            let offset = Isometry2::new(
+                vector![rng.gen_range(-5.0, 5.0), rng.gen_range(-5.0, 5.0)],
+                rng.gen_range(0.0, TAU),
+            );
+
+            for other in shapes() {
+                assert_eq!(
+                    polygon.is_colliding(&other, offset),
+                    rectangle.is_colliding(&other, offset),
+                    "{other:?} at {offset:?}",
+                );
+                assert_eq!(
+                    other.is_colliding(&polygon, offset),
+                    other.is_colliding(&rectangle, offset),
+                    "{other:?} at {offset:?}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn polygons_only_collide_inside_their_slanted_edges() {
+        let triangle = Shape::Polygon {
+            vertices: &TRIANGLE,
+            scale: 1.0,
+        };
+        let at = |x, y| Isometry2::new(vector![x, y], 0.0);
+
+        assert!(triangle.is_colliding(&Shape::Point, at(0.2, 0.2)));
+        assert!(!triangle.is_colliding(&Shape::Point, at(0.6, 0.6)));
+
+        assert!(!triangle.is_colliding(&Shape::Circle { radius: 0.1 }, at(0.6, 0.6)));
+        assert!(triangle.is_colliding(&Shape::Circle { radius: 0.2 }, at(0.6, 0.6)));
+
+        let small_square = Shape::Rectangle {
+            half_size: vector![0.1, 0.1],
+        };
+        assert!(!small_square.is_colliding(&triangle, at(-0.65, -0.65)));
+        assert!(triangle.is_colliding(&small_square, at(0.5, 0.4)));
+        assert!(!triangle.is_colliding(
+            &Shape::Polygon {
+                vertices: &TRIANGLE,
+                scale: 0.5,
+            },
+            Isometry2::new(vector![1.2, 1.2], PI),
+        ));
+    }
+
+    #[test]
+    fn collisions_give_the_shortest_push_apart() {
+        let circle = Shape::Circle { radius: 1.0 };
+        let collision = circle
+            .collision(
+                &Shape::Circle { radius: 0.5 },
+                Isometry2::new(vector![0.0, 1.2], 0.0),
+            )
+            .unwrap();
+        assert!((collision.depth - 0.3).abs() < 1e-9);
+        assert!((collision.normal.angle() - PI / 2.0).abs() < 1e-9);
+
+        let rectangle = Shape::Rectangle {
+            half_size: vector![2.0, 0.5],
+        };
+        let collision = rectangle
+            .collision(&circle, Isometry2::new(vector![0.5, -1.25], 0.0))
+            .unwrap();
+        assert!((collision.depth - 0.25).abs() < 1e-9);
+        assert!((collision.normal.angle() + PI / 2.0).abs() < 1e-9);
+
+        let mut rng = Rng::new(22);
+        for _ in 0..500 {
+            let offset = Isometry2::new(
+                vector![rng.gen_range(-4.0, 4.0), rng.gen_range(-4.0, 4.0)],
+                rng.gen_range(0.0, TAU),
+            );
+
+            for (a, b) in shapes().into_iter().flat_map(|a| shapes().map(|b| (a, b))) {
+                let collision = a.collision(&b, offset);
+                assert_eq!(collision.is_some(), a.is_colliding(&b, offset));
+
+                if let Some(collision) = collision {
+                    assert!(collision.depth >= 0.0);
+
+                    let push = collision.normal * vector![collision.depth + 1e-6, 0.0];
+                    let pushed = Isometry2::new(push, 0.0) * offset;
+                    assert!(!a.is_colliding(&b, pushed), "{a:?} {b:?} {offset:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1497 @@
+//! Whole games saved as bytes and restored, for save files and rewinding. Only the simulation is
+//! saved. Particles, ghosts, damage indicators, and display and input settings start over at
+//! their defaults, and enemy behaviors are recreated from their kind.
+//!
+//! Built-in enemy kinds are saved by index. Others, like kinds loaded from content files, are
+//! saved in full and leaked when restored, once per distinct kind, like content is when loaded.
+//!
+//! Entity keys aren't kept either, so entities are restored in spawn order. A game restored with
+//! `stable_order` set plays out exactly like the original would have. Without it, collisions can
+//! be resolved in a different order.
+//!
+//! The bytes are split into a gameplay section and a cosmetic section, so that
+//! [`Game::state_hash`] can ignore everything that only changes how the game looks.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{LazyLock, Mutex},
+};
+
+use macroquad::color::Color;
+use nalgebra::{Complex, Isometry2, Point2, Translation2, UnitComplex, Vector2, point, vector};
+use slotmap::Key;
+
+use crate::{
+    arena::WrapArena,
+    enemy::{
+        ChargeTimer, DeathEffect, ENEMY_KINDS, Enemy, EnemyKind, EnemyProperties, PendingExplosion,
+    },
+    game::{EnemyKey, Game, GameState},
+    projectile::{
+        Faction, FirePattern, PROJECTILE_KINDS, Projectile, ProjectileKind, ProjectileProperties,
+        RicochetProperties, SourceId,
+    },
+    rng::Rng,
+    score::Score,
+    shape::Shape,
+    spawn::{AutoSpawner, Portal, SpawnQueue, SpawnTable, SpawnTelegraph},
+    spawner::{Spawner, Wave},
+    stats::RunStats,
+    tether::{Tether, TetherProperties, TetherPull},
+    trail::{TrailProperties, TrailZone},
+    turret::{Parry, PlayerInput, Turret},
+    utils::TextureId,
+};
+
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Written in place of an enemy that has since been removed
+const REMOVED_ENEMY: u32 = u32::MAX;
+
+/// Enemy kinds and polygon vertices restored so far, by the bytes they were restored from, so
+/// that restoring the same game repeatedly doesn't leak more each time
+static RESTORED_KINDS: Restored<EnemyKind> = LazyLock::new(Default::default);
+static RESTORED_VERTICES: Restored<[Point2<f64>]> = LazyLock::new(Default::default);
+
+type Restored<T> = LazyLock<Mutex<HashMap<Vec<u8>, &'static T>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    Truncated,
+    UnsupportedVersion(u32),
+    /// An enemy has a behavior that doesn't come from one of [`ENEMY_KINDS`], so it couldn't be
+    /// recreated
+    UnknownEnemyKind,
+    /// A turret's projectile kind isn't named after one of [`PROJECTILE_KINDS`]
+    UnknownProjectileKind,
+    Invalid,
+}
+
+/// Saves `game`, see [`Game::to_bytes`]
+pub fn encode(game: &Game) -> Result<Vec<u8>, SnapshotError> {
+    let (gameplay, cosmetic) = write_game(game)?;
+
+    let mut bytes = Writer::default();
+    bytes.u32(FORMAT_VERSION);
+    bytes.u64(gameplay.bytes.len() as u64);
+    bytes.bytes.extend(gameplay.bytes);
+    bytes.bytes.extend(cosmetic.bytes);
+
+    Ok(bytes.bytes)
+}
+
+/// Restores a game from [`encode`], see [`Game::from_bytes`]
+pub fn decode(bytes: &[u8]) -> Result<Game, SnapshotError> {
+    let mut header = Reader { bytes, offset: 0 };
+
+    let version = header.u32()?;
+    if version != FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+
+    let gameplay_length = usize::try_from(header.u64()?).map_err(|_| SnapshotError::Truncated)?;
+    let gameplay_start = header.offset;
+    let cosmetic_start = (gameplay_start.checked_add(gameplay_length))
+        .filter(|&end| end <= bytes.len())
+        .ok_or(SnapshotError::Truncated)?;
+
+    let mut gameplay = Reader {
+        bytes: &bytes[..cosmetic_start],
+        offset: gameplay_start,
+    };
+    let mut cosmetic = Reader {
+        bytes,
+        offset: cosmetic_start,
+    };
+
+    let game = read_game(&mut gameplay, &mut cosmetic)?;
+
+    if gameplay.is_empty() && cosmetic.is_empty() {
+        Ok(game)
+    } else {
+        Err(SnapshotError::Invalid)
+    }
+}
+
+/// A hash of the gameplay section of `game`'s bytes, see [`Game::state_hash`]
+pub fn gameplay_hash(game: &Game) -> Result<u64, SnapshotError> {
+    let (gameplay, _) = write_game(game)?;
+
+    // FNV-1a
+    Ok(gameplay
+        .bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        }))
+}
+
+/// What an enemy is, as opposed to where it is or how it's doing, for [`crate::delta`]
+pub(crate) fn encode_enemy_kind(enemy: &Enemy) -> Result<Vec<u8>, SnapshotError> {
+    let mut bytes = Writer::default();
+    write_enemy_kind(&mut bytes, enemy)?;
+
+    Ok(bytes.bytes)
+}
+
+/// A fresh enemy at the origin, from [`encode_enemy_kind`]
+pub(crate) fn decode_enemy_kind(bytes: &[u8]) -> Result<Enemy, SnapshotError> {
+    read_all(bytes, read_enemy_kind)
+}
+
+/// Like [`encode_enemy_kind`], for projectiles
+pub(crate) fn encode_projectile_kind(projectile: &Projectile) -> Vec<u8> {
+    let mut bytes = Writer::default();
+    write_projectile_properties(&mut bytes, &projectile.properties);
+    bytes.u8(projectile.faction as u8);
+    bytes.u32(projectile.source.0);
+
+    bytes.bytes
+}
+
+pub(crate) fn decode_projectile_kind(bytes: &[u8]) -> Result<Projectile, SnapshotError> {
+    read_all(bytes, |bytes| {
+        let kind = ProjectileKind {
+            properties: read_projectile_properties(bytes)?,
+            ..PROJECTILE_KINDS[0].clone()
+        };
+        let mut projectile = Projectile::new(Isometry2::identity(), &kind, &mut Rng::default());
+        projectile.faction = bytes.faction()?;
+        projectile.source = SourceId(bytes.u32()?);
+
+        Ok(projectile)
+    })
+}
+
+/// Like [`encode_enemy_kind`], for turrets
+pub(crate) fn encode_turret_kind(turret: &Turret) -> Vec<u8> {
+    let mut bytes = Writer::default();
+    bytes.shape(turret.shape);
+    write_projectile_kind(&mut bytes, &turret.projectile_kind);
+    bytes.f64(turret.aim_decay);
+    bytes.u32(turret.source.0);
+    bytes.option(turret.recharge_step, Writer::f64);
+
+    bytes.bytes
+}
+
+pub(crate) fn decode_turret_kind(bytes: &[u8]) -> Result<Turret, SnapshotError> {
+    read_all(bytes, |bytes| {
+        Ok(Turret {
+            shape: bytes.shape()?,
+            projectile_kind: read_projectile_kind(bytes)?,
+            aim_decay: bytes.f64()?,
+            source: SourceId(bytes.u32()?),
+            recharge_step: bytes.option(Reader::f64)?,
+            ..Default::default()
+        })
+    })
+}
+
+/// Reads `bytes` with `read`, which has to use all of them
+fn read_all<T>(
+    bytes: &[u8],
+    read: impl FnOnce(&mut Reader) -> Result<T, SnapshotError>,
+) -> Result<T, SnapshotError> {
+    let mut reader = Reader { bytes, offset: 0 };
+    let value = read(&mut reader)?;
+
+    if reader.is_empty() {
+        Ok(value)
+    } else {
+        Err(SnapshotError::Invalid)
+    }
+}
+
+fn write_game(game: &Game) -> Result<(Writer, Writer), SnapshotError> {
+    let mut gameplay = Writer::default();
+    let mut cosmetic = Writer::default();
+
+    gameplay.u32(game.health);
+    gameplay.bool(game.state == GameState::GameOver);
+    gameplay.isometry(game.origin);
+    gameplay.u64(game.tick_count);
+    gameplay.u64(game.seed);
+    gameplay.bytes.extend(game.rng.to_bytes());
+    cosmetic.bytes.extend(game.cosmetic_rng.to_bytes());
+
+    gameplay.bool(game.friendly_fire);
+    gameplay.bool(game.invincible);
+    gameplay.bool(game.stable_order);
+    gameplay.bool(game.double_buffered_enemies);
+    gameplay.u64(game.separation_iterations as u64);
+    gameplay.bool(game.last_wave_perfect);
+    gameplay.option(game.arena, |gameplay, arena| {
+        gameplay.vector(arena.size);
+        gameplay.bool(arena.wrap_projectiles);
+        gameplay.u32(arena.maximum_projectile_wraps);
+    });
+
+    write_score(&mut gameplay, &game.score);
+    write_stats(&mut gameplay, &game.stats);
+
+    // The primary turret goes first, so that it can be found again
+    let others = (game.turrets.iter())
+        .filter(|&(key, _)| key != game.primary_turret_key)
+        .map(|(_, turret)| turret);
+
+    gameplay.u32(game.turrets.len() as u32);
+    for turret in std::iter::once(game.primary_turret()).chain(others) {
+        write_turret(&mut gameplay, turret)?;
+    }
+    gameplay.u32(game.next_source_id);
+    gameplay.u64(game.spawn_order.next_index);
+
+    let mut enemies = game.enemies.iter().collect::<Vec<_>>();
+    enemies.sort_unstable_by_key(|(_, enemy)| enemy.spawn_index);
+
+    let ordinals = (enemies.iter().enumerate())
+        .map(|(ordinal, &(key, _))| (key, ordinal as u32))
+        .collect::<HashMap<_, _>>();
+
+    gameplay.u32(enemies.len() as u32);
+    for (_, enemy) in enemies {
+        write_enemy(&mut gameplay, enemy)?;
+        write_enemy_cosmetics(&mut cosmetic, enemy);
+    }
+
+    // Queued explosions only make particles
+    cosmetic.u32(game.explosion_queue.len() as u32);
+    for enemy in &game.explosion_queue {
+        write_enemy(&mut cosmetic, enemy)?;
+        write_enemy_cosmetics(&mut cosmetic, enemy);
+    }
+
+    let mut projectiles = game.projectiles.values().collect::<Vec<_>>();
+    projectiles.sort_unstable_by_key(|projectile| projectile.spawn_index);
+
+    gameplay.u32(projectiles.len() as u32);
+    for projectile in projectiles {
+        write_projectile(&mut gameplay, &mut cosmetic, projectile, &ordinals);
+    }
+
+    gameplay.u32(game.trail_zones.len() as u32);
+    for trail_zone in &game.trail_zones {
+        gameplay.point(trail_zone.position);
+        gameplay.shape(trail_zone.shape);
+        gameplay.f64(trail_zone.damage_per_second);
+        gameplay.f64(trail_zone.time_left);
+        gameplay.f64(trail_zone.lifetime);
+        gameplay.option(trail_zone.source, |gameplay, source| gameplay.u32(source.0));
+    }
+
+    write_spawn_queue(&mut gameplay, &mut cosmetic, &game.spawn_queue, &ordinals)?;
+
+    gameplay.option(game.auto_spawner, |gameplay, auto_spawner| {
+        gameplay.f64(auto_spawner.elapsed);
+        gameplay.f64(auto_spawner.time_until_spawn);
+    });
+
+    gameplay.bool(game.spawner.is_some());
+    if let Some(spawner) = &game.spawner {
+        gameplay.u32(spawner.waves.len() as u32);
+        for wave in &spawner.waves {
+            gameplay.u32(wave.enemies.len() as u32);
+            for &(kind_index, count) in &wave.enemies {
+                gameplay.u64(kind_index as u64);
+                gameplay.u32(count);
+            }
+            gameplay.f64(wave.spawn_interval);
+        }
+
+        gameplay.u32(spawner.wave);
+        gameplay.u32(spawner.pending.len() as u32);
+        for &kind in &spawner.pending {
+            gameplay.enemy_kind(kind)?;
+        }
+
+        let spawned = (spawner.spawned.iter())
+            .filter_map(|key| ordinals.get(key))
+            .collect::<Vec<_>>();
+        gameplay.u32(spawned.len() as u32);
+        for &ordinal in spawned {
+            gameplay.u32(ordinal);
+        }
+
+        gameplay.f64(spawner.spawn_interval);
+        gameplay.f64(spawner.time_until_spawn);
+        gameplay.f64(spawner.wave_break);
+        gameplay.f64(spawner.time_until_wave);
+    }
+
+    Ok((gameplay, cosmetic))
+}
+
+fn read_game(gameplay: &mut Reader, cosmetic: &mut Reader) -> Result<Game, SnapshotError> {
+    let mut game = Game::default();
+    game.turrets.clear();
+
+    game.health = gameplay.u32()?;
+    game.state = if gameplay.bool()? {
+        GameState::GameOver
+    } else {
+        GameState::Playing
+    };
+    game.origin = gameplay.isometry()?;
+    game.tick_count = gameplay.u64()?;
+    game.seed = gameplay.u64()?;
+    game.rng = Rng::from_bytes(gameplay.take()?);
+    game.cosmetic_rng = Rng::from_bytes(cosmetic.take()?);
+
+    game.friendly_fire = gameplay.bool()?;
+    game.invincible = gameplay.bool()?;
+    game.stable_order = gameplay.bool()?;
+    game.double_buffered_enemies = gameplay.bool()?;
+    game.separation_iterations = gameplay.usize()?;
+    game.last_wave_perfect = gameplay.bool()?;
+    game.arena = gameplay.option(|gameplay| {
+        Ok(WrapArena {
+            size: gameplay.vector()?,
+            wrap_projectiles: gameplay.bool()?,
+            maximum_projectile_wraps: gameplay.u32()?,
+        })
+    })?;
+
+    game.score = read_score(gameplay)?;
+    game.stats = read_stats(gameplay)?;
+
+    for _ in 0..gameplay.u32()? {
+        let key = game.turrets.insert(read_turret(gameplay)?);
+
+        if game.turrets.len() == 1 {
+            game.primary_turret_key = key;
+        }
+    }
+    if game.turrets.is_empty() {
+        return Err(SnapshotError::Invalid);
+    }
+    game.next_source_id = gameplay.u32()?;
+    game.spawn_order.next_index = gameplay.u64()?;
+
+    let mut enemy_keys = Vec::new();
+    for _ in 0..gameplay.u32()? {
+        let mut enemy = read_enemy(gameplay)?;
+        read_enemy_cosmetics(cosmetic, &mut enemy)?;
+
+        enemy_keys.push(game.enemies.insert(enemy));
+    }
+
+    for _ in 0..cosmetic.u32()? {
+        let mut enemy = read_enemy(cosmetic)?;
+        read_enemy_cosmetics(cosmetic, &mut enemy)?;
+
+        game.explosion_queue.push(enemy);
+    }
+
+    for _ in 0..gameplay.u32()? {
+        let projectile = read_projectile(gameplay, cosmetic, &enemy_keys)?;
+        game.projectiles.insert(projectile);
+    }
+
+    for _ in 0..gameplay.u32()? {
+        game.trail_zones.push(TrailZone {
+            position: gameplay.point()?,
+            shape: gameplay.shape()?,
+            damage_per_second: gameplay.f64()?,
+            time_left: gameplay.f64()?,
+            lifetime: gameplay.f64()?,
+            source: gameplay.option(|gameplay| Ok(SourceId(gameplay.u32()?)))?,
+        });
+    }
+
+    game.spawn_queue = read_spawn_queue(gameplay, cosmetic, &enemy_keys)?;
+
+    game.auto_spawner = gameplay.option(|gameplay| {
+        Ok(AutoSpawner {
+            elapsed: gameplay.f64()?,
+            time_until_spawn: gameplay.f64()?,
+        })
+    })?;
+
+    game.spawner = gameplay.option(|gameplay| {
+        let mut waves = Vec::new();
+        for _ in 0..gameplay.u32()? {
+            let mut enemies = Vec::new();
+            for _ in 0..gameplay.u32()? {
+                enemies.push((gameplay.usize()?, gameplay.u32()?));
+            }
+
+            waves.push(Wave {
+                enemies,
+                spawn_interval: gameplay.f64()?,
+            });
+        }
+
+        let mut spawner = Spawner::new(waves);
+        spawner.wave = gameplay.u32()?;
+        for _ in 0..gameplay.u32()? {
+            spawner.pending.push_back(gameplay.enemy_kind()?);
+        }
+        for _ in 0..gameplay.u32()? {
+            let key = (enemy_keys.get(gameplay.u32()? as usize)).ok_or(SnapshotError::Invalid)?;
+            spawner.spawned.push(*key);
+        }
+        spawner.spawn_interval = gameplay.f64()?;
+        spawner.time_until_spawn = gameplay.f64()?;
+        spawner.wave_break = gameplay.f64()?;
+        spawner.time_until_wave = gameplay.f64()?;
+
+        Ok(spawner)
+    })?;
+
+    Ok(game)
+}
+
+fn write_score(bytes: &mut Writer, score: &Score) {
+    bytes.u64(score.points);
+    bytes.f64(score.multiplier);
+    bytes.f64(score.time_since_kill);
+}
+
+fn read_score(bytes: &mut Reader) -> Result<Score, SnapshotError> {
+    Ok(Score {
+        points: bytes.u64()?,
+        multiplier: bytes.f64()?,
+        time_since_kill: bytes.f64()?,
+    })
+}
+
+fn write_stats(bytes: &mut Writer, stats: &RunStats) {
+    bytes.f64(stats.duration);
+    bytes.u32(stats.shots_fired);
+    bytes.u32(stats.shots_hit);
+
+    bytes.u32(stats.kills_by_kind.len() as u32);
+    for (name, &kills) in &stats.kills_by_kind {
+        bytes.str(name);
+        bytes.u32(kills);
+    }
+
+    bytes.u32(stats.combo);
+    bytes.u32(stats.max_combo);
+}
+
+fn read_stats(bytes: &mut Reader) -> Result<RunStats, SnapshotError> {
+    let mut stats = RunStats {
+        duration: bytes.f64()?,
+        shots_fired: bytes.u32()?,
+        shots_hit: bytes.u32()?,
+        ..Default::default()
+    };
+
+    for _ in 0..bytes.u32()? {
+        let name = bytes.string()?;
+        stats.kills_by_kind.insert(name.into(), bytes.u32()?);
+    }
+
+    stats.combo = bytes.u32()?;
+    stats.max_combo = bytes.u32()?;
+
+    Ok(stats)
+}
+
+fn write_turret(bytes: &mut Writer, turret: &Turret) -> Result<(), SnapshotError> {
+    bytes.shape(turret.shape);
+    bytes.isometry(turret.position);
+    bytes.point(turret.target);
+    bytes.f64(turret.time_since_shoot);
+    bytes.f64(turret.time_since_recharged);
+    write_projectile_kind(bytes, &turret.projectile_kind);
+    bytes.f64(turret.aim_decay);
+    bytes.u32(turret.source.0);
+    bytes.option(turret.recharge_step, Writer::f64);
+
+    let input = &turret.input;
+    bytes.bool(input.shoot);
+    bytes.f64(input.time_since_press);
+    bytes.bool(input.held);
+    bytes.bool(input.just_pressed);
+    bytes.bool(input.just_released);
+    bytes.bool(input.wait_for_release);
+
+    bytes.option(turret.parry, |bytes, parry| {
+        bytes.f64(parry.window);
+        bytes.f64(parry.cooldown);
+        bytes.f64(parry.time_since_start);
+    });
+
+    Ok(())
+}
+
+/// The turret's recharge easing can't be saved, so it is left at its default
+fn read_turret(bytes: &mut Reader) -> Result<Turret, SnapshotError> {
+    Ok(Turret {
+        shape: bytes.shape()?,
+        position: bytes.isometry()?,
+        target: bytes.point()?,
+        time_since_shoot: bytes.f64()?,
+        time_since_recharged: bytes.f64()?,
+        projectile_kind: read_projectile_kind(bytes)?,
+        aim_decay: bytes.f64()?,
+        source: SourceId(bytes.u32()?),
+        recharge_step: bytes.option(Reader::f64)?,
+        input: PlayerInput {
+            shoot: bytes.bool()?,
+            time_since_press: bytes.f64()?,
+            held: bytes.bool()?,
+            just_pressed: bytes.bool()?,
+            just_released: bytes.bool()?,
+            wait_for_release: bytes.bool()?,
+        },
+        parry: bytes.option(|bytes| {
+            Ok(Parry {
+                window: bytes.f64()?,
+                cooldown: bytes.f64()?,
+                time_since_start: bytes.f64()?,
+            })
+        })?,
+        ..Default::default()
+    })
+}
+
+fn write_projectile_kind(bytes: &mut Writer, kind: &ProjectileKind) {
+    bytes.str(kind.name);
+    write_projectile_properties(bytes, &kind.properties);
+    bytes.f64(kind.shoot_cooldown);
+    bytes.u64(kind.fire_pattern.pellets as u64);
+    bytes.f64(kind.fire_pattern.spread);
+    bytes.bool(kind.auto_fire);
+    bytes.option(kind.impact_flash, Writer::color);
+}
+
+fn read_projectile_kind(bytes: &mut Reader) -> Result<ProjectileKind, SnapshotError> {
+    let name = bytes.string()?;
+    let name = (PROJECTILE_KINDS.iter())
+        .find(|kind| kind.name == name)
+        .ok_or(SnapshotError::UnknownProjectileKind)?
+        .name;
+
+    Ok(ProjectileKind {
+        name,
+        properties: read_projectile_properties(bytes)?,
+        shoot_cooldown: bytes.f64()?,
+        fire_pattern: FirePattern {
+            pellets: bytes.usize()?,
+            spread: bytes.f64()?,
+        },
+        auto_fire: bytes.bool()?,
+        impact_flash: bytes.option(Reader::color)?,
+    })
+}
+
+fn write_projectile_properties(bytes: &mut Writer, properties: &ProjectileProperties) {
+    bytes.vector(properties.size);
+    bytes.u32(properties.damage);
+    bytes.bool(properties.piercing);
+    bytes.f64(properties.speed);
+    bytes.f64(properties.particle_distance);
+    bytes.u64(properties.hit_particle_count as u64);
+    bytes.f64(properties.hit_particle_distance);
+    bytes.f64(properties.hit_particle_jitter);
+    bytes.option(properties.trail, |bytes, trail| {
+        bytes.f64(trail.interval);
+        bytes.f64(trail.radius);
+        bytes.f64(trail.damage_per_second);
+        bytes.f64(trail.lifetime);
+    });
+    bytes.vector(properties.gravity);
+    bytes.option(properties.tether, |bytes, tether| {
+        bytes.f64(tether.stiffness);
+        bytes.f64(tether.damping);
+        bytes.f64(tether.duration);
+        bytes.f64(tether.break_length);
+    });
+    bytes.option(properties.ricochet, |bytes, ricochet| {
+        bytes.u32(ricochet.bounces);
+        bytes.f64(ricochet.range);
+    });
+    bytes.option(properties.homing, Writer::f64);
+    bytes.option(properties.explosion_radius, Writer::f64);
+    bytes.f64(properties.pierce_falloff);
+    bytes.f64(properties.overcharge_bonus);
+    bytes.f64(properties.overcharge_distance);
+    bytes.f64(properties.hit_cooldown);
+    bytes.u64(properties.trail_particles_per_segment as u64);
+    bytes.f64(properties.trail_particle_spread);
+}
+
+fn read_projectile_properties(bytes: &mut Reader) -> Result<ProjectileProperties, SnapshotError> {
+    Ok(ProjectileProperties {
+        size: bytes.vector()?,
+        damage: bytes.u32()?,
+        piercing: bytes.bool()?,
+        speed: bytes.f64()?,
+        particle_distance: bytes.f64()?,
+        hit_particle_count: bytes.usize()?,
+        hit_particle_distance: bytes.f64()?,
+        hit_particle_jitter: bytes.f64()?,
+        trail: bytes.option(|bytes| {
+            Ok(TrailProperties {
+                interval: bytes.f64()?,
+                radius: bytes.f64()?,
+                damage_per_second: bytes.f64()?,
+                lifetime: bytes.f64()?,
+            })
+        })?,
+        gravity: bytes.vector()?,
+        tether: bytes.option(|bytes| {
+            Ok(TetherProperties {
+                stiffness: bytes.f64()?,
+                damping: bytes.f64()?,
+                duration: bytes.f64()?,
+                break_length: bytes.f64()?,
+            })
+        })?,
+        ricochet: bytes.option(|bytes| {
+            Ok(RicochetProperties {
+                bounces: bytes.u32()?,
+                range: bytes.f64()?,
+            })
+        })?,
+        homing: bytes.option(Reader::f64)?,
+        explosion_radius: bytes.option(Reader::f64)?,
+        pierce_falloff: bytes.f64()?,
+        overcharge_bonus: bytes.f64()?,
+        overcharge_distance: bytes.f64()?,
+        hit_cooldown: bytes.f64()?,
+        trail_particles_per_segment: bytes.usize()?,
+        trail_particle_spread: bytes.f64()?,
+    })
+}
+
+fn write_enemy(gameplay: &mut Writer, enemy: &Enemy) -> Result<(), SnapshotError> {
+    write_enemy_kind(gameplay, enemy)?;
+    gameplay.u64(enemy.spawn_index);
+    gameplay.isometry(enemy.position);
+    gameplay.vector(enemy.linear_velocity);
+    gameplay.f64(enemy.angular_velocity);
+    gameplay.rotation(enemy.direction);
+    gameplay.f64(enemy.current_speed);
+    gameplay.u32(enemy.health);
+    gameplay.f64(enemy.time_since_spawn);
+    gameplay.f64(enemy.time_since_hit);
+    gameplay.option(enemy.last_hit_source, |gameplay, source| {
+        gameplay.u32(source.0);
+    });
+    gameplay.u64(enemy.last_hit_tick);
+    gameplay.f64(enemy.partial_damage);
+    gameplay.f64(enemy.partial_regen);
+    gameplay.option(enemy.charge_timer, |gameplay, charge_timer| {
+        gameplay.f64(charge_timer.elapsed);
+        gameplay.f64(charge_timer.duration);
+    });
+    gameplay.option(enemy.tether, |gameplay, tether| {
+        gameplay.point(tether.anchor);
+        gameplay.f64(tether.stiffness);
+        gameplay.f64(tether.damping);
+    });
+    gameplay.option(enemy.anchor, Writer::point);
+    gameplay.f64(enemy.leash_range);
+
+    Ok(())
+}
+
+/// What the enemy is, as opposed to where it is or how it's doing
+fn write_enemy_kind(bytes: &mut Writer, enemy: &Enemy) -> Result<(), SnapshotError> {
+    if enemy.kind_index.is_none() && enemy.properties.behavior.is_some() {
+        return Err(SnapshotError::UnknownEnemyKind);
+    }
+
+    bytes.option(enemy.kind_index, |bytes, index| bytes.u32(index as u32));
+    bytes.str(&enemy.name);
+    bytes.enemy_properties(&enemy.properties);
+
+    Ok(())
+}
+
+/// A fresh enemy at the origin, from [`write_enemy_kind`]
+fn read_enemy_kind(bytes: &mut Reader) -> Result<Enemy, SnapshotError> {
+    let kind_index = bytes.option(|bytes| Ok(bytes.u32()? as usize))?;
+    let built_in = match kind_index {
+        Some(index) => Some(
+            ENEMY_KINDS
+                .get(index)
+                .ok_or(SnapshotError::UnknownEnemyKind)?,
+        ),
+        None => None,
+    };
+
+    let name = bytes.string()?;
+    let mut properties = bytes.enemy_properties()?;
+    properties.behavior = built_in.and_then(|kind| kind.properties.behavior);
+
+    let kind = EnemyKind {
+        name: name.into(),
+        properties,
+    };
+    let mut enemy = Enemy::new(Isometry2::identity(), &kind);
+    enemy.kind_index = kind_index;
+
+    Ok(enemy)
+}
+
+fn write_enemy_cosmetics(cosmetic: &mut Writer, enemy: &Enemy) {
+    cosmetic.f64(enemy.brightness);
+    cosmetic.f64(enemy.brightness_update_time);
+    cosmetic.option(enemy.pending_explosion, |cosmetic, explosion| {
+        cosmetic.point(explosion.hit_position);
+        cosmetic.vector(explosion.hit_velocity);
+        cosmetic.u64(explosion.seed);
+    });
+}
+
+/// The enemy is recreated from its kind, so its behavior starts over
+fn read_enemy(gameplay: &mut Reader) -> Result<Enemy, SnapshotError> {
+    let mut enemy = read_enemy_kind(gameplay)?;
+    enemy.spawn_index = gameplay.u64()?;
+    enemy.position = gameplay.isometry()?;
+
+    enemy.linear_velocity = gameplay.vector()?;
+    enemy.angular_velocity = gameplay.f64()?;
+    enemy.direction = gameplay.rotation()?;
+    enemy.current_speed = gameplay.f64()?;
+    enemy.health = gameplay.u32()?;
+    enemy.time_since_spawn = gameplay.f64()?;
+    enemy.time_since_hit = gameplay.f64()?;
+    enemy.last_hit_source = gameplay.option(|gameplay| Ok(SourceId(gameplay.u32()?)))?;
+    enemy.last_hit_tick = gameplay.u64()?;
+    enemy.partial_damage = gameplay.f64()?;
+    enemy.partial_regen = gameplay.f64()?;
+    enemy.charge_timer = gameplay.option(|gameplay| {
+        Ok(ChargeTimer {
+            elapsed: gameplay.f64()?,
+            duration: gameplay.f64()?,
+        })
+    })?;
+    enemy.tether = gameplay.option(|gameplay| {
+        Ok(TetherPull {
+            anchor: gameplay.point()?,
+            stiffness: gameplay.f64()?,
+            damping: gameplay.f64()?,
+        })
+    })?;
+    enemy.anchor = gameplay.option(Reader::point)?;
+    enemy.leash_range = gameplay.f64()?;
+
+    Ok(enemy)
+}
+
+fn read_enemy_cosmetics(cosmetic: &mut Reader, enemy: &mut Enemy) -> Result<(), SnapshotError> {
+    enemy.brightness = cosmetic.f64()?;
+    enemy.brightness_update_time = cosmetic.f64()?;
+    enemy.pending_explosion = cosmetic.option(|cosmetic| {
+        Ok(PendingExplosion {
+            hit_position: cosmetic.point()?,
+            hit_velocity: cosmetic.vector()?,
+            seed: cosmetic.u64()?,
+        })
+    })?;
+
+    Ok(())
+}
+
+fn write_projectile(
+    gameplay: &mut Writer,
+    cosmetic: &mut Writer,
+    projectile: &Projectile,
+    ordinals: &HashMap<EnemyKey, u32>,
+) {
+    let enemy = |key: EnemyKey| ordinals.get(&key).copied().unwrap_or(REMOVED_ENEMY);
+
+    gameplay.u64(projectile.spawn_index);
+    gameplay.isometry(projectile.position);
+    gameplay.vector(projectile.linear_velocity);
+    gameplay.f64(projectile.angular_velocity);
+    gameplay.rotation(projectile.direction);
+    write_projectile_properties(gameplay, &projectile.properties);
+    gameplay.u8(projectile.faction as u8);
+    gameplay.u32(projectile.source.0);
+    gameplay.option(projectile.impact_flash, Writer::color);
+
+    for keys in [
+        &projectile.enemies_colliding,
+        &projectile.enemies_intersecting,
+        &projectile.enemies_hit,
+    ] {
+        gameplay.u32(keys.len() as u32);
+        for &key in keys {
+            gameplay.u32(enemy(key));
+        }
+    }
+
+    gameplay.u32(projectile.recent_hits.len() as u32);
+    for &(key, time) in &projectile.recent_hits {
+        gameplay.u32(enemy(key));
+        gameplay.f64(time);
+    }
+
+    gameplay.f64(projectile.time_since_collision);
+    gameplay.f64(projectile.time_since_exit);
+    gameplay.f64(projectile.distance_since_trail);
+    gameplay.f64(projectile.distance_travelled);
+    gameplay.vector(projectile.fall_velocity);
+    gameplay.point(projectile.origin);
+    gameplay.option(projectile.tether, |gameplay, tether| {
+        gameplay.u32(enemy(tether.enemy));
+        gameplay.point(tether.anchor);
+        gameplay.f64(tether.time_left);
+    });
+    gameplay.u32(projectile.bounces_left);
+    gameplay.option(projectile.ricochet_from, |gameplay, key| {
+        gameplay.u32(enemy(key));
+    });
+    gameplay.option(projectile.ricochet_target, |gameplay, key| {
+        gameplay.u32(enemy(key));
+    });
+    gameplay.u32(projectile.wraps);
+    gameplay.bool(projectile.exploded);
+
+    cosmetic.f64(projectile.distance_since_particle);
+    cosmetic.u32(projectile.history.len() as u32);
+    for &position in projectile.history.iter() {
+        cosmetic.isometry(position);
+    }
+}
+
+fn read_projectile(
+    gameplay: &mut Reader,
+    cosmetic: &mut Reader,
+    enemy_keys: &[EnemyKey],
+) -> Result<Projectile, SnapshotError> {
+    let enemy = |ordinal: u32| match ordinal {
+        REMOVED_ENEMY => Ok(EnemyKey::null()),
+        ordinal => (enemy_keys.get(ordinal as usize).copied()).ok_or(SnapshotError::Invalid),
+    };
+
+    let spawn_index = gameplay.u64()?;
+    let position = gameplay.isometry()?;
+    let linear_velocity = gameplay.vector()?;
+    let angular_velocity = gameplay.f64()?;
+    let direction = gameplay.rotation()?;
+
+    let kind = ProjectileKind {
+        properties: read_projectile_properties(gameplay)?,
+        ..PROJECTILE_KINDS[0].clone()
+    };
+    let mut projectile = Projectile::new(position, &kind, &mut Rng::default());
+
+    projectile.spawn_index = spawn_index;
+    projectile.linear_velocity = linear_velocity;
+    projectile.angular_velocity = angular_velocity;
+    projectile.direction = direction;
+    projectile.faction = gameplay.faction()?;
+    projectile.source = SourceId(gameplay.u32()?);
+    projectile.impact_flash = gameplay.option(Reader::color)?;
+
+    for keys in [
+        &mut projectile.enemies_colliding,
+        &mut projectile.enemies_intersecting,
+        &mut projectile.enemies_hit,
+    ] {
+        for _ in 0..gameplay.u32()? {
+            keys.push(enemy(gameplay.u32()?)?);
+        }
+    }
+
+    for _ in 0..gameplay.u32()? {
+        let key = enemy(gameplay.u32()?)?;
+        projectile.recent_hits.push((key, gameplay.f64()?));
+    }
+
+    projectile.time_since_collision = gameplay.f64()?;
+    projectile.time_since_exit = gameplay.f64()?;
+    projectile.distance_since_trail = gameplay.f64()?;
+    projectile.distance_travelled = gameplay.f64()?;
+    projectile.fall_velocity = gameplay.vector()?;
+    projectile.origin = gameplay.point()?;
+    projectile.tether = gameplay.option(|gameplay| {
+        Ok(Tether {
+            enemy: enemy(gameplay.u32()?)?,
+            anchor: gameplay.point()?,
+            time_left: gameplay.f64()?,
+        })
+    })?;
+    projectile.bounces_left = gameplay.u32()?;
+    projectile.ricochet_from = gameplay.option(|gameplay| enemy(gameplay.u32()?))?;
+    projectile.ricochet_target = gameplay.option(|gameplay| enemy(gameplay.u32()?))?;
+    projectile.wraps = gameplay.u32()?;
+    projectile.exploded = gameplay.bool()?;
+
+    projectile.distance_since_particle = cosmetic.f64()?;
+    let mut history = Vec::new();
+    for _ in 0..cosmetic.u32()? {
+        history.push(cosmetic.isometry()?);
+    }
+    for position in history.into_iter().rev() {
+        projectile.history.push(position);
+    }
+
+    Ok(projectile)
+}
+
+fn write_spawn_queue(
+    gameplay: &mut Writer,
+    cosmetic: &mut Writer,
+    queue: &SpawnQueue,
+    ordinals: &HashMap<EnemyKey, u32>,
+) -> Result<(), SnapshotError> {
+    gameplay.u32(queue.telegraphs.len() as u32);
+    for telegraph in &queue.telegraphs {
+        gameplay.isometry(telegraph.position);
+        gameplay.enemy_kind(telegraph.kind)?;
+        gameplay.f64(telegraph.time_left);
+        gameplay.option(telegraph.wave, Writer::u32);
+    }
+
+    gameplay.u32(queue.portals.len() as u32);
+    for portal in &queue.portals {
+        gameplay.isometry(portal.position);
+        gameplay.u32(portal.queue.len() as u32);
+        for &kind in &portal.queue {
+            gameplay.enemy_kind(kind)?;
+        }
+        gameplay.f64(portal.emit_interval);
+        gameplay.f64(portal.time_until_emit);
+        gameplay.option(portal.wave, Writer::u32);
+        cosmetic.f64(portal.age);
+    }
+
+    gameplay.u32(queue.recent_angles.len() as u32);
+    for &angle in &queue.recent_angles {
+        gameplay.f64(angle);
+    }
+    gameplay.f64(queue.balance_strength);
+
+    gameplay.u32(queue.wave);
+    // Enemies of the wave that have died don't count towards anything anymore
+    let wave_enemies = (queue.wave_enemies.iter())
+        .filter_map(|key| ordinals.get(key))
+        .collect::<Vec<_>>();
+    gameplay.u32(wave_enemies.len() as u32);
+    for &ordinal in wave_enemies {
+        gameplay.u32(ordinal);
+    }
+    gameplay.option(queue.next_wave_delay, Writer::f64);
+    gameplay.f64(queue.wave_time);
+    gameplay.f64(queue.wave_time_target);
+    gameplay.bool(queue.wave_damaged);
+    gameplay.bool(queue.wave_cleared);
+
+    gameplay.u32(queue.spawn_table.weights.len() as u32);
+    for &(kind, weight) in &queue.spawn_table.weights {
+        gameplay.enemy_kind(kind)?;
+        gameplay.f64(weight);
+    }
+
+    Ok(())
+}
+
+fn read_spawn_queue(
+    gameplay: &mut Reader,
+    cosmetic: &mut Reader,
+    enemy_keys: &[EnemyKey],
+) -> Result<SpawnQueue, SnapshotError> {
+    let mut queue = SpawnQueue::default();
+
+    for _ in 0..gameplay.u32()? {
+        queue.telegraphs.push(SpawnTelegraph {
+            position: gameplay.isometry()?,
+            kind: gameplay.enemy_kind()?,
+            time_left: gameplay.f64()?,
+            wave: gameplay.option(Reader::u32)?,
+        });
+    }
+
+    for _ in 0..gameplay.u32()? {
+        let position = gameplay.isometry()?;
+        let mut kinds = Vec::new();
+        for _ in 0..gameplay.u32()? {
+            kinds.push(gameplay.enemy_kind()?);
+        }
+
+        queue.portals.push(Portal {
+            emit_interval: gameplay.f64()?,
+            time_until_emit: gameplay.f64()?,
+            wave: gameplay.option(Reader::u32)?,
+            age: cosmetic.f64()?,
+            ..Portal::new(position, kinds, 0.0)
+        });
+    }
+
+    for _ in 0..gameplay.u32()? {
+        queue.recent_angles.push_back(gameplay.f64()?);
+    }
+    queue.balance_strength = gameplay.f64()?;
+
+    queue.wave = gameplay.u32()?;
+    for _ in 0..gameplay.u32()? {
+        let key = (enemy_keys.get(gameplay.u32()? as usize)).ok_or(SnapshotError::Invalid)?;
+        queue.wave_enemies.push(*key);
+    }
+    queue.next_wave_delay = gameplay.option(Reader::f64)?;
+    queue.wave_time = gameplay.f64()?;
+    queue.wave_time_target = gameplay.f64()?;
+    queue.wave_damaged = gameplay.bool()?;
+    queue.wave_cleared = gameplay.bool()?;
+
+    let mut weights = Vec::new();
+    for _ in 0..gameplay.u32()? {
+        weights.push((gameplay.enemy_kind()?, gameplay.f64()?));
+    }
+    queue.spawn_table = SpawnTable { weights };
+
+    Ok(queue)
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value.into());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn vector(&mut self, value: Vector2<f64>) {
+        self.f64(value.x);
+        self.f64(value.y);
+    }
+
+    fn point(&mut self, value: Point2<f64>) {
+        self.vector(value.coords);
+    }
+
+    /// Rotations are written as is rather than as an angle, which wouldn't survive the trip
+    fn rotation(&mut self, value: UnitComplex<f64>) {
+        self.f64(value.re);
+        self.f64(value.im);
+    }
+
+    fn isometry(&mut self, value: Isometry2<f64>) {
+        self.vector(value.translation.vector);
+        self.rotation(value.rotation);
+    }
+
+    fn color(&mut self, value: Color) {
+        for channel in [value.r, value.g, value.b, value.a] {
+            self.u32(channel.to_bits());
+        }
+    }
+
+    fn shape(&mut self, value: Shape) {
+        match value {
+            Shape::Point => self.u8(0),
+            Shape::Circle { radius } => {
+                self.u8(1);
+                self.f64(radius);
+            }
+            Shape::Rectangle { half_size } => {
+                self.u8(2);
+                self.vector(half_size);
+            }
+            Shape::Polygon { vertices, scale } => {
+                self.u8(3);
+                self.u32(vertices.len() as u32);
+                for &vertex in vertices {
+                    self.point(vertex);
+                }
+                self.f64(scale);
+            }
+        }
+    }
+
+    /// Built-in kinds are written by index, and others in full. Only built-in kinds can have a
+    /// behavior.
+    fn enemy_kind(&mut self, kind: &EnemyKind) -> Result<(), SnapshotError> {
+        if let Some(index) = kind.index() {
+            self.u8(0);
+            self.u32(index as u32);
+        } else if kind.properties.behavior.is_some() {
+            return Err(SnapshotError::UnknownEnemyKind);
+        } else {
+            self.u8(1);
+            self.str(&kind.name);
+            self.enemy_properties(&kind.properties);
+        }
+
+        Ok(())
+    }
+
+    /// Everything but the behavior, which has to come from a built-in kind
+    fn enemy_properties(&mut self, properties: &EnemyProperties) {
+        self.shape(properties.shape);
+        self.f64(properties.speed);
+        self.f64(properties.acceleration);
+        self.f64(properties.angular_velocity);
+        self.u32(properties.maximum_health);
+        self.f64(properties.hitbox_scale);
+        self.f64(properties.regen_rate);
+        self.f64(properties.regen_delay);
+        self.option(properties.enrage_after, Self::f64);
+        self.f64(properties.enrage_multiplier);
+        self.u32(properties.texture.0);
+        self.u8(properties.death_effect as u8);
+    }
+
+    fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let bytes =
+            (self.bytes.get(self.offset..self.offset + N)).ok_or(SnapshotError::Truncated)?;
+        self.offset += N;
+
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn usize(&mut self) -> Result<usize, SnapshotError> {
+        usize::try_from(self.u64()?).map_err(|_| SnapshotError::Invalid)
+    }
+
+    fn f64(&mut self) -> Result<f64, SnapshotError> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> Result<String, SnapshotError> {
+        let length = self.u32()? as usize;
+        let bytes =
+            (self.bytes.get(self.offset..self.offset + length)).ok_or(SnapshotError::Truncated)?;
+        self.offset += length;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::Invalid)
+    }
+
+    fn vector(&mut self) -> Result<Vector2<f64>, SnapshotError> {
+        Ok(vector![self.f64()?, self.f64()?])
+    }
+
+    fn point(&mut self) -> Result<Point2<f64>, SnapshotError> {
+        Ok(point![self.f64()?, self.f64()?])
+    }
+
+    fn rotation(&mut self) -> Result<UnitComplex<f64>, SnapshotError> {
+        Ok(UnitComplex::new_unchecked(Complex::new(
+            self.f64()?,
+            self.f64()?,
+        )))
+    }
+
+    fn isometry(&mut self) -> Result<Isometry2<f64>, SnapshotError> {
+        let translation = self.vector()?;
+
+        Ok(Isometry2::from_parts(
+            Translation2::from(translation),
+            self.rotation()?,
+        ))
+    }
+
+    fn color(&mut self) -> Result<Color, SnapshotError> {
+        let [r, g, b, a] = [self.u32()?, self.u32()?, self.u32()?, self.u32()?].map(f32::from_bits);
+
+        Ok(Color { r, g, b, a })
+    }
+
+    fn shape(&mut self) -> Result<Shape, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(Shape::Point),
+            1 => Ok(Shape::Circle {
+                radius: self.f64()?,
+            }),
+            2 => Ok(Shape::Rectangle {
+                half_size: self.vector()?,
+            }),
+            3 => {
+                let start = self.offset;
+                let count = self.u32()?;
+                let vertices = (0..count)
+                    .map(|_| self.point())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if vertices.len() < 3 {
+                    return Err(SnapshotError::Invalid);
+                }
+
+                let vertices = *(RESTORED_VERTICES.lock().unwrap())
+                    .entry(self.bytes[start..self.offset].to_vec())
+                    .or_insert_with(|| Box::leak(vertices.into_boxed_slice()));
+
+                Ok(Shape::Polygon {
+                    vertices,
+                    scale: self.f64()?,
+                })
+            }
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    fn faction(&mut self) -> Result<Faction, SnapshotError> {
+        match self.u8()? {
+            tag if tag == Faction::Player as u8 => Ok(Faction::Player),
+            tag if tag == Faction::Enemy as u8 => Ok(Faction::Enemy),
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    fn enemy_kind(&mut self) -> Result<&'static EnemyKind, SnapshotError> {
+        match self.u8()? {
+            0 => {
+                let index = self.u32()? as usize;
+
+                ENEMY_KINDS
+                    .get(index)
+                    .ok_or(SnapshotError::UnknownEnemyKind)
+            }
+            1 => {
+                let start = self.offset;
+                let kind = EnemyKind {
+                    name: self.string()?.into(),
+                    properties: self.enemy_properties()?,
+                };
+
+                Ok(*(RESTORED_KINDS.lock().unwrap())
+                    .entry(self.bytes[start..self.offset].to_vec())
+                    .or_insert_with(|| Box::leak(Box::new(kind))))
+            }
+            _ => Err(SnapshotError::Invalid),
+        }
+    }
+
+    fn enemy_properties(&mut self) -> Result<EnemyProperties, SnapshotError> {
+        Ok(EnemyProperties {
+            shape: self.shape()?,
+            speed: self.f64()?,
+            acceleration: self.f64()?,
+            angular_velocity: self.f64()?,
+            maximum_health: self.u32()?,
+            hitbox_scale: self.f64()?,
+            regen_rate: self.f64()?,
+            regen_delay: self.f64()?,
+            enrage_after: self.option(Self::f64)?,
+            enrage_multiplier: self.f64()?,
+            behavior: None,
+            texture: TextureId(self.u32()?),
+            death_effect: match self.u8()? {
+                0 => DeathEffect::Explode,
+                1 => DeathEffect::ShrinkFade,
+                2 => DeathEffect::None,
+                _ => return Err(SnapshotError::Invalid),
+            },
+        })
+    }
+
+    fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<Option<T>, SnapshotError> {
+        if self.bool()? {
+            read(self).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Truncated => write!(f, "snapshot ended early"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot format version {version}")
+            }
+            SnapshotError::UnknownEnemyKind => write!(f, "enemy kind can't be saved or restored"),
+            SnapshotError::UnknownProjectileKind => {
+                write!(f, "projectile kind can't be saved or restored")
+            }
+            SnapshotError::Invalid => write!(f, "snapshot is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::point;
+
+    use super::*;
+    use crate::game::InputState;
+
+    const DT: f64 = 1.0 / 120.0;
+
+    /// Ticks `game` with input that only depends on its tick count, so that two copies of a game
+    /// see the same input
+    fn play(game: &mut Game, ticks: usize) {
+        game.simulate(
+            ticks,
+            |tick| {
+                let angle = tick as f64 * DT;
+                InputState {
+                    mouse_position: point![angle.cos(), angle.sin()] * 10.0,
+                    shoot: true,
+                    parry: false,
+                    camera_bounds: Shape::Rectangle {
+                        half_size: vector![20.0, 20.0],
+                    },
+                }
+            },
+            DT,
+        );
+    }
+
+    fn busy_game() -> Game {
+        let mut game = Game {
+            auto_spawner: Some(AutoSpawner::default()),
+            stable_order: true,
+            ..Game::with_seed(5)
+        };
+        play(&mut game, 1200);
+
+        game
+    }
+
+    #[test]
+    fn restored_game_plays_out_like_the_original() {
+        let mut original = busy_game();
+        assert!(!original.enemies.is_empty());
+        assert!(!original.projectiles.is_empty());
+
+        let mut restored = Game::from_bytes(&original.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.state_hash(), original.state_hash());
+
+        for _ in 0..10 {
+            play(&mut original, 60);
+            play(&mut restored, 60);
+
+            assert_eq!(restored.state_hash(), original.state_hash());
+        }
+        assert_eq!(restored.rng, original.rng);
+    }
+
+    #[test]
+    fn restoring_keeps_every_byte() {
+        let game = busy_game();
+        let bytes = game.to_bytes().unwrap();
+
+        assert_eq!(Game::from_bytes(&bytes).unwrap().to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn damaged_snapshots_are_rejected() {
+        let bytes = busy_game().to_bytes().unwrap();
+
+        assert_eq!(
+            Game::from_bytes(&bytes[..bytes.len() / 2]).unwrap_err(),
+            SnapshotError::Truncated,
+        );
+
+        let mut future = bytes.clone();
+        future[0] += 1;
+        assert_eq!(
+            Game::from_bytes(&future).unwrap_err(),
+            SnapshotError::UnsupportedVersion(FORMAT_VERSION + 1),
+        );
+    }
+
+    #[test]
+    fn content_kinds_and_polygons_are_saved_in_full() {
+        static TRIANGLE: [Point2<f64>; 3] =
+            [point![1.0, 0.0], point![-0.5, 0.8], point![-0.5, -0.8]];
+        let kind: &'static EnemyKind = Box::leak(Box::new(EnemyKind {
+            name: String::from("Loaded Triangle").into(),
+            properties: EnemyProperties {
+                shape: Shape::Polygon {
+                    vertices: &TRIANGLE,
+                    scale: 1.5,
+                },
+                ..ENEMY_KINDS[0].properties
+            },
+        }));
+
+        let mut game = busy_game();
+        game.enemies
+            .insert(Enemy::new(Isometry2::translation(2.0, 1.0), kind));
+        game.spawner = Some(Spawner {
+            pending: [kind, kind].into(),
+            ..Spawner::new([])
+        });
+
+        let bytes = game.to_bytes().unwrap();
+        let restored = Game::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.to_bytes().unwrap(), bytes);
+
+        let pending = &restored.spawner.as_ref().unwrap().pending;
+        assert_eq!(pending[0].name, "Loaded Triangle");
+        assert!(std::ptr::eq(pending[0], pending[1]));
+        assert!(std::ptr::eq(
+            pending[0],
+            Game::from_bytes(&bytes).unwrap().spawner.unwrap().pending[0],
+        ));
+
+        let triangle = (restored.enemies.values())
+            .find(|enemy| enemy.name == "Loaded Triangle")
+            .unwrap();
+        let Shape::Polygon { vertices, scale } = triangle.properties.shape else {
+            panic!("expected a polygon");
+        };
+        assert_eq!((vertices, scale), (&TRIANGLE[..], 1.5));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use nalgebra::{Point2, point};
+use slotmap::HopSlotMap;
+
+use crate::{enemy::Enemy, game::EnemyKey, utils::BoundingBox};
+
+/// Buckets enemies into square cells by their position, so that collision checks only need to
+/// look at enemies near whatever they're checking
+#[derive(Clone, Debug)]
+pub struct SpatialGrid {
+    /// The width and height of each cell, in world units
+    pub cell_size: f64,
+    pub cells: HashMap<(i64, i64), Vec<EnemyKey>>,
+    /// The largest bounding radius of any enemy in the grid. Enemies are bucketed by their
+    /// centers, so queries are widened by this much to catch enemies reaching into them.
+    pub maximum_radius: f64,
+    /// Set once enemies may have moved, spawned or died since the last rebuild, after which the
+    /// grid can't be trusted until it's rebuilt again
+    pub stale: bool,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self {
+            cell_size: Self::DEFAULT_CELL_SIZE,
+            cells: HashMap::new(),
+            maximum_radius: 0.0,
+            stale: true,
+        }
+    }
+}
+
+impl SpatialGrid {
+    pub const DEFAULT_CELL_SIZE: f64 = 4.0;
+
+    /// Clears the grid and adds every enemy at its current position
+    pub fn rebuild(&mut self, enemies: &HopSlotMap<EnemyKey, Enemy>) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.maximum_radius = 0.0;
+        self.stale = false;
+
+        for (key, enemy) in enemies {
+            let cell = self.cell_of(enemy.position.translation.vector.into());
+
+            self.cells.entry(cell).or_default().push(key);
+            self.maximum_radius = self.maximum_radius.max(enemy.shape.bounding_radius());
+        }
+    }
+
+    #[must_use]
+    pub fn cell_of(&self, position: Point2<f64>) -> (i64, i64) {
+        (
+            (position.x / self.cell_size).floor() as i64,
+            (position.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Every enemy that might overlap `bounds`, each exactly once. Enemies that have been
+    /// removed since the last rebuild are still included.
+    pub fn query_region(&self, bounds: BoundingBox<f64>) -> impl Iterator<Item = EnemyKey> + '_ {
+        let margin = self.maximum_radius;
+        let (min_x, min_y) = self.cell_of(point![bounds.min.x - margin, bounds.min.y - margin]);
+        let (max_x, max_y) = self.cell_of(point![bounds.max.x + margin, bounds.max.y + margin]);
+
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use nalgebra::{Isometry2, vector};
+
+    use super::*;
+    use crate::{enemy::ENEMY_KINDS, rng::Rng};
+
+    #[test]
+    fn queries_find_every_nearby_enemy_once() {
+        let mut rng = Rng::new(12);
+        let mut enemies = HopSlotMap::with_key();
+        for index in 0..200 {
+            let position = vector![rng.gen_range(-30.0, 30.0), rng.gen_range(-30.0, 30.0)];
+            enemies.insert(Enemy::new(
+                Isometry2::new(position, 0.0),
+                &ENEMY_KINDS[index % ENEMY_KINDS.len()],
+            ));
+        }
+
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(&enemies);
+
+        for _ in 0..100 {
+            let center = point![rng.gen_range(-30.0, 30.0), rng.gen_range(-30.0, 30.0)];
+            let half_size = vector![rng.gen_range(0.0, 5.0), rng.gen_range(0.0, 5.0)];
+            let bounds = BoundingBox::around(center, half_size);
+
+            let found = grid.query_region(bounds).collect::<Vec<_>>();
+            let unique = found.iter().collect::<HashSet<_>>();
+            assert_eq!(unique.len(), found.len());
+
+            for (key, enemy) in &enemies {
+                let radius = enemy.shape.bounding_radius();
+                let reach = BoundingBox::around(
+                    enemy.position.translation.vector.into(),
+                    vector![radius, radius],
+                );
+
+                if reach.intersects(&bounds) {
+                    assert!(unique.contains(&key));
+                }
+            }
+        }
+
+        let far = BoundingBox::around(point![100.0, 100.0], vector![5.0, 5.0]);
+        assert_eq!(grid.query_region(far).count(), 0);
+    }
+}
@@ -0,0 +1,626 @@
+use std::{
+    collections::VecDeque,
+    f64::consts::{PI, TAU},
+};
+
+use macroquad::color::Color;
+use nalgebra::{Isometry2, Point2, UnitComplex, vector};
+use slotmap::HopSlotMap;
+
+use crate::{
+    enemy::{ENEMY_KINDS, Enemy, EnemyKind},
+    game::EnemyKey,
+    rng::Rng,
+    shape::Shape,
+    utils::SpawnOrder,
+};
+
+/// Delays enemy spawns, showing a warning where each enemy is about to appear
+#[derive(Clone, Debug)]
+pub struct SpawnQueue {
+    pub telegraphs: Vec<SpawnTelegraph>,
+    pub portals: Vec<Portal>,
+
+    /// The most recent angles from [`SpawnQueue::next_spawn_angle`], newest last
+    pub recent_angles: VecDeque<f64>,
+    /// From `0.0` for uniformly random spawn angles to `1.0` for strongly favoring directions that
+    /// haven't been spawned from recently
+    pub balance_strength: f64,
+
+    /// The number of the latest wave, or `0` before the first one
+    pub wave: u32,
+    /// The enemies spawned so far by the latest wave, including ones that have since died
+    pub wave_enemies: Vec<EnemyKey>,
+    /// Counts down to the next wave, if one has been queued
+    pub next_wave_delay: Option<f64>,
+    /// How long the latest wave has been going, stopping once it's cleared
+    pub wave_time: f64,
+    /// Clearing a wave faster than this earns a bonus
+    pub wave_time_target: f64,
+    /// Whether the turret has been damaged during the latest wave
+    pub wave_damaged: bool,
+    pub wave_cleared: bool,
+
+    pub spawn_table: SpawnTable,
+}
+
+impl Default for SpawnQueue {
+    fn default() -> Self {
+        Self {
+            telegraphs: Vec::new(),
+            portals: Vec::new(),
+            recent_angles: VecDeque::new(),
+            balance_strength: 0.5,
+            wave: 0,
+            wave_enemies: Vec::new(),
+            next_wave_delay: None,
+            wave_time: 0.0,
+            wave_time_target: 30.0,
+            wave_damaged: false,
+            wave_cleared: false,
+            spawn_table: SpawnTable::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpawnTelegraph {
+    pub position: Isometry2<f64>,
+    pub kind: &'static EnemyKind,
+    pub time_left: f64,
+    /// The wave this spawn belongs to, if any
+    pub wave: Option<u32>,
+}
+
+/// Opens at a location and lets out a group of enemies one at a time, closing once it's empty
+#[derive(Clone, Debug)]
+pub struct Portal {
+    pub position: Isometry2<f64>,
+    /// The enemies still to come out, next first
+    pub queue: VecDeque<&'static EnemyKind>,
+    pub emit_interval: f64,
+    pub time_until_emit: f64,
+    /// How long the portal has been open, for its pulsing
+    pub age: f64,
+    /// The wave the emitted enemies belong to, if any
+    pub wave: Option<u32>,
+}
+
+impl Portal {
+    pub const COLOR: Color = Color::from_hex(0xb040ff);
+    pub const BASE_RADIUS: f64 = 0.5;
+    pub const RADIUS_PER_ENEMY: f64 = 0.15;
+    pub const PULSE_RATE: f64 = 6.0;
+    pub const PULSE_AMOUNT: f64 = 0.1;
+
+    /// A portal that lets out its first enemy after [`SpawnQueue::LEAD_TIME`], and the rest every
+    /// `emit_interval` seconds after that
+    pub fn new(
+        position: Isometry2<f64>,
+        kinds: impl IntoIterator<Item = &'static EnemyKind>,
+        emit_interval: f64,
+    ) -> Self {
+        Self {
+            position,
+            queue: kinds.into_iter().collect(),
+            emit_interval,
+            time_until_emit: SpawnQueue::LEAD_TIME,
+            age: 0.0,
+            wave: None,
+        }
+    }
+
+    /// Counts down, returning the kind of each enemy due to come out
+    pub fn tick(&mut self, dt: f64) -> Vec<&'static EnemyKind> {
+        let mut emitted = Vec::new();
+
+        self.age += dt;
+        self.time_until_emit -= dt;
+
+        while self.time_until_emit <= 0.0
+            && let Some(kind) = self.queue.pop_front()
+        {
+            emitted.push(kind);
+            self.time_until_emit += self.emit_interval;
+        }
+
+        emitted
+    }
+
+    #[must_use]
+    pub fn should_delete(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Draws a pulsing ring that shrinks as the portal empties
+    pub fn draw(&self) {
+        let pulse = 1.0 + (self.age * Self::PULSE_RATE).sin() * Self::PULSE_AMOUNT;
+        let radius = (Self::BASE_RADIUS + Self::RADIUS_PER_ENEMY * self.queue.len() as f64) * pulse;
+
+        Shape::Circle { radius }.draw_outline(self.position, 0.1, Self::COLOR);
+    }
+}
+
+impl SpawnQueue {
+    pub const LEAD_TIME: f64 = 0.75;
+    /// How long after a wave is cleared the next one starts
+    pub const WAVE_BREAK: f64 = 3.0;
+    pub const TELEGRAPH_COLOR: Color = Color::from_hex(0xff4040);
+    pub const TELEGRAPH_FLASH_RATE: f64 = 8.0;
+
+    pub const RECENT_ANGLE_COUNT: usize = 8;
+    pub const MAXIMUM_EXTRA_CANDIDATES: usize = 7;
+
+    /// Spawns an enemy of `kind` at `position` after [`SpawnQueue::LEAD_TIME`]
+    pub fn schedule(&mut self, position: Isometry2<f64>, kind: &'static EnemyKind) {
+        self.telegraphs.push(SpawnTelegraph {
+            position,
+            kind,
+            time_left: Self::LEAD_TIME,
+            wave: None,
+        });
+    }
+
+    /// Starts the next wave, spawning each of `spawns` after [`SpawnQueue::LEAD_TIME`]
+    pub fn schedule_wave(
+        &mut self,
+        spawns: impl IntoIterator<Item = (Isometry2<f64>, &'static EnemyKind)>,
+    ) {
+        self.start_wave();
+
+        for (position, kind) in spawns {
+            self.schedule_in_wave(position, kind);
+        }
+    }
+
+    /// Starts the next wave without spawning anything. Enemies are added to it with
+    /// [`SpawnQueue::schedule_in_wave`].
+    pub fn start_wave(&mut self) {
+        self.wave += 1;
+        self.wave_enemies.clear();
+        self.next_wave_delay = None;
+        self.wave_time = 0.0;
+        self.wave_damaged = false;
+        self.wave_cleared = false;
+    }
+
+    /// Like [`SpawnQueue::schedule`], but the enemy counts towards the latest wave
+    pub fn schedule_in_wave(&mut self, position: Isometry2<f64>, kind: &'static EnemyKind) {
+        self.telegraphs.push(SpawnTelegraph {
+            position,
+            kind,
+            time_left: Self::LEAD_TIME,
+            wave: Some(self.wave),
+        });
+    }
+
+    /// Opens a portal that lets out `kinds` in order, one every `emit_interval` seconds
+    pub fn open_portal(
+        &mut self,
+        position: Isometry2<f64>,
+        kinds: impl IntoIterator<Item = &'static EnemyKind>,
+        emit_interval: f64,
+    ) {
+        self.portals
+            .push(Portal::new(position, kinds, emit_interval));
+    }
+
+    /// Starts counting down `delay` seconds until the next wave
+    pub fn queue_next_wave(&mut self, delay: f64) {
+        self.next_wave_delay = Some(delay);
+    }
+
+    /// How many spawns of the latest wave are still waiting to appear
+    #[must_use]
+    pub fn queued_in_wave(&self) -> usize {
+        let telegraphs = (self.telegraphs.iter())
+            .filter(|telegraph| telegraph.wave == Some(self.wave))
+            .count();
+        let portals = (self.portals.iter())
+            .filter(|portal| portal.wave == Some(self.wave))
+            .map(|portal| portal.queue.len())
+            .sum::<usize>();
+
+        telegraphs + portals
+    }
+
+    /// Picks an angle around the turret to spawn from. Several random candidates are tried
+    /// depending on the balance strength, and the one furthest from any recent spawn is used.
+    pub fn next_spawn_angle(&mut self, rng: &mut Rng) -> f64 {
+        let candidates = 1
+            + (self.balance_strength.clamp(0.0, 1.0) * Self::MAXIMUM_EXTRA_CANDIDATES as f64)
+                .round() as usize;
+
+        let angle = (0..candidates)
+            .map(|_| rng.gen_range(0.0, TAU))
+            .map(|angle| {
+                let pressure = self
+                    .recent_angles
+                    .iter()
+                    .map(|&recent| angle_between(angle, recent))
+                    .fold(PI, f64::min);
+
+                (angle, pressure)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(angle, _)| angle)
+            .unwrap();
+
+        if self.recent_angles.len() >= Self::RECENT_ANGLE_COUNT {
+            self.recent_angles.pop_front();
+        }
+        self.recent_angles.push_back(angle);
+
+        angle
+    }
+
+    pub fn tick(
+        &mut self,
+        enemies: &mut HopSlotMap<EnemyKey, Enemy>,
+        spawn_order: &mut SpawnOrder,
+        dt: f64,
+    ) {
+        if let Some(delay) = &mut self.next_wave_delay {
+            *delay = (*delay - dt).max(0.0);
+        }
+
+        if self.wave > 0 && !self.wave_cleared {
+            self.wave_time += dt;
+        }
+
+        self.telegraphs.retain_mut(|telegraph| {
+            telegraph.time_left -= dt;
+
+            if telegraph.time_left <= 0.0 {
+                let key = enemies.insert(Enemy {
+                    spawn_index: spawn_order.allocate(),
+                    ..Enemy::new(telegraph.position, telegraph.kind)
+                });
+
+                if telegraph.wave == Some(self.wave) {
+                    self.wave_enemies.push(key);
+                }
+
+                false
+            } else {
+                true
+            }
+        });
+
+        self.portals.retain_mut(|portal| {
+            for kind in portal.tick(dt) {
+                let key = enemies.insert(Enemy {
+                    spawn_index: spawn_order.allocate(),
+                    ..Enemy::new(portal.position, kind)
+                });
+
+                if portal.wave == Some(self.wave) {
+                    self.wave_enemies.push(key);
+                }
+            }
+
+            !portal.should_delete()
+        });
+    }
+
+    pub fn draw(&self) {
+        for telegraph in &self.telegraphs {
+            let flash = (telegraph.time_left * Self::TELEGRAPH_FLASH_RATE).fract() < 0.5;
+
+            if flash {
+                telegraph.kind.properties.shape.draw_outline(
+                    telegraph.position,
+                    0.1,
+                    Self::TELEGRAPH_COLOR,
+                );
+            }
+        }
+
+        for portal in &self.portals {
+            portal.draw();
+        }
+    }
+}
+
+/// Keeps scheduling single enemies around the turret, more and more often as time goes on
+#[derive(Clone, Copy, Debug)]
+pub struct AutoSpawner {
+    pub elapsed: f64,
+    pub time_until_spawn: f64,
+}
+
+impl Default for AutoSpawner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutoSpawner {
+    pub const INITIAL_INTERVAL: f64 = 4.0;
+    pub const MINIMUM_INTERVAL: f64 = 0.75;
+    /// How long it takes for the interval to halve
+    pub const INTERVAL_HALF_LIFE: f64 = 60.0;
+    /// How far outside of the view enemies spawn
+    pub const SPAWN_MARGIN: f64 = 2.0;
+
+    pub fn new() -> Self {
+        Self {
+            elapsed: 0.0,
+            time_until_spawn: Self::INITIAL_INTERVAL,
+        }
+    }
+
+    #[must_use]
+    pub fn interval(&self) -> f64 {
+        (Self::INITIAL_INTERVAL * 0.5f64.powf(self.elapsed / Self::INTERVAL_HALF_LIFE))
+            .max(Self::MINIMUM_INTERVAL)
+    }
+
+    /// Schedules an enemy on `queue` whenever one is due, `spawn_distance` away from `center`
+    /// and facing it. Kinds and angles are drawn from `rng`.
+    pub fn tick(
+        &mut self,
+        queue: &mut SpawnQueue,
+        center: Point2<f64>,
+        spawn_distance: f64,
+        rng: &mut Rng,
+        dt: f64,
+    ) {
+        self.elapsed += dt;
+        self.time_until_spawn -= dt;
+
+        while self.time_until_spawn <= 0.0 {
+            self.time_until_spawn += self.interval();
+
+            let Some(kind) = queue.spawn_table.choose(u32::MAX, rng) else {
+                return;
+            };
+
+            let angle = queue.next_spawn_angle(rng);
+            let offset = UnitComplex::new(angle) * vector![spawn_distance, 0.0];
+
+            queue.schedule(Isometry2::new(center.coords + offset, angle + PI), kind);
+        }
+    }
+}
+
+/// How often each kind of enemy is picked relative to the others
+#[derive(Clone, Debug)]
+pub struct SpawnTable {
+    pub weights: Vec<(&'static EnemyKind, f64)>,
+}
+
+impl Default for SpawnTable {
+    /// Every kind in [`ENEMY_KINDS`] with the same weight
+    fn default() -> Self {
+        Self {
+            weights: ENEMY_KINDS.iter().map(|kind| (kind, 1.0)).collect(),
+        }
+    }
+}
+
+impl SpawnTable {
+    /// Picks a kind by weight from those costing at most `budget`
+    pub fn choose(&self, budget: u32, rng: &mut Rng) -> Option<&'static EnemyKind> {
+        let affordable = (self.weights.iter())
+            .filter(|(kind, _)| kind.cost() <= budget)
+            .copied()
+            .collect::<Vec<_>>();
+
+        rng.weighted_choice(&affordable).copied()
+    }
+}
+
+/// Randomly buys enemies from `table` until no more can be afforded. The budget left over will
+/// always be less than the cost of the cheapest enemy with a positive weight.
+#[must_use]
+pub fn draw_wave(budget: u32, table: &SpawnTable, rng: &mut Rng) -> Vec<&'static EnemyKind> {
+    let mut remaining = budget;
+    let mut wave = Vec::new();
+
+    while let Some(kind) = table.choose(remaining, rng) {
+        remaining -= kind.cost();
+        wave.push(kind);
+    }
+
+    wave
+}
+
+/// The smallest angle between two directions, from `0.0` to `PI`
+fn angle_between(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(TAU);
+    difference.min(TAU - difference)
+}
+
+#[must_use]
+pub fn wave_cost(wave: &[&EnemyKind]) -> u32 {
+    wave.iter().map(|kind| kind.cost()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawn_waves_spend_the_budget_without_going_over() {
+        let table = SpawnTable::default();
+        let cheapest = ENEMY_KINDS.iter().map(EnemyKind::cost).min().unwrap();
+        let mut rng = Rng::new(8);
+
+        for budget in [0, 1, 5, 20, 100, 1000] {
+            for _ in 0..20 {
+                let cost = wave_cost(&draw_wave(budget, &table, &mut rng));
+
+                assert!(cost <= budget);
+                assert!(budget - cost < cheapest);
+            }
+        }
+    }
+
+    #[test]
+    fn scheduled_spawns_are_telegraphed_before_they_appear() {
+        let position = Isometry2::new(vector![4.0, -3.0], 1.0);
+        let mut queue = SpawnQueue::default();
+        let mut enemies = HopSlotMap::with_key();
+
+        queue.schedule(position, &ENEMY_KINDS[1]);
+        assert_eq!(queue.telegraphs.len(), 1);
+        assert_eq!(queue.telegraphs[0].position, position);
+        assert!(enemies.is_empty());
+
+        let dt = 1.0 / 120.0;
+        let mut time = 0.0;
+        while time + dt < SpawnQueue::LEAD_TIME - 1e-9 {
+            queue.tick(&mut enemies, &mut SpawnOrder::default(), dt);
+            time += dt;
+        }
+        assert!(enemies.is_empty());
+        assert_eq!(queue.telegraphs.len(), 1);
+
+        queue.tick(&mut enemies, &mut SpawnOrder::default(), 2.0 * dt);
+        assert!(queue.telegraphs.is_empty());
+        assert_eq!(enemies.len(), 1);
+
+        let enemy = enemies.values().next().unwrap();
+        assert_eq!(enemy.position, position);
+        assert_eq!(enemy.name, ENEMY_KINDS[1].name);
+    }
+
+    /// How unevenly `count` spawn angles in a row spread over the directions around the turret,
+    /// summed over many runs. Zero would be perfectly even every time.
+    fn spawn_angle_clumping(balance_strength: f64, count: usize) -> f64 {
+        const BINS: usize = 8;
+
+        let mut queue = SpawnQueue {
+            balance_strength,
+            ..Default::default()
+        };
+        let mut rng = Rng::new(18);
+        let expected = count as f64 / BINS as f64;
+
+        (0..200)
+            .map(|_| {
+                let mut bins = [0; BINS];
+                for _ in 0..count {
+                    let angle = queue.next_spawn_angle(&mut rng);
+                    bins[((angle / TAU * BINS as f64) as usize).min(BINS - 1)] += 1;
+                }
+
+                bins.iter()
+                    .map(|&bin| (bin as f64 - expected).powi(2))
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn balanced_spawn_angles_spread_out_more_evenly() {
+        let random = spawn_angle_clumping(0.0, 16);
+        let balanced = spawn_angle_clumping(1.0, 16);
+
+        assert!(balanced < random * 0.5);
+        assert!(spawn_angle_clumping(0.5, 16) < random);
+    }
+
+    #[test]
+    fn spawn_tables_pick_kinds_by_weight() {
+        let table = SpawnTable {
+            weights: vec![
+                (&ENEMY_KINDS[0], 3.0),
+                (&ENEMY_KINDS[1], 1.0),
+                (&ENEMY_KINDS[2], 0.0),
+            ],
+        };
+        let mut rng = Rng::new(9);
+        let mut counts = [0; 3];
+
+        for _ in 0..4000 {
+            let kind = table.choose(u32::MAX, &mut rng).unwrap();
+            let index = (table.weights.iter())
+                .position(|&(other, _)| std::ptr::eq(kind, other))
+                .unwrap();
+            counts[index] += 1;
+        }
+
+        assert_eq!(counts[2], 0);
+        let ratio = counts[0] as f64 / counts[1] as f64;
+        assert!((2.6..3.4).contains(&ratio), "{ratio}");
+    }
+
+    /// How many enemies the auto spawner lets out over each minute of `minutes`
+    fn auto_spawns_per_minute(minutes: usize) -> Vec<usize> {
+        let mut spawner = AutoSpawner::new();
+        let mut queue = SpawnQueue::default();
+        let mut enemies = HopSlotMap::with_key();
+        let mut rng = Rng::new(10);
+        let dt = 1.0 / 120.0;
+
+        (0..minutes)
+            .map(|_| {
+                let before = enemies.len();
+                for _ in 0..60 * 120 {
+                    spawner.tick(&mut queue, Point2::origin(), 25.0, &mut rng, dt);
+                    queue.tick(&mut enemies, &mut SpawnOrder::default(), dt);
+                }
+
+                enemies.len() - before
+            })
+            .collect()
+    }
+
+    #[test]
+    fn auto_spawner_lets_out_enemies_faster_over_time() {
+        let counts = auto_spawns_per_minute(5);
+
+        // The interval halves over the first minute, starting from four seconds
+        let first_minute = AutoSpawner::INTERVAL_HALF_LIFE
+            / (AutoSpawner::INITIAL_INTERVAL * std::f64::consts::LN_2);
+        assert!((counts[0] as f64 - first_minute).abs() <= 2.0, "{counts:?}");
+
+        assert!(
+            counts.windows(2).all(|pair| pair[1] >= pair[0]),
+            "{counts:?}"
+        );
+
+        let fastest = 60.0 / AutoSpawner::MINIMUM_INTERVAL;
+        assert!((counts[4] as f64 - fastest).abs() <= 1.0, "{counts:?}");
+    }
+
+    #[test]
+    fn portals_let_out_their_group_then_close() {
+        let position = Isometry2::new(vector![-6.0, 2.0], 0.3);
+        let kinds = [0, 1, 2, 3, 4].map(|index| &ENEMY_KINDS[index]);
+        let mut queue = SpawnQueue::default();
+        let mut enemies = HopSlotMap::with_key();
+
+        queue.open_portal(position, kinds, 0.5);
+
+        let dt = 1.0 / 120.0;
+        let mut emit_times = Vec::new();
+        for tick in 1..=600 {
+            let before = enemies.len();
+            queue.tick(&mut enemies, &mut SpawnOrder::default(), dt);
+
+            for _ in before..enemies.len() {
+                emit_times.push(tick as f64 * dt);
+            }
+        }
+
+        assert_eq!(emit_times.len(), 5);
+        for (index, time) in emit_times.iter().enumerate() {
+            let expected = SpawnQueue::LEAD_TIME + index as f64 * 0.5;
+            assert!((time - expected).abs() <= dt, "{emit_times:?}");
+        }
+        assert!(queue.portals.is_empty());
+
+        let mut names = enemies
+            .values()
+            .map(|enemy| &enemy.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut expected = kinds.map(|kind| &kind.name);
+        expected.sort();
+        assert_eq!(names, expected);
+        assert!(enemies.values().all(|enemy| enemy.position == position));
+    }
+}
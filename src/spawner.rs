@@ -0,0 +1,260 @@
+//! Spawns enemies from a fixed list of waves, for levels that are laid out by hand rather than
+//! drawn at random
+
+use std::{
+    collections::VecDeque,
+    f64::consts::{PI, TAU},
+};
+
+use nalgebra::{Isometry2, UnitComplex, Vector2, vector};
+use slotmap::HopSlotMap;
+
+use crate::{
+    enemy::{ENEMY_KINDS, Enemy, EnemyKind},
+    game::EnemyKey,
+    rng::Rng,
+    shape::Shape,
+    spawn::SpawnQueue,
+    utils::SpawnOrder,
+};
+
+/// One wave of a [`Spawner`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wave {
+    /// Indices into [`ENEMY_KINDS`] and how many of each to spawn, in order
+    pub enemies: Vec<(usize, u32)>,
+    /// Seconds between each enemy of the wave
+    pub spawn_interval: f64,
+}
+
+/// Works through a list of waves, spawning their enemies just outside the view and facing the
+/// turret. Each wave starts once every enemy of the last one is dead.
+#[derive(Clone, Debug)]
+pub struct Spawner {
+    /// The waves that haven't started yet, next first
+    pub waves: VecDeque<Wave>,
+    /// The number of the current wave, or `0` before the first one
+    pub wave: u32,
+    /// The enemies of the current wave still to be spawned, next first
+    pub pending: VecDeque<&'static EnemyKind>,
+    /// The enemies spawned by the current wave, including ones that have since died
+    pub spawned: Vec<EnemyKey>,
+    pub spawn_interval: f64,
+    pub time_until_spawn: f64,
+    /// Seconds to wait after a wave is cleared before starting the next one
+    pub wave_break: f64,
+    pub time_until_wave: f64,
+}
+
+impl Spawner {
+    /// How far outside of the view enemies spawn, on top of their own size
+    pub const SPAWN_MARGIN: f64 = 0.5;
+
+    /// Starts the first of `waves` on the first tick
+    pub fn new(waves: impl IntoIterator<Item = Wave>) -> Self {
+        Self {
+            waves: waves.into_iter().collect(),
+            wave: 0,
+            pending: VecDeque::new(),
+            spawned: Vec::new(),
+            spawn_interval: 0.0,
+            time_until_spawn: 0.0,
+            wave_break: SpawnQueue::WAVE_BREAK,
+            time_until_wave: 0.0,
+        }
+    }
+
+    /// How many waves are left to start, not counting the current one
+    #[must_use]
+    pub fn remaining_waves(&self) -> usize {
+        self.waves.len()
+    }
+
+    /// How many enemies of the current wave are still alive or waiting to spawn
+    #[must_use]
+    pub fn enemies_remaining(&self, enemies: &HopSlotMap<EnemyKey, Enemy>) -> usize {
+        let alive = (self.spawned.iter())
+            .filter(|&&key| enemies.contains_key(key))
+            .count();
+
+        alive + self.pending.len()
+    }
+
+    /// Starts the next wave once the current one is cleared and the break after it is over, then
+    /// spawns its enemies one at a time. `camera_bounds` are centered on `origin`, where the turret
+    /// is, and spawn angles are drawn from `rng`. Unknown kind indices are skipped.
+    pub fn tick(
+        &mut self,
+        dt: f64,
+        enemies: &mut HopSlotMap<EnemyKey, Enemy>,
+        spawn_order: &mut SpawnOrder,
+        camera_bounds: &Shape,
+        origin: &Isometry2<f64>,
+        rng: &mut Rng,
+    ) {
+        if self.enemies_remaining(enemies) == 0 {
+            self.time_until_wave -= dt;
+
+            if self.time_until_wave > 0.0 || !self.start_next_wave() {
+                return;
+            }
+        }
+
+        self.time_until_spawn -= dt;
+
+        while self.time_until_spawn <= 0.0 {
+            let Some(kind) = self.pending.pop_front() else {
+                return;
+            };
+
+            self.time_until_spawn += self.spawn_interval;
+
+            let angle = rng.gen_range(0.0, TAU);
+            let direction = UnitComplex::new(angle) * vector![1.0, 0.0];
+            let clearance = kind.properties.shape.bounding_radius() + Self::SPAWN_MARGIN;
+            let distance = distance_to_edge(camera_bounds, clearance, direction);
+
+            let position = origin * Isometry2::new(direction * distance, angle + PI);
+            self.spawned.push(enemies.insert(Enemy {
+                spawn_index: spawn_order.allocate(),
+                ..Enemy::new(position, kind)
+            }));
+        }
+    }
+
+    /// Returns false if there are no waves left
+    fn start_next_wave(&mut self) -> bool {
+        let Some(wave) = self.waves.pop_front() else {
+            return false;
+        };
+
+        self.wave += 1;
+        self.pending = (wave.enemies.iter())
+            .filter_map(|&(index, count)| Some((ENEMY_KINDS.get(index)?, count)))
+            .flat_map(|(kind, count)| std::iter::repeat_n(kind, count as usize))
+            .collect();
+        self.spawned.clear();
+        self.spawn_interval = wave.spawn_interval;
+        self.time_until_spawn = 0.0;
+        self.time_until_wave = self.wave_break;
+
+        true
+    }
+}
+
+/// How far from the center of `bounds` to go in `direction` to be at least `clearance` away from
+/// its edge. `direction` must be normalized. Shapes other than rectangles are treated as their
+/// bounding circle.
+fn distance_to_edge(bounds: &Shape, clearance: f64, direction: Vector2<f64>) -> f64 {
+    match *bounds {
+        Shape::Rectangle { half_size } => {
+            let half_size = half_size.add_scalar(clearance);
+
+            (half_size.x / direction.x.abs()).min(half_size.y / direction.y.abs())
+        }
+        _ => bounds.bounding_radius() + clearance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::SQRT_2;
+
+    use nalgebra::vector;
+
+    use super::*;
+
+    const DT: f64 = 1.0 / 120.0;
+
+    fn camera_bounds() -> Shape {
+        Shape::Rectangle {
+            half_size: vector![20.0, 12.0],
+        }
+    }
+
+    fn tick(
+        spawner: &mut Spawner,
+        enemies: &mut HopSlotMap<EnemyKey, Enemy>,
+        rng: &mut Rng,
+        seconds: f64,
+    ) {
+        for _ in 0..(seconds / DT).ceil() as usize {
+            spawner.tick(
+                DT,
+                enemies,
+                &mut SpawnOrder::default(),
+                &camera_bounds(),
+                &Isometry2::identity(),
+                rng,
+            );
+        }
+    }
+
+    #[test]
+    fn enemies_spawn_just_outside_the_view_facing_the_turret() {
+        let mut spawner = Spawner::new([Wave {
+            enemies: vec![(0, 20), (3, 20)],
+            spawn_interval: 0.1,
+        }]);
+        let mut enemies = HopSlotMap::with_key();
+        let mut rng = Rng::new(5);
+
+        tick(&mut spawner, &mut enemies, &mut rng, 3.95);
+        assert_eq!(enemies.len(), 40);
+
+        for enemy in enemies.values() {
+            let position = enemy.position.translation.vector;
+            let clearance = enemy.shape.bounding_radius() + Spawner::SPAWN_MARGIN;
+            let distance_from_view = (position.abs() - vector![20.0, 12.0])
+                .sup(&vector![0.0, 0.0])
+                .norm();
+
+            assert!(!camera_bounds().is_colliding(&enemy.shape, enemy.position));
+            assert!(distance_from_view >= clearance - 1e-9);
+            assert!(distance_from_view <= clearance * SQRT_2 + 1e-9);
+
+            let heading = enemy.position.rotation * vector![1.0, 0.0];
+            assert!(heading.dot(&-position.normalize()) > 1.0 - 1e-9);
+        }
+    }
+
+    #[test]
+    fn waves_advance_once_the_last_wave_is_dead() {
+        let mut spawner = Spawner::new([
+            Wave {
+                enemies: vec![(0, 2), (1, 1)],
+                spawn_interval: 0.5,
+            },
+            Wave {
+                enemies: vec![(2, 1)],
+                spawn_interval: 0.5,
+            },
+        ]);
+        let mut enemies = HopSlotMap::with_key();
+        let mut rng = Rng::new(6);
+
+        tick(&mut spawner, &mut enemies, &mut rng, 0.1);
+        assert_eq!(spawner.wave, 1);
+        assert_eq!(spawner.remaining_waves(), 1);
+
+        tick(&mut spawner, &mut enemies, &mut rng, 10.0);
+        assert_eq!(enemies.len(), 3);
+        assert_eq!(spawner.enemies_remaining(&enemies), 3);
+        assert_eq!(spawner.wave, 1);
+
+        let key = enemies.keys().next().unwrap();
+        enemies.remove(key);
+        tick(&mut spawner, &mut enemies, &mut rng, 10.0);
+        assert_eq!(spawner.wave, 1);
+
+        enemies.clear();
+        let wave_break = spawner.wave_break;
+        tick(&mut spawner, &mut enemies, &mut rng, wave_break - 0.1);
+        assert_eq!(spawner.wave, 1);
+
+        tick(&mut spawner, &mut enemies, &mut rng, 0.2);
+        assert_eq!(spawner.wave, 2);
+        assert_eq!(spawner.remaining_waves(), 0);
+        assert_eq!(enemies.len(), 1);
+    }
+}
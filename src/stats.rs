@@ -0,0 +1,109 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt::Write};
+
+use crate::{content, score::Score};
+
+/// Counters collected over a run, for [`RunSummary`]
+#[derive(Clone, Debug, Default)]
+pub struct RunStats {
+    pub duration: f64,
+    pub shots_fired: u32,
+    /// Shots that hit at least one enemy
+    pub shots_hit: u32,
+    pub kills_by_kind: BTreeMap<Cow<'static, str>, u32>,
+    /// Kills in the current combo, each within [`Score::COMBO_TIME`] of the last
+    pub combo: u32,
+    pub max_combo: u32,
+}
+
+impl RunStats {
+    /// Counts a kill of an enemy of kind `name`. Call this before [`Score::on_kill`], so that
+    /// `score` still knows how long it has been since the previous kill.
+    pub fn on_kill(&mut self, name: Cow<'static, str>, score: &Score) {
+        *self.kills_by_kind.entry(name).or_default() += 1;
+
+        if score.time_since_kill <= Score::COMBO_TIME {
+            self.combo += 1;
+        } else {
+            self.combo = 1;
+        }
+        self.max_combo = self.max_combo.max(self.combo);
+    }
+
+    /// The portion of shots that hit something, or zero before the first shot
+    #[must_use]
+    pub fn accuracy(&self) -> f64 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f64 / self.shots_fired as f64
+        }
+    }
+}
+
+/// Statistics for a finished run, for leaderboards
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunSummary {
+    /// The seed the game was started with
+    pub seed: u64,
+    pub duration: f64,
+    pub score: u64,
+    pub kills_by_kind: Vec<(Cow<'static, str>, u32)>,
+    pub shots_fired: u32,
+    pub accuracy: f64,
+    pub max_combo: u32,
+}
+
+impl RunSummary {
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+
+        write!(
+            json,
+            "\"seed\":{},\"duration\":{},\"score\":{},\"kills_by_kind\":{{",
+            self.seed, self.duration, self.score,
+        )
+        .unwrap();
+
+        for (i, (name, kills)) in self.kills_by_kind.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            content::write_json_string(&mut json, name);
+            write!(json, ":{kills}").unwrap();
+        }
+
+        write!(
+            json,
+            "}},\"shots_fired\":{},\"accuracy\":{},\"max_combo\":{}}}",
+            self.shots_fired, self.accuracy, self.max_combo,
+        )
+        .unwrap();
+
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_escape_kind_names_in_json() {
+        let summary = RunSummary {
+            seed: 1,
+            duration: 2.0,
+            score: 3,
+            kills_by_kind: vec![("Boss \"Big\" \\ One".into(), 2)],
+            shots_fired: 4,
+            accuracy: 0.5,
+            max_combo: 5,
+        };
+
+        assert!(
+            summary
+                .to_json()
+                .contains(r#""kills_by_kind":{"Boss \"Big\" \\ One":2}"#)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use macroquad::{color::Color, shapes};
+use nalgebra::{Point2, Vector2};
+
+use crate::{game::EnemyKey, utils};
+
+/// How a projectile latches onto the first enemy it hits and reels it in
+#[derive(Clone, Copy, Debug)]
+pub struct TetherProperties {
+    /// How strongly the enemy is pulled towards the anchor per unit of distance
+    pub stiffness: f64,
+    /// How strongly the enemy's velocity is resisted while tethered
+    pub damping: f64,
+    /// How long the tether holds before breaking
+    pub duration: f64,
+    /// The tether breaks if the enemy gets further than this from the anchor
+    pub break_length: f64,
+}
+
+/// A projectile's attachment to an enemy
+#[derive(Clone, Copy, Debug)]
+pub struct Tether {
+    pub enemy: EnemyKey,
+    pub anchor: Point2<f64>,
+    pub time_left: f64,
+}
+
+/// The spring force pulling an enemy, replacing its own movement for a tick
+#[derive(Clone, Copy, Debug)]
+pub struct TetherPull {
+    pub anchor: Point2<f64>,
+    pub stiffness: f64,
+    pub damping: f64,
+}
+
+impl Tether {
+    pub const COLOR: Color = Color::from_hex(0x00ffff);
+    pub const THICKNESS: f64 = 0.05;
+
+    pub fn new(enemy: EnemyKey, anchor: Point2<f64>, properties: &TetherProperties) -> Self {
+        Self {
+            enemy,
+            anchor,
+            time_left: properties.duration,
+        }
+    }
+
+    #[must_use]
+    pub fn should_break(&self, enemy_position: Point2<f64>, properties: &TetherProperties) -> bool {
+        self.time_left <= 0.0 || (enemy_position - self.anchor).norm() > properties.break_length
+    }
+
+    pub fn draw(&self, end: Point2<f64>, opacity: f64) {
+        let start = utils::point2_f64_to_vec2(self.anchor);
+        let end = utils::point2_f64_to_vec2(end);
+
+        shapes::draw_line(
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            Self::THICKNESS as f32,
+            Color {
+                a: opacity as f32,
+                ..Self::COLOR
+            },
+        );
+    }
+}
+
+impl TetherPull {
+    /// Springs `velocity` towards the anchor from `position`
+    #[must_use]
+    pub fn apply(&self, position: Point2<f64>, velocity: Vector2<f64>, dt: f64) -> Vector2<f64> {
+        let acceleration = (self.anchor - position) * self.stiffness - velocity * self.damping;
+
+        velocity + acceleration * dt
+    }
+}
@@ -0,0 +1,151 @@
+use macroquad::{color::Color, shapes};
+use nalgebra::{Isometry2, Point2};
+use slotmap::HopSlotMap;
+
+use crate::{
+    enemy::{Enemy, ExplosionParameters},
+    game::{EnemyKey, ParticleKey},
+    particle::Particle,
+    projectile::SourceId,
+    rng::Rng,
+    shape::Shape,
+};
+
+/// How a projectile lays down trail zones behind itself
+#[derive(Clone, Copy, Debug)]
+pub struct TrailProperties {
+    /// The distance travelled between zones
+    pub interval: f64,
+    pub radius: f64,
+    pub damage_per_second: f64,
+    pub lifetime: f64,
+}
+
+/// A lingering area that damages enemies inside of it
+#[derive(Clone, Copy, Debug)]
+pub struct TrailZone {
+    pub position: Point2<f64>,
+    pub shape: Shape,
+    pub damage_per_second: f64,
+    pub time_left: f64,
+    pub lifetime: f64,
+    pub source: Option<SourceId>,
+}
+
+impl TrailZone {
+    pub const COLOR: Color = Color::from_hex(0x7fff3f);
+    pub const OPACITY: f64 = 0.35;
+
+    pub fn new(
+        position: Point2<f64>,
+        properties: &TrailProperties,
+        source: Option<SourceId>,
+    ) -> Self {
+        Self {
+            position,
+            shape: Shape::Circle {
+                radius: properties.radius,
+            },
+            damage_per_second: properties.damage_per_second,
+            time_left: properties.lifetime,
+            lifetime: properties.lifetime,
+            source,
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        enemies: &mut HopSlotMap<EnemyKey, Enemy>,
+        particles: &mut HopSlotMap<ParticleKey, Particle>,
+        explosion: &ExplosionParameters,
+        tick: u64,
+        rng: &mut Rng,
+        dt: f64,
+    ) {
+        self.time_left -= dt;
+
+        let position = Isometry2::translation(self.position.x, self.position.y);
+
+        for (key, enemy) in enemies {
+            if enemy.should_delete()
+                || !self
+                    .shape
+                    .is_colliding(&enemy.shape, position.inv_mul(&enemy.position))
+            {
+                continue;
+            }
+
+            enemy.damage_over_time(self.damage_per_second * dt, self.source, tick);
+
+            if enemy.should_delete() {
+                enemy.die(
+                    self.position,
+                    enemy.linear_velocity,
+                    explosion,
+                    particles,
+                    Enemy::explosion_seed(key, tick),
+                    rng,
+                );
+            }
+        }
+    }
+
+    pub fn draw(&self) {
+        let Shape::Circle { radius } = self.shape else {
+            return;
+        };
+
+        shapes::draw_circle(
+            self.position.x as f32,
+            self.position.y as f32,
+            radius as f32,
+            Color {
+                a: (Self::OPACITY * (self.time_left / self.lifetime).clamp(0.0, 1.0)) as f32,
+                ..Self::COLOR
+            },
+        );
+    }
+
+    pub fn should_delete(&self) -> bool {
+        self.time_left <= 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use nalgebra::{point, vector};
+
+    use super::*;
+    use crate::{behavior::EnemyContext, enemy::ENEMY_KINDS, projectile::PROJECTILE_KINDS};
+
+    #[test]
+    fn zones_hurt_enemies_walking_through_until_they_expire() {
+        let properties = PROJECTILE_KINDS[4].properties.trail.unwrap();
+        let mut zone = TrailZone::new(point![0.0, 0.0], &properties, None);
+
+        let mut enemies = HopSlotMap::with_key();
+        let key = enemies.insert(Enemy {
+            time_since_spawn: 1.0,
+            ..Enemy::new(Isometry2::new(vector![2.0, 0.0], PI), &ENEMY_KINDS[3])
+        });
+        let mut particles = HopSlotMap::with_key();
+        let explosion = ExplosionParameters::default();
+        let context = EnemyContext::default();
+        let mut rng = Rng::new(16);
+
+        let dt = 1.0 / 120.0;
+        let mut time = 0.0;
+        while !zone.should_delete() {
+            zone.tick(&mut enemies, &mut particles, &explosion, 0, &mut rng, dt);
+            enemies[key].tick(&context, &mut rng, dt);
+            time += dt;
+        }
+
+        let enemy = &enemies[key];
+        assert!(enemy.health < enemy.properties.maximum_health);
+        assert!(enemy.position.translation.x < -1.0);
+        assert!((time - properties.lifetime).abs() < dt);
+    }
+}
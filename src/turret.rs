@@ -4,32 +4,100 @@ use macroquad::{
     shapes::{self, DrawRectangleParams},
     texture::{self, DrawTextureParams},
 };
-use nalgebra::{Complex, Isometry2, Point2, UnitComplex, point, vector};
+use nalgebra::{Complex, Isometry2, Point2, UnitComplex, Vector2, point, vector};
 use slotmap::HopSlotMap;
 
 use crate::{
     game::ProjectileKey,
-    projectile::{PROJECTILE_KINDS, Projectile, ProjectileKind},
+    object::{Object, Transform},
+    projectile::{PROJECTILE_KINDS, Projectile, ProjectileKind, SourceId},
+    rng::Rng,
     shape::Shape,
-    utils::{self, TURRET_BASE_TEXTURE},
+    tween::{self, Tween},
+    utils::{self, SpawnOrder, TURRET_BASE_TEXTURE},
 };
 
 #[derive(Clone, Debug)]
 pub struct Turret {
     pub shape: Shape,
     pub position: Isometry2<f64>,
+    /// Where the turret turns towards and shoots at. The game points its primary turret at the
+    /// player's aim every tick, and leaves the others wherever they're pointed.
+    pub target: Point2<f64>,
 
     pub time_since_shoot: f64,
     pub time_since_recharged: f64,
     pub projectile_kind: ProjectileKind,
+    /// How quickly the turret turns towards its target. Shooting always snaps to the target.
+    pub aim_decay: f64,
+    /// Marks projectiles fired by this turret
+    pub source: SourceId,
+
+    /// Shapes how the barrel slides back into place while recharging. Takes the remaining
+    /// portion of the cooldown, from `1.0` right after shooting to `0.0` when ready.
+    pub recharge_easing: fn(f64) -> f64,
+    /// The barrel offset is rounded up to a multiple of this, or left smooth if `None`
+    pub recharge_step: Option<f64>,
 
     pub input: PlayerInput,
+    /// Lets the turret bounce enemy projectiles back, if set
+    pub parry: Option<Parry>,
+}
+
+/// A short window where enemy projectiles that reach the turret are reflected instead of hurting
+/// it, followed by a cooldown
+#[derive(Clone, Copy, Debug)]
+pub struct Parry {
+    /// How long each parry lasts
+    pub window: f64,
+    /// How long after starting a parry until the next one can start
+    pub cooldown: f64,
+    pub time_since_start: f64,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct PlayerInput {
+    /// Set by a press of the shoot button, and kept for [`PlayerInput::BUFFER_TIME`] or until a
+    /// shot is fired
     pub shoot: bool,
     pub time_since_press: f64,
+
+    /// Whether the shoot button is down
+    pub held: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    /// Ignores the shoot button until it is released, after [`PlayerInput::cancel`]
+    pub wait_for_release: bool,
+}
+
+/// Shapes how stick or key deflection turns into aim movement. Mouse aiming is direct and doesn't
+/// go through this.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputSettings {
+    /// Deflections shorter than this are ignored, so a resting stick doesn't drift
+    pub dead_zone: f64,
+    /// Higher values give finer control near the center and save speed for full deflection
+    pub exponent: f64,
+    /// How fast the aim moves at full deflection, in units per second
+    pub sensitivity: f64,
+}
+
+/// A cursor steered by keys instead of the mouse, for aiming without one
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VirtualCursor {
+    pub position: Point2<f64>,
+    pub settings: InputSettings,
+}
+
+/// Pulls the aim point onto nearby enemies, or onto a world grid, for precise aiming
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReticleSnapping {
+    /// How close a snap target has to be to the aim point to pull it
+    pub threshold: f64,
+    /// How far towards the snap target to pull the aim point, from `0.0` to `1.0`
+    pub strength: f64,
+    /// The spacing of a world grid to snap to when no enemy is close enough
+    pub grid_spacing: Option<f64>,
 }
 
 impl Default for Turret {
@@ -37,11 +105,52 @@ impl Default for Turret {
         Self {
             shape: Shape::Circle { radius: 0.6 },
             position: Isometry2::new(vector![0.0, 0.0], 0.0),
+            target: point![0.0, 0.0],
             time_since_shoot: 0.0,
             time_since_recharged: 0.0,
             projectile_kind: PROJECTILE_KINDS[0].clone(),
+            aim_decay: Self::DEFAULT_AIM_DECAY,
+            source: SourceId::default(),
+            recharge_easing: tween::ease_in_quad,
+            recharge_step: Some(Self::DEFAULT_RECHARGE_STEP),
             input: PlayerInput::default(),
+            parry: Some(Parry::default()),
+        }
+    }
+}
+
+impl Default for Parry {
+    fn default() -> Self {
+        Self {
+            window: 0.2,
+            cooldown: 1.0,
+            time_since_start: f64::INFINITY,
+        }
+    }
+}
+
+impl Parry {
+    pub const KEY: KeyCode = KeyCode::LeftShift;
+    pub const COLOR: Color = Color::from_hex(0xffe040);
+    pub const THICKNESS: f64 = 0.1;
+
+    pub fn tick(&mut self, dt: f64) {
+        self.time_since_start += dt;
+    }
+
+    /// Starts a parry unless the last one is still cooling down, returning whether it started
+    pub fn start(&mut self) -> bool {
+        if self.time_since_start < self.cooldown {
+            return false;
         }
+
+        self.time_since_start = 0.0;
+        true
+    }
+
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.time_since_start < self.window
     }
 }
 
@@ -52,9 +161,24 @@ impl PlayerInput {
     pub const BUFFER_TIME: f64 = 1.0 / 6.0;
 
     pub fn tick(&mut self, dt: f64) {
-        let _ = dt;
+        self.tick_with(Self::shoot_down(), dt);
+    }
+
+    /// Like [`PlayerInput::tick`], but with the state of the shoot button given instead of read
+    /// from the window
+    pub fn tick_with(&mut self, shoot_down: bool, dt: f64) {
+        if self.wait_for_release {
+            if shoot_down {
+                return;
+            }
+            self.wait_for_release = false;
+        }
+
+        self.just_pressed = shoot_down && !self.held;
+        self.just_released = !shoot_down && self.held;
+        self.held = shoot_down;
 
-        if Self::shoot_down() {
+        if self.just_pressed {
             self.shoot = true;
             self.time_since_press = 0.0;
         } else {
@@ -66,6 +190,22 @@ impl PlayerInput {
         }
     }
 
+    /// Drops any buffered shot and ignores the shoot button until it's released, so that the
+    /// click that closes a menu doesn't also fire
+    pub fn cancel(&mut self) {
+        *self = Self {
+            wait_for_release: true,
+            ..Self::default()
+        };
+    }
+
+    /// Whether the turret should fire if it can, given whether its weapon fires automatically
+    #[must_use]
+    pub fn wants_to_shoot(&self, auto_fire: bool) -> bool {
+        self.shoot || (auto_fire && self.held)
+    }
+
+    /// Also counts buttons pressed this frame, so clicks shorter than a frame aren't missed
     pub fn shoot_down() -> bool {
         Self::KEYS.into_iter().any(input::is_key_down)
             || Self::MOUSE_BUTTONS.into_iter().any(|button| {
@@ -74,6 +214,133 @@ impl PlayerInput {
     }
 }
 
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            exponent: 2.0,
+            sensitivity: 20.0,
+        }
+    }
+}
+
+impl Default for ReticleSnapping {
+    fn default() -> Self {
+        Self {
+            threshold: 1.5,
+            strength: 1.0,
+            grid_spacing: None,
+        }
+    }
+}
+
+impl ReticleSnapping {
+    pub const RADIUS: f64 = 0.4;
+    pub const THICKNESS: f64 = 0.08;
+    pub const COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.5);
+    pub const SNAPPED_COLOR: Color = colors::WHITE;
+
+    /// Where the reticle goes for an aim point of `aim`, given the enemy centers in `targets`.
+    /// Returns `aim` unchanged if nothing is within [`ReticleSnapping::threshold`].
+    #[must_use]
+    pub fn snap(
+        &self,
+        aim: Point2<f64>,
+        targets: impl IntoIterator<Item = Point2<f64>>,
+    ) -> Point2<f64> {
+        let target = targets
+            .into_iter()
+            .map(|target| (target, nalgebra::distance(&aim, &target)))
+            .filter(|&(_, distance)| distance <= self.threshold)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(target, _)| target)
+            .or_else(|| {
+                let spacing = self.grid_spacing?;
+                let target = (aim / spacing).map(f64::round) * spacing;
+
+                (nalgebra::distance(&aim, &target) <= self.threshold).then_some(target)
+            });
+
+        match target {
+            Some(target) => aim + (target - aim) * self.strength,
+            None => aim,
+        }
+    }
+
+    /// Draws the reticle at `position`, brighter when it's been pulled away from `aim`
+    pub fn draw(position: Point2<f64>, aim: Point2<f64>) {
+        let color = if position == aim {
+            Self::COLOR
+        } else {
+            Self::SNAPPED_COLOR
+        };
+        let center = utils::point2_f64_to_vec2(position);
+
+        shapes::draw_circle_lines(
+            center.x,
+            center.y,
+            Self::RADIUS as f32,
+            Self::THICKNESS as f32,
+            color,
+        );
+
+        for direction in [vector![1.0, 0.0], vector![0.0, 1.0]] {
+            let start = utils::point2_f64_to_vec2(position - direction * Self::RADIUS * 1.5);
+            let end = utils::point2_f64_to_vec2(position + direction * Self::RADIUS * 1.5);
+
+            shapes::draw_line(
+                start.x,
+                start.y,
+                end.x,
+                end.y,
+                Self::THICKNESS as f32,
+                color,
+            );
+        }
+    }
+}
+
+impl InputSettings {
+    /// How fast to move the aim for a deflection of up to length `1.0`. Longer deflections are
+    /// treated as full deflection in the same direction.
+    #[must_use]
+    pub fn aim_velocity(&self, deflection: Vector2<f64>) -> Vector2<f64> {
+        let magnitude = deflection.norm();
+
+        if magnitude <= self.dead_zone {
+            return Vector2::zeros();
+        }
+
+        let response =
+            ((magnitude.min(1.0) - self.dead_zone) / (1.0 - self.dead_zone)).powf(self.exponent);
+
+        deflection / magnitude * response * self.sensitivity
+    }
+}
+
+impl VirtualCursor {
+    pub const KEYS: [(KeyCode, Vector2<f64>); 4] = [
+        (KeyCode::Up, vector![0.0, 1.0]),
+        (KeyCode::Down, vector![0.0, -1.0]),
+        (KeyCode::Left, vector![-1.0, 0.0]),
+        (KeyCode::Right, vector![1.0, 0.0]),
+    ];
+
+    pub fn tick(&mut self, deflection: Vector2<f64>, dt: f64) {
+        self.position += self.settings.aim_velocity(deflection) * dt;
+    }
+
+    /// The deflection from whichever of [`VirtualCursor::KEYS`] are held
+    #[must_use]
+    pub fn keyboard_deflection() -> Vector2<f64> {
+        Self::KEYS
+            .into_iter()
+            .filter(|&(key, _)| input::is_key_down(key))
+            .map(|(_, direction)| direction)
+            .sum()
+    }
+}
+
 impl Turret {
     pub const PLATFORM_WIDTH: f64 = 0.4;
     pub const PLATFORM_RECHARGE_ANIMATION_WIDTH: f64 = 0.6;
@@ -85,28 +352,39 @@ impl Turret {
     pub const BARREL_BASE_OFFSET: f64 = Self::BARREL_WIDTH / 2.0;
     pub const BARREL_SHOOT_OFFSET: f64 = 0.5;
 
+    pub const DEFAULT_AIM_DECAY: f64 = 20.0;
+    /// One pixel of the turret texture
+    pub const DEFAULT_RECHARGE_STEP: f64 = 0.1;
+
     pub fn tick(
         &mut self,
-        mouse_position: Point2<f64>,
         projectiles: &mut HopSlotMap<ProjectileKey, Projectile>,
+        spawn_order: &mut SpawnOrder,
+        rng: &mut Rng,
         dt: f64,
     ) {
-        let mouse_offset = mouse_position.coords - self.position.translation.vector;
+        let target_offset = self.target.coords - self.position.translation.vector;
 
-        let mouse_direction = if mouse_offset.magnitude_squared() == 0.0 {
+        let target_direction = if target_offset.magnitude_squared() == 0.0 {
             self.position.rotation
         } else {
-            UnitComplex::new_normalize(Complex::new(mouse_offset.x, mouse_offset.y))
+            UnitComplex::new_normalize(Complex::new(target_offset.x, target_offset.y))
         };
 
         self.time_since_shoot += dt;
 
-        if self.input.shoot && self.can_shoot() {
-            self.position.rotation = mouse_direction;
-            self.shoot(projectiles);
+        if let Some(parry) = &mut self.parry {
+            parry.tick(dt);
+        }
+
+        if self.input.wants_to_shoot(self.projectile_kind.auto_fire) && self.can_shoot() {
+            self.position.rotation = target_direction;
+            self.shoot(projectiles, spawn_order, rng);
         } else {
-            self.position.rotation = (self.position.rotation)
-                .slerp(&mouse_direction, utils::exp_decay(0.0, 1.0, 20.0, dt));
+            self.position.rotation = (self.position.rotation).slerp(
+                &target_direction,
+                tween::exp_decay(0.0, 1.0, self.aim_decay, dt),
+            );
         }
 
         if self.can_shoot() {
@@ -116,14 +394,25 @@ impl Turret {
         }
     }
 
+    pub fn set_aim_decay(&mut self, aim_decay: f64) {
+        self.aim_decay = aim_decay.max(0.0);
+    }
+
     pub fn draw(&self) {
+        self.draw_with_opacity(1.0);
+    }
+
+    pub fn draw_with_opacity(&self, opacity: f32) {
         let size = TURRET_BASE_TEXTURE.size() * 0.1;
 
         texture::draw_texture_ex(
             &TURRET_BASE_TEXTURE,
             self.position.translation.x as f32 - size.x / 2.0,
             self.position.translation.y as f32 - size.y / 2.0,
-            colors::WHITE,
+            Color {
+                a: opacity,
+                ..colors::WHITE
+            },
             DrawTextureParams {
                 dest_size: Some(size),
                 source: None,
@@ -152,15 +441,15 @@ impl Turret {
             DrawRectangleParams {
                 offset: [0.5, 0.5].into(),
                 rotation: self.position.rotation.angle() as f32,
-                color: utils::brighten_color(Color::from_hex(0x00b6bf), brightness),
+                color: Color {
+                    a: opacity,
+                    ..utils::brighten_color(Color::from_hex(0x00b6bf), brightness)
+                },
             },
         );
 
-        let position = self.position
-            * point![
-                -Self::BARREL_BASE_OFFSET - self.shoot_recharge_offset(),
-                0.0
-            ];
+        let position =
+            self.barrel_tip() - self.position.rotation * vector![self.shoot_recharge_offset(), 0.0];
 
         shapes::draw_rectangle_ex(
             position.x as f32,
@@ -168,46 +457,425 @@ impl Turret {
             (Self::BARREL_LENGTH + Self::BARREL_BASE_OFFSET) as f32,
             Self::BARREL_WIDTH as f32,
             DrawRectangleParams {
-                offset: [0.0, 0.5].into(),
+                offset: [1.0, 0.5].into(),
                 rotation: self.position.rotation.angle() as f32,
-                color: utils::brighten_color(
-                    Color::from_hex(0x00d8e4),
-                    (1.0 - self.shoot_recharge_progress()) * 0.65,
-                ),
+                color: Color {
+                    a: opacity,
+                    ..utils::brighten_color(
+                        Color::from_hex(0x00d8e4),
+                        (1.0 - self.shoot_recharge_progress()) * 0.65,
+                    )
+                },
             },
-        )
+        );
+
+        if self.parry.is_some_and(|parry| parry.is_active()) {
+            self.shape.draw_outline(
+                self.position,
+                Parry::THICKNESS,
+                Color {
+                    a: opacity,
+                    ..Parry::COLOR
+                },
+            );
+        }
     }
 
-    pub fn shoot(&mut self, projectiles: &mut HopSlotMap<ProjectileKey, Projectile>) {
+    /// The turret as a stationary collision object
+    #[must_use]
+    pub fn object(&self) -> Object {
+        Object {
+            shape: self.shape,
+            transform: Transform {
+                position: self.position,
+                linear_velocity: vector![0.0, 0.0],
+                angular_velocity: 0.0,
+            },
+        }
+    }
+
+    pub fn shoot(
+        &mut self,
+        projectiles: &mut HopSlotMap<ProjectileKey, Projectile>,
+        spawn_order: &mut SpawnOrder,
+        rng: &mut Rng,
+    ) {
         self.time_since_shoot = 0.0;
         self.input.shoot = false;
 
-        let translation = self.position
-            * point![
-                Self::BARREL_LENGTH + self.projectile_kind.properties.distance_to_back(),
-                0.0
-            ];
-        let position = Isometry2::from_parts(translation.into(), self.position.rotation);
+        for index in 0..self.projectile_kind.fire_pattern.pellets {
+            projectiles.insert(Projectile {
+                spawn_index: spawn_order.allocate(),
+                source: self.source,
+                ..Projectile::new(self.pellet_transform(index), &self.projectile_kind, rng)
+            });
+        }
+    }
+
+    /// The end of the barrel when it is fully recharged
+    #[must_use]
+    pub fn barrel_tip(&self) -> Point2<f64> {
+        self.position * point![Self::BARREL_LENGTH, 0.0]
+    }
+
+    /// Where projectiles are spawned, with the back of the projectile touching the barrel tip
+    #[must_use]
+    pub fn muzzle_transform(&self) -> Isometry2<f64> {
+        let translation = self.barrel_tip()
+            + self.position.rotation
+                * vector![self.projectile_kind.properties.distance_to_back(), 0.0];
 
-        projectiles.insert(Projectile::new(position, &self.projectile_kind));
+        Isometry2::from_parts(translation.into(), self.position.rotation)
+    }
+
+    /// Where the pellet at `index` of the fire pattern is spawned. Pellets are spread across the
+    /// width of the barrel and fanned out by the pattern's spread.
+    #[must_use]
+    pub fn pellet_transform(&self, index: usize) -> Isometry2<f64> {
+        let pattern = self.projectile_kind.fire_pattern;
+        let fraction = pattern.pellet_fraction(index);
+
+        let muzzle = self.muzzle_transform();
+        let translation = muzzle.translation.vector
+            + muzzle.rotation * vector![0.0, fraction * Self::BARREL_WIDTH];
+
+        Isometry2::from_parts(
+            translation.into(),
+            muzzle.rotation * UnitComplex::new(fraction * pattern.spread),
+        )
     }
 
     pub fn shoot_recharge_progress(&self) -> f64 {
         (self.time_since_shoot / self.projectile_kind.shoot_cooldown).clamp(0.0, 1.0)
     }
 
+    /// The barrel's offset out to [`Turret::BARREL_SHOOT_OFFSET`] over the cooldown of the last
+    /// shot. It plays in reverse while the turret recharges, so that
+    /// [`Turret::recharge_easing`] gets the remaining portion of the cooldown.
+    #[must_use]
+    pub fn recharge_tween(&self) -> Tween<f64> {
+        let cooldown = self.projectile_kind.shoot_cooldown;
+
+        Tween {
+            elapsed: (cooldown - self.time_since_shoot).max(0.0),
+            ..Tween::new(
+                0.0,
+                Self::BARREL_SHOOT_OFFSET,
+                cooldown,
+                self.recharge_easing,
+            )
+        }
+    }
+
     pub fn shoot_recharge_offset(&self) -> f64 {
-        let progress = (1.0 - self.shoot_recharge_progress()).powi(2);
-        let offset = progress * Self::BARREL_SHOOT_OFFSET;
+        let offset = self.recharge_tween().value();
 
-        (offset / 0.1).ceil() * 0.1
+        match self.recharge_step {
+            Some(step) if step > 0.0 => (offset / step).ceil() * step,
+            _ => offset,
+        }
+    }
+
+    /// Whether the turret can shoot right now, and if not, why
+    #[must_use]
+    pub fn shoot_status(&self) -> ShootStatus {
+        if self.time_since_shoot >= self.projectile_kind.shoot_cooldown {
+            ShootStatus::Ready
+        } else {
+            ShootStatus::CoolingDown {
+                progress: self.shoot_recharge_progress(),
+            }
+        }
     }
 
     pub fn can_shoot(&self) -> bool {
-        self.time_since_shoot >= self.projectile_kind.shoot_cooldown
+        matches!(self.shoot_status(), ShootStatus::Ready)
     }
 
     pub fn show_recharge_animation(&self) -> bool {
         self.can_shoot() && self.time_since_recharged < Self::RECHARGE_ANIMATION_LENGTH
     }
 }
+
+/// What is stopping a turret from shooting, for showing on the HUD
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShootStatus {
+    Ready,
+    /// Waiting for the shoot cooldown, with `progress` going from `0.0` to `1.0`
+    CoolingDown {
+        progress: f64,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::projectile::FirePattern;
+
+    const DT: f64 = 1.0 / 120.0;
+
+    /// The angle left between the turret's aim and a target straight up after `ticks` ticks
+    fn aim_error_after(aim_decay: f64, ticks: usize) -> f64 {
+        let mut turret = Turret {
+            target: point![0.0, 10.0],
+            ..Default::default()
+        };
+        turret.set_aim_decay(aim_decay);
+
+        let mut projectiles = HopSlotMap::with_key();
+        let mut rng = Rng::new(9);
+        for _ in 0..ticks {
+            turret.tick(&mut projectiles, &mut SpawnOrder::default(), &mut rng, DT);
+        }
+
+        (FRAC_PI_2 - turret.position.rotation.angle()).abs()
+    }
+
+    #[test]
+    fn higher_aim_decay_turns_faster_and_shooting_snaps() {
+        assert!(aim_error_after(40.0, 10) < aim_error_after(10.0, 10));
+        assert!(aim_error_after(10.0, 10) < aim_error_after(2.0, 10));
+        assert_eq!(aim_error_after(0.0, 10), FRAC_PI_2);
+
+        let mut turret = Turret {
+            target: point![0.0, 10.0],
+            time_since_shoot: f64::INFINITY,
+            ..Default::default()
+        };
+        turret.set_aim_decay(0.0);
+        turret.input.shoot = true;
+
+        let mut projectiles = HopSlotMap::with_key();
+        turret.tick(
+            &mut projectiles,
+            &mut SpawnOrder::default(),
+            &mut Rng::new(9),
+            DT,
+        );
+
+        assert_eq!(projectiles.len(), 1);
+        assert!((turret.position.rotation.angle() - FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn muzzle_sits_along_the_barrel_for_any_rotation() {
+        for angle in [0.0, 1.0, -2.5] {
+            let turret = Turret {
+                position: Isometry2::new(vector![3.0, -1.0], angle),
+                ..Default::default()
+            };
+            let facing = turret.position.rotation * vector![1.0, 0.0];
+            let distance =
+                Turret::BARREL_LENGTH + turret.projectile_kind.properties.distance_to_back();
+
+            let muzzle = turret.muzzle_transform();
+            let offset = muzzle.translation.vector - turret.position.translation.vector;
+
+            assert!((offset - facing * distance).norm() < 1e-12);
+            assert!((muzzle.rotation.angle() - angle).abs() < 1e-12);
+            assert!(
+                (turret.barrel_tip() - point![3.0, -1.0] - facing * Turret::BARREL_LENGTH).norm()
+                    < 1e-12
+            );
+        }
+    }
+
+    #[test]
+    fn spread_pellets_leave_from_across_the_barrel() {
+        let mut turret = Turret {
+            position: Isometry2::new(vector![1.0, 2.0], 0.7),
+            time_since_shoot: f64::INFINITY,
+            ..Default::default()
+        };
+        turret.projectile_kind.fire_pattern = FirePattern {
+            pellets: 5,
+            spread: 0.4,
+        };
+
+        let mut projectiles = HopSlotMap::with_key();
+        turret.shoot(
+            &mut projectiles,
+            &mut SpawnOrder::default(),
+            &mut Rng::new(12),
+        );
+        assert_eq!(projectiles.len(), 5);
+
+        let muzzle = turret.muzzle_transform();
+        let aim = turret.position.rotation * vector![1.0, 0.0];
+        let lateral = turret.position.rotation * vector![0.0, 1.0];
+
+        let mut offsets = (projectiles.values())
+            .map(|projectile| projectile.position.translation.vector - muzzle.translation.vector)
+            .inspect(|offset| assert!(offset.dot(&aim).abs() < 1e-12))
+            .map(|offset| offset.dot(&lateral))
+            .collect::<Vec<_>>();
+        offsets.sort_by(f64::total_cmp);
+
+        assert!((offsets[0] + 0.5 * Turret::BARREL_WIDTH).abs() < 1e-12);
+        assert!((offsets[4] - 0.5 * Turret::BARREL_WIDTH).abs() < 1e-12);
+        assert!(offsets.windows(2).all(|pair| pair[1] - pair[0] > 1e-3));
+
+        turret.projectile_kind.fire_pattern = FirePattern::SINGLE;
+        assert_eq!(turret.pellet_transform(0), muzzle);
+    }
+
+    #[test]
+    fn shoot_status_reports_the_cooldown() {
+        let mut turret = Turret {
+            time_since_shoot: f64::INFINITY,
+            ..Default::default()
+        };
+        assert_eq!(turret.shoot_status(), ShootStatus::Ready);
+        assert!(turret.can_shoot());
+
+        let mut projectiles = HopSlotMap::with_key();
+        turret.shoot(
+            &mut projectiles,
+            &mut SpawnOrder::default(),
+            &mut Rng::new(9),
+        );
+        assert_eq!(
+            turret.shoot_status(),
+            ShootStatus::CoolingDown { progress: 0.0 }
+        );
+        assert!(!turret.can_shoot());
+
+        let cooldown = turret.projectile_kind.shoot_cooldown;
+        turret.time_since_shoot = cooldown * 0.25;
+        assert_eq!(
+            turret.shoot_status(),
+            ShootStatus::CoolingDown { progress: 0.25 }
+        );
+
+        turret.time_since_shoot = cooldown;
+        assert_eq!(turret.shoot_status(), ShootStatus::Ready);
+    }
+
+    /// The barrel offset sampled every tick over one cooldown
+    fn recharge_offsets(turret: &Turret) -> Vec<f64> {
+        let cooldown = turret.projectile_kind.shoot_cooldown;
+
+        (0..(cooldown / DT) as usize)
+            .map(|tick| {
+                Turret {
+                    time_since_shoot: tick as f64 * DT,
+                    ..turret.clone()
+                }
+                .shoot_recharge_offset()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recharge_offsets_are_stepped_unless_made_smooth() {
+        let step = Turret::DEFAULT_RECHARGE_STEP;
+        let stepped = recharge_offsets(&Turret::default());
+        assert!(stepped.iter().all(|offset| {
+            let steps = offset / step;
+            (steps - steps.round()).abs() < 1e-9
+        }));
+        assert!(stepped.windows(2).any(|pair| pair[0] == pair[1]));
+
+        let smooth = recharge_offsets(&Turret {
+            recharge_easing: tween::linear,
+            recharge_step: None,
+            ..Default::default()
+        });
+        assert!(smooth.windows(2).all(|pair| pair[1] < pair[0]));
+        let total_drop = smooth[0] - smooth[smooth.len() - 1];
+        let largest_drop = (smooth.windows(2))
+            .map(|pair| pair[0] - pair[1])
+            .fold(0.0, f64::max);
+        assert!(largest_drop < total_drop / 10.0);
+    }
+
+    /// How many shots a turret with `kind` fires while the button is held for five seconds,
+    /// optionally after the input has been cancelled with the button already down
+    fn shots_while_holding(kind: &ProjectileKind, cancel: bool) -> usize {
+        let mut turret = Turret {
+            target: point![0.0, 10.0],
+            time_since_shoot: f64::INFINITY,
+            projectile_kind: kind.clone(),
+            ..Default::default()
+        };
+        let mut projectiles = HopSlotMap::with_key();
+        let mut rng = Rng::new(9);
+
+        turret.input.tick_with(true, DT);
+        if cancel {
+            turret.input.cancel();
+        }
+
+        for _ in 0..600 {
+            turret.input.tick_with(true, DT);
+            turret.tick(&mut projectiles, &mut SpawnOrder::default(), &mut rng, DT);
+        }
+
+        projectiles.len()
+    }
+
+    #[test]
+    fn tap_fire_weapons_shoot_once_per_press() {
+        let tap_fire = PROJECTILE_KINDS
+            .iter()
+            .find(|kind| !kind.auto_fire)
+            .unwrap();
+        let auto_fire = PROJECTILE_KINDS.iter().find(|kind| kind.auto_fire).unwrap();
+
+        assert_eq!(shots_while_holding(tap_fire, false), 1);
+        assert!(shots_while_holding(auto_fire, false) > 1);
+
+        // A click that closes a menu doesn't shoot, even if it's held
+        assert_eq!(shots_while_holding(tap_fire, true), 0);
+        assert_eq!(shots_while_holding(auto_fire, true), 0);
+    }
+
+    #[test]
+    fn aim_curve_ignores_the_dead_zone_and_follows_the_exponent() {
+        let settings = InputSettings {
+            dead_zone: 0.2,
+            exponent: 2.0,
+            sensitivity: 10.0,
+        };
+        let mut cursor = VirtualCursor {
+            position: point![1.0, 1.0],
+            settings,
+        };
+
+        cursor.tick(vector![0.1, 0.1], 1.0);
+        assert_eq!(cursor.position, point![1.0, 1.0]);
+
+        // Halfway between the dead zone and full deflection gives a quarter of the speed
+        let velocity = settings.aim_velocity(vector![0.0, -0.6]);
+        assert!((velocity - vector![0.0, -2.5]).norm() < 1e-9);
+
+        let full = settings.aim_velocity(vector![3.0, 0.0]);
+        assert!((full - vector![10.0, 0.0]).norm() < 1e-9);
+
+        let linear = InputSettings {
+            exponent: 1.0,
+            ..settings
+        };
+        assert!((linear.aim_velocity(vector![0.0, -0.6]) - vector![0.0, -5.0]).norm() < 1e-9);
+    }
+
+    #[test]
+    fn the_barrel_eases_back_over_the_remaining_cooldown() {
+        let cooldown = PROJECTILE_KINDS[0].shoot_cooldown;
+        let offset_after = |time_since_shoot| {
+            Turret {
+                time_since_shoot,
+                recharge_step: None,
+                ..Default::default()
+            }
+            .shoot_recharge_offset()
+        };
+
+        assert_eq!(offset_after(0.0), Turret::BARREL_SHOOT_OFFSET);
+        assert!((offset_after(cooldown / 2.0) - Turret::BARREL_SHOOT_OFFSET / 4.0).abs() < 1e-9);
+        assert_eq!(offset_after(cooldown), 0.0);
+        assert_eq!(offset_after(f64::INFINITY), 0.0);
+    }
+}
@@ -0,0 +1,188 @@
+//! Easing curves and smoothing shared by animations. The easing functions take a progress from
+//! `0.0` to `1.0` and return `0.0` at the start and `1.0` at the end.
+
+use macroquad::color::Color;
+use nalgebra::{Point2, Vector2};
+
+#[must_use]
+pub fn linear(t: f64) -> f64 {
+    t
+}
+
+/// Starts slowly and speeds up. Reaches `0.25` halfway.
+#[must_use]
+pub fn ease_in_quad(t: f64) -> f64 {
+    t * t
+}
+
+/// Starts quickly and slows down. Reaches `0.875` halfway.
+#[must_use]
+pub fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Stays at `0.0` for the first 60%, then rises steadily. Fading along it holds something fully
+/// visible for most of its life before it quickly disappears.
+#[must_use]
+pub fn hold_then_linear(t: f64) -> f64 {
+    ((t - 0.6) / 0.4).max(0.0)
+}
+
+/// Slow at both ends. Reaches `0.5` halfway.
+#[must_use]
+pub fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Moves `a` towards `b` by a portion that depends only on `decay` and `dt`, so the motion is the
+/// same at any frame rate.
+///
+/// CREDIT: Freya Holmér: <https://www.youtube.com/watch?v=LSNQuFEDOyQ>
+#[must_use]
+pub fn exp_decay(a: f64, b: f64, decay: f64, dt: f64) -> f64 {
+    b + (a - b) * (-decay * dt).exp()
+}
+
+/// A value that can be blended between two others
+pub trait Tweenable: Copy {
+    #[must_use]
+    fn tween(self, end: Self, t: f64) -> Self;
+}
+
+/// Animates a value from `start` to `end` over `duration` seconds, shaped by `easing`
+#[derive(Clone, Copy, Debug)]
+pub struct Tween<T> {
+    pub start: T,
+    pub end: T,
+    pub duration: f64,
+    pub elapsed: f64,
+    pub easing: fn(f64) -> f64,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(start: T, end: T, duration: f64, easing: fn(f64) -> f64) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            elapsed: 0.0,
+            easing,
+        }
+    }
+
+    pub fn tick(&mut self, dt: f64) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    /// How far through the duration the tween is, from `0.0` to `1.0`, before easing
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    #[must_use]
+    pub fn value(&self) -> T {
+        self.start.tween(self.end, (self.easing)(self.progress()))
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+impl Tweenable for f64 {
+    fn tween(self, end: Self, t: f64) -> Self {
+        self + (end - self) * t
+    }
+}
+
+impl Tweenable for f32 {
+    fn tween(self, end: Self, t: f64) -> Self {
+        self + (end - self) * t as f32
+    }
+}
+
+impl Tweenable for Vector2<f64> {
+    fn tween(self, end: Self, t: f64) -> Self {
+        self.lerp(&end, t)
+    }
+}
+
+impl Tweenable for Point2<f64> {
+    fn tween(self, end: Self, t: f64) -> Self {
+        self.coords.lerp(&end.coords, t).into()
+    }
+}
+
+impl Tweenable for Color {
+    fn tween(self, end: Self, t: f64) -> Self {
+        crate::utils::color_lerp(self, end, t as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        let easings: [fn(f64) -> f64; 5] = [
+            linear,
+            ease_in_quad,
+            ease_out_cubic,
+            hold_then_linear,
+            smoothstep,
+        ];
+
+        for easing in easings {
+            assert_eq!(easing(0.0), 0.0);
+            assert_eq!(easing(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn easings_reach_their_documented_midpoints() {
+        assert_eq!(linear(0.5), 0.5);
+        assert_eq!(ease_in_quad(0.5), 0.25);
+        assert_eq!(ease_out_cubic(0.5), 0.875);
+        assert_eq!(hold_then_linear(0.5), 0.0);
+        assert_eq!(hold_then_linear(0.6), 0.0);
+        assert_eq!(smoothstep(0.5), 0.5);
+    }
+
+    #[test]
+    fn exp_decay_approaches_the_target_independently_of_frame_rate() {
+        let mut one_step = 10.0;
+        let mut many_steps = 10.0;
+
+        one_step = exp_decay(one_step, 2.0, 3.0, 0.5);
+        for _ in 0..60 {
+            many_steps = exp_decay(many_steps, 2.0, 3.0, 0.5 / 60.0);
+        }
+
+        assert!((one_step - many_steps).abs() < 1e-9);
+        assert!(one_step > 2.0 && one_step < 10.0);
+    }
+
+    #[test]
+    fn tweens_ease_from_start_to_end_over_their_duration() {
+        let mut tween = Tween::new(2.0, 4.0, 2.0, ease_in_quad);
+        assert_eq!(tween.value(), 2.0);
+
+        tween.tick(1.0);
+        assert_eq!(tween.progress(), 0.5);
+        assert_eq!(tween.value(), 2.5);
+        assert!(!tween.is_finished());
+
+        tween.tick(5.0);
+        assert_eq!(tween.value(), 4.0);
+        assert!(tween.is_finished());
+
+        assert_eq!(Tween::new(1.0, 3.0, 0.0, linear).value(), 3.0);
+    }
+}
@@ -1,17 +1,21 @@
-use std::{ops::Deref, sync::LazyLock};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, LazyLock, OnceLock, RwLock},
+};
 
 use macroquad::{
     Error,
-    camera::Camera2D,
-    color::Color,
+    camera::{self, Camera, Camera2D},
+    color::{Color, colors},
     input,
     math::Vec2,
-    texture::{FilterMode, Image, Texture2D},
+    texture::{FilterMode, Image, RenderTarget, Texture2D},
     window,
 };
-use nalgebra::{Point2, Vector2, vector};
+use nalgebra::{Point2, Scalar, Vector2, vector};
 
-use crate::shape::Shape;
+use crate::{rng::Rng, shape::Shape};
 
 #[must_use]
 pub fn vec2_to_vector2_f64(vector: Vec2) -> Vector2<f64> {
@@ -33,22 +37,92 @@ pub fn point2_f64_to_vec2(point: Point2<f64>) -> Vec2 {
     <[f64; 2]>::from(point).map(|x| x as f32).into()
 }
 
+/// Where the mouse is in the world, see [`screen_to_world`]
 #[must_use]
 pub fn mouse_position(camera: &Camera2D) -> Point2<f64> {
-    vec2_to_point2_f64(camera.screen_to_world(input::mouse_position().into()))
+    screen_to_world(
+        camera,
+        input::mouse_position().into(),
+        Vec2::new(window::screen_width(), window::screen_height()),
+    )
+}
+
+/// Where `screen_position`, in pixels from the top left of a screen of `screen_size`, is in the
+/// world. This inverts the camera's whole transform, so it stays accurate when the view is
+/// rotated.
+#[must_use]
+pub fn screen_to_world(camera: &Camera2D, screen_position: Vec2, screen_size: Vec2) -> Point2<f64> {
+    let clip_position = Vec2::new(
+        screen_position.x / screen_size.x * 2.0 - 1.0,
+        1.0 - screen_position.y / screen_size.y * 2.0,
+    );
+
+    vec2_to_point2_f64(
+        camera
+            .matrix()
+            .inverse()
+            .transform_point3(clip_position.extend(0.0))
+            .truncate(),
+    )
 }
 
+/// Keeps world units square on screen. `zoom` applies after the camera's rotation, along the
+/// screen's axes, so this holds for any rotation.
 pub fn update_camera_aspect_ratio(camera: &mut Camera2D) {
     camera.zoom.x = camera.zoom.y.abs() * window::screen_height() / window::screen_width();
 }
 
+/// How many screen pixels one world unit covers vertically in `camera`'s view
+#[must_use]
+pub fn pixels_per_unit(camera: &Camera2D) -> f64 {
+    (camera.zoom.y.abs() * window::screen_height() / 2.0) as f64
+}
+
+/// An axis aligned rectangle containing everything `camera` shows, which is larger than the view
+/// itself when the view is rotated
 #[must_use]
 pub fn bounds_of_camera(camera: &Camera2D) -> Shape {
     Shape::Rectangle {
-        half_size: vec2_to_vector2_f64(1.0 / camera.zoom).abs(),
+        half_size: rotated_half_size(
+            vec2_to_vector2_f64(1.0 / camera.zoom).abs(),
+            (camera.rotation as f64).to_radians(),
+        ),
     }
 }
 
+/// The half size of the axis aligned rectangle containing a rectangle of `half_size` rotated by
+/// `angle` radians
+#[must_use]
+pub fn rotated_half_size(half_size: Vector2<f64>, angle: f64) -> Vector2<f64> {
+    let (sin, cos) = (angle.sin().abs(), angle.cos().abs());
+
+    vector![
+        cos * half_size.x + sin * half_size.y,
+        sin * half_size.x + cos * half_size.y,
+    ]
+}
+
+/// Runs `draw` with `camera` pointed at `target` after clearing it, then restores the previous
+/// camera
+pub fn draw_to_target(
+    target: &RenderTarget,
+    camera: &Camera2D,
+    background: Color,
+    draw: impl FnOnce(),
+) {
+    camera::push_camera_state();
+
+    camera::set_camera(&Camera2D {
+        render_target: Some(target.clone()),
+        ..*camera
+    });
+
+    window::clear_background(background);
+    draw();
+
+    camera::pop_camera_state();
+}
+
 pub fn darken_color(color: Color, brightness: f64) -> Color {
     Color {
         r: color.r * brightness as f32,
@@ -67,15 +141,19 @@ pub fn brighten_color(color: Color, brightness: f64) -> Color {
     }
 }
 
-pub fn next_flickering_brightness(current_brightnes: f64, minimum_brightness: f64) -> f64 {
+pub fn next_flickering_brightness(
+    current_brightnes: f64,
+    minimum_brightness: f64,
+    rng: &mut Rng,
+) -> f64 {
     if minimum_brightness == 1.0 {
         1.0
     } else if minimum_brightness > 0.5 {
-        macroquad::rand::gen_range(minimum_brightness, (minimum_brightness + 0.75).min(1.0))
-    } else if (current_brightnes < 0.5) ^ (macroquad::rand::rand() & 0b11 == 0) {
-        macroquad::rand::gen_range(0.5, (minimum_brightness + 0.75).min(1.0))
+        rng.gen_range(minimum_brightness, (minimum_brightness + 0.75).min(1.0))
+    } else if (current_brightnes < 0.5) ^ (rng.rand() & 0b11 == 0) {
+        rng.gen_range(0.5, (minimum_brightness + 0.75).min(1.0))
     } else {
-        macroquad::rand::gen_range(minimum_brightness, 0.5)
+        rng.gen_range(minimum_brightness, 0.5)
     }
 }
 
@@ -94,43 +172,199 @@ pub const fn color_lerp(a: Color, b: Color, t: f32) -> Color {
     }
 }
 
-/// CREDIT: Freya Holmér: <https://www.youtube.com/watch?v=LSNQuFEDOyQ>
+/// Tracks a smoothed frame time and flags frames that took far longer than expected
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameTimer {
+    pub smoothed_frame_time: Option<f64>,
+    pub last_frame_time: f64,
+}
+
+impl FrameTimer {
+    /// Frames longer than this are considered stalls
+    pub const STALL_THRESHOLD: f64 = 0.1;
+    /// The portion of the smoothed frame time kept from previous frames each frame
+    pub const SMOOTHING: f64 = 0.9;
+
+    pub fn record(&mut self, dt: f64) {
+        self.last_frame_time = dt;
+
+        // Stalls are left out of the average so a single hitch doesn't skew it
+        if self.is_stall() {
+            return;
+        }
+
+        self.smoothed_frame_time = Some(match self.smoothed_frame_time {
+            Some(smoothed) => smoothed * Self::SMOOTHING + dt * (1.0 - Self::SMOOTHING),
+            None => dt,
+        });
+    }
+
+    #[must_use]
+    pub fn smoothed(&self) -> f64 {
+        self.smoothed_frame_time.unwrap_or(self.last_frame_time)
+    }
+
+    #[must_use]
+    pub fn is_stall(&self) -> bool {
+        self.last_frame_time > Self::STALL_THRESHOLD
+    }
+
+    /// The last frame time, or the smoothed frame time if the last frame was a stall
+    #[must_use]
+    pub fn frame_time(&self) -> f64 {
+        if self.is_stall() {
+            self.smoothed().min(Self::STALL_THRESHOLD)
+        } else {
+            self.last_frame_time
+        }
+    }
+}
+
+/// Shrinks `image` by `factor` in each direction, keeping the top left pixel of each block
 #[must_use]
-pub fn exp_decay(a: f64, b: f64, decay: f64, dt: f64) -> f64 {
-    b + (a - b) * (-decay * dt).exp()
+pub fn downsample_image(image: &Image, factor: usize) -> Image {
+    let factor = factor.max(1);
+    let width = image.width().div_ceil(factor);
+    let height = image.height().div_ceil(factor);
+
+    let mut result = Image::gen_image_color(width as u16, height as u16, colors::BLANK);
+
+    for x in 0..width {
+        for y in 0..height {
+            result.get_image_data_mut()[x + y * width] =
+                image.get_image_data()[x * factor + y * factor * image.width()];
+        }
+    }
+
+    result
 }
 
-/// CREDIT: Freya Holmér: <https://www.youtube.com/watch?v=LSNQuFEDOyQ>
+/// Halves `image` in each direction, averaging each 2x2 block of pixels
 #[must_use]
-pub fn lerp_follow(a: f64, b: f64, t: f64, dt: f64) -> f64 {
-    b + (a - b) * t.powf(dt)
+pub fn halve_image(image: &Image) -> Image {
+    let width = image.width().div_ceil(2);
+    let height = image.height().div_ceil(2);
+
+    let mut result = Image::gen_image_color(width as u16, height as u16, colors::BLANK);
+
+    for x in 0..width {
+        for y in 0..height {
+            let pixels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| {
+                    (
+                        (x * 2 + dx).min(image.width() - 1),
+                        (y * 2 + dy).min(image.height() - 1),
+                    )
+                })
+                .map(|(x, y)| image.get_image_data()[x + y * image.width()]);
+
+            result.get_image_data_mut()[x + y * width] = std::array::from_fn(|i| {
+                (pixels.iter().map(|pixel| pixel[i] as u32).sum::<u32>() / 4) as u8
+            });
+        }
+    }
+
+    result
 }
 
+/// An image that is uploaded to the GPU the first time it's drawn, so entities holding one can be
+/// created and simulated without a window
 #[derive(Clone, Debug)]
 pub struct TextureEntry {
     pub image: Image,
-    pub texture: Texture2D,
+    texture: OnceLock<Texture2D>,
+    /// Progressively halved copies of the image, for drawing it smaller than its native size
+    /// without shimmering
+    pub mip_images: Vec<Image>,
+    mip_levels: OnceLock<Vec<Texture2D>>,
 }
 
 impl TextureEntry {
+    #[must_use]
+    pub fn from_image(image: Image) -> Self {
+        Self {
+            image,
+            texture: OnceLock::new(),
+            mip_images: Vec::new(),
+            mip_levels: OnceLock::new(),
+        }
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        let image = Image::from_file_with_format(bytes, None)?;
-        let texture = Texture2D::from_image(&image);
-        texture.set_filter(FilterMode::Nearest);
+        Ok(Self::from_image(Image::from_file_with_format(bytes, None)?))
+    }
+
+    /// The full size texture, uploading it if this is the first use
+    #[must_use]
+    pub fn texture(&self) -> &Texture2D {
+        self.texture.get_or_init(|| {
+            let texture = Texture2D::from_image(&self.image);
+            texture.set_filter(FilterMode::Nearest);
+            texture
+        })
+    }
+
+    fn mip_levels(&self) -> &[Texture2D] {
+        self.mip_levels.get_or_init(|| {
+            (self.mip_images.iter())
+                .map(|image| {
+                    let texture = Texture2D::from_image(image);
+                    texture.set_filter(FilterMode::Linear);
+                    texture
+                })
+                .collect()
+        })
+    }
+
+    /// Generates up to `count` mip levels, stopping early once the texture is a single pixel wide
+    /// or tall
+    #[must_use]
+    pub fn with_mip_levels(mut self, count: usize) -> Self {
+        let mut image = self.image.clone();
+
+        self.mip_images.clear();
+        self.mip_levels = OnceLock::new();
 
-        Ok(Self { image, texture })
+        while self.mip_images.len() < count && image.width() > 1 && image.height() > 1 {
+            image = halve_image(&image);
+            self.mip_images.push(image.clone());
+        }
+
+        self
     }
 
     pub fn pixel_size(&self) -> Vector2<usize> {
         vector![self.image.width(), self.image.height()]
     }
+
+    /// Which mip level to draw with when the texture covers `on_screen_width` pixels on screen,
+    /// where 0 is the texture itself. Non-positive widths always use the texture itself.
+    #[must_use]
+    pub fn mip_level(&self, on_screen_width: f64) -> usize {
+        if on_screen_width <= 0.0 {
+            return 0;
+        }
+
+        let ratio = self.image.width() as f64 / on_screen_width;
+
+        (ratio.log2().floor().max(0.0) as usize).min(self.mip_images.len())
+    }
+
+    /// The texture or mip level best suited to covering `on_screen_width` pixels on screen
+    #[must_use]
+    pub fn texture_for_width(&self, on_screen_width: f64) -> &Texture2D {
+        match self.mip_level(on_screen_width) {
+            0 => self.texture(),
+            level => &self.mip_levels()[level - 1],
+        }
+    }
 }
 
 impl Deref for TextureEntry {
     type Target = Texture2D;
 
     fn deref(&self) -> &Self::Target {
-        &self.texture
+        self.texture()
     }
 }
 
@@ -138,6 +372,9 @@ pub static TURRET_BASE_TEXTURE: LazyLock<TextureEntry> = LazyLock::new(|| {
     TextureEntry::from_bytes(include_bytes!("../assets/turret/base.png")).unwrap()
 });
 
+/// How many mip levels are generated for enemy textures
+pub const ENEMY_MIP_LEVELS: usize = 3;
+
 pub static ENEMY_TEXTURES: LazyLock<[TextureEntry; 5]> = LazyLock::new(|| {
     [
         TextureEntry::from_bytes(include_bytes!("../assets/enemies/red_circle.png")).unwrap(),
@@ -146,34 +383,215 @@ pub static ENEMY_TEXTURES: LazyLock<[TextureEntry; 5]> = LazyLock::new(|| {
         TextureEntry::from_bytes(include_bytes!("../assets/enemies/red_square.png")).unwrap(),
         TextureEntry::from_bytes(include_bytes!("../assets/enemies/purple_square.png")).unwrap(),
     ]
+    .map(|entry| entry.with_mip_levels(ENEMY_MIP_LEVELS))
 });
 
-pub static GLITTER_TEXTURES: LazyLock<[TextureEntry; 2]> = LazyLock::new(|| {
+pub static GLITTER_TEXTURES: LazyLock<[Arc<TextureEntry>; 2]> = LazyLock::new(|| {
     [
         TextureEntry::from_bytes(include_bytes!("../assets/particles/glitter_1.png")).unwrap(),
         TextureEntry::from_bytes(include_bytes!("../assets/particles/glitter_2.png")).unwrap(),
     ]
+    .map(Arc::new)
 });
 
 pub static ABSORB_TEXTURE: LazyLock<TextureEntry> = LazyLock::new(|| {
     TextureEntry::from_bytes(include_bytes!("../assets/particles/absorb.png")).unwrap()
 });
 
+/// Refers to a texture in the [`TEXTURE_REGISTRY`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub u32);
+
+impl TextureId {
+    pub const RED_CIRCLE: Self = Self(0);
+    pub const PURPLE_CIRCLE: Self = Self(1);
+    pub const ELECTRIC_CIRCLE: Self = Self(2);
+    pub const RED_SQUARE: Self = Self(3);
+    pub const PURPLE_SQUARE: Self = Self(4);
+    pub const GLITTER_1: Self = Self(5);
+    pub const GLITTER_2: Self = Self(6);
+    pub const TURRET_BASE: Self = Self(7);
+    pub const ABSORB: Self = Self(8);
+}
+
+/// Maps names and ids to textures, so content can refer to textures that are loaded at runtime
+#[derive(Debug, Default)]
+pub struct TextureRegistry {
+    pub entries: Vec<Arc<TextureEntry>>,
+    pub ids: HashMap<String, TextureId>,
+}
+
+impl TextureRegistry {
+    /// A registry holding the built in textures under the ids defined on [`TextureId`]
+    #[must_use]
+    pub fn with_built_in_textures() -> Self {
+        let mut registry = Self::default();
+
+        for (name, entry) in [
+            ("enemies/red_circle", &ENEMY_TEXTURES[0]),
+            ("enemies/purple_circle", &ENEMY_TEXTURES[1]),
+            ("enemies/electric_circle", &ENEMY_TEXTURES[2]),
+            ("enemies/red_square", &ENEMY_TEXTURES[3]),
+            ("enemies/purple_square", &ENEMY_TEXTURES[4]),
+            ("particles/glitter_1", &*GLITTER_TEXTURES[0]),
+            ("particles/glitter_2", &*GLITTER_TEXTURES[1]),
+            ("turret/base", &*TURRET_BASE_TEXTURE),
+            ("particles/absorb", &*ABSORB_TEXTURE),
+        ] {
+            registry.register(name, entry.clone());
+        }
+
+        registry
+    }
+
+    /// Adds a texture under `name`, replacing any texture that already has that name
+    pub fn register(&mut self, name: &str, entry: TextureEntry) -> TextureId {
+        if let Some(&id) = self.ids.get(name) {
+            self.entries[id.0 as usize] = Arc::new(entry);
+            return id;
+        }
+
+        let id = TextureId(self.entries.len() as u32);
+        self.entries.push(Arc::new(entry));
+        self.ids.insert(name.to_owned(), id);
+
+        id
+    }
+
+    #[must_use]
+    pub fn get(&self, id: TextureId) -> Option<Arc<TextureEntry>> {
+        self.entries.get(id.0 as usize).cloned()
+    }
+
+    #[must_use]
+    pub fn id_of(&self, name: &str) -> Option<TextureId> {
+        self.ids.get(name).copied()
+    }
+}
+
+pub static TEXTURE_REGISTRY: LazyLock<RwLock<TextureRegistry>> =
+    LazyLock::new(|| RwLock::new(TextureRegistry::with_built_in_textures()));
+
+/// Looks up `id` in the [`TEXTURE_REGISTRY`]
+#[must_use]
+pub fn texture(id: TextureId) -> Option<Arc<TextureEntry>> {
+    TEXTURE_REGISTRY.read().unwrap().get(id)
+}
+
+/// Numbers entities by when they were added to a game, for ordering them independently of how
+/// their keys were reused
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpawnOrder {
+    /// The index [`SpawnOrder::allocate`] hands out next
+    pub next_index: u64,
+}
+
+impl SpawnOrder {
+    /// A spawn index larger than any handed out before
+    pub fn allocate(&mut self) -> u64 {
+        let index = self.next_index;
+        self.next_index += 1;
+
+        index
+    }
+}
+
+/// Holds the latest `N` values pushed into it, overwriting the oldest once full, without
+/// allocating
 #[derive(Clone, Copy, Debug)]
-pub struct BoundingBox {
-    pub min: Point2<usize>,
-    pub max: Point2<usize>,
+pub struct RingBuffer<T, const N: usize> {
+    items: [Option<T>; N],
+    /// Where the next value will be written
+    next: usize,
 }
 
-impl BoundingBox {
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    pub fn new() -> Self {
+        Self {
+            items: std::array::from_fn(|_| None),
+            next: 0,
+        }
+    }
+
+    /// Adds `value` as the newest item, returning the oldest item if it had to be removed to make
+    /// room
+    pub fn push(&mut self, value: T) -> Option<T> {
+        if N == 0 {
+            return Some(value);
+        }
+
+        let removed = self.items[self.next].replace(value);
+        self.next = (self.next + 1) % N;
+
+        removed
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.items.iter().filter(|item| item.is_some()).count()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.newest().is_none()
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[must_use]
+    pub fn newest(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Iterates from the newest item to the oldest
+    pub fn iter(&self) -> impl Iterator<Item = &T> + Clone {
+        (1..=N)
+            .map(move |offset| &self.items[(self.next + N - offset) % N])
+            .map_while(Option::as_ref)
+    }
+}
+
+/// An axis aligned box, in pixels by default or in world units with `f64`
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingBox<T: Scalar = usize> {
+    pub min: Point2<T>,
+    pub max: Point2<T>,
+}
+
+impl<T: Scalar + PartialOrd> BoundingBox<T> {
     #[must_use]
-    pub fn intersects(&self, other: &BoundingBox) -> bool {
+    pub fn intersects(&self, other: &BoundingBox<T>) -> bool {
         self.min.x <= other.max.x
             && self.min.y <= other.max.y
             && other.min.x <= self.max.x
             && other.min.y <= self.max.y
     }
+}
 
+impl BoundingBox<f64> {
+    #[must_use]
+    pub fn around(center: Point2<f64>, half_size: Vector2<f64>) -> Self {
+        Self {
+            min: center - half_size,
+            max: center + half_size,
+        }
+    }
+}
+
+impl BoundingBox {
     #[must_use]
     pub fn center(&self) -> Point2<f64> {
         (self.min.map(|x| x as f64) + self.max.map(|x| (x + 1) as f64).coords) / 2.0
@@ -200,3 +618,163 @@ impl BoundingBox {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::point;
+
+    use super::*;
+
+    fn blank_texture(width: u16, height: u16) -> TextureEntry {
+        TextureEntry::from_image(Image::gen_image_color(width, height, colors::WHITE))
+    }
+
+    #[test]
+    fn built_in_textures_resolve_by_id_and_name() {
+        let registry = TextureRegistry::with_built_in_textures();
+
+        for (name, id) in [
+            ("enemies/red_circle", TextureId::RED_CIRCLE),
+            ("enemies/purple_square", TextureId::PURPLE_SQUARE),
+            ("particles/glitter_2", TextureId::GLITTER_2),
+            ("turret/base", TextureId::TURRET_BASE),
+            ("particles/absorb", TextureId::ABSORB),
+        ] {
+            assert_eq!(registry.id_of(name), Some(id));
+            assert!(registry.get(id).is_some());
+        }
+
+        assert_eq!(
+            registry.get(TextureId::RED_SQUARE).unwrap().pixel_size(),
+            ENEMY_TEXTURES[3].pixel_size(),
+        );
+    }
+
+    #[test]
+    fn registering_adds_or_replaces_textures() {
+        let mut registry = TextureRegistry::with_built_in_textures();
+        let built_in_count = registry.entries.len();
+
+        let id = registry.register("mods/big", blank_texture(3, 2));
+        assert_eq!(id, TextureId(built_in_count as u32));
+        assert_eq!(registry.id_of("mods/big"), Some(id));
+        assert_eq!(registry.get(id).unwrap().pixel_size(), vector![3, 2]);
+
+        assert_eq!(registry.register("mods/big", blank_texture(5, 5)), id);
+        assert_eq!(registry.get(id).unwrap().pixel_size(), vector![5, 5]);
+        assert_eq!(registry.entries.len(), built_in_count + 1);
+    }
+
+    #[test]
+    fn missing_textures_resolve_to_none() {
+        let registry = TextureRegistry::with_built_in_textures();
+
+        assert!(registry.get(TextureId(u32::MAX)).is_none());
+        assert_eq!(registry.id_of("enemies/missing"), None);
+    }
+
+    #[test]
+    fn ring_buffer_overwrites_the_oldest_items() {
+        let mut buffer = RingBuffer::<usize, 3>::new();
+
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.push(0), None);
+        assert_eq!(buffer.push(1), None);
+        assert_eq!(buffer.push(2), None);
+        assert_eq!(buffer.len(), 3);
+
+        for value in 3..10 {
+            assert_eq!(buffer.push(value), Some(value - 3));
+            assert_eq!(buffer.len(), buffer.capacity());
+        }
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.newest(), None);
+    }
+
+    #[test]
+    fn ring_buffer_iterates_from_newest_to_oldest() {
+        let mut buffer = RingBuffer::<usize, 4>::new();
+
+        buffer.push(0);
+        buffer.push(1);
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [1, 0]);
+
+        for value in 2..7 {
+            buffer.push(value);
+        }
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [6, 5, 4, 3]);
+        assert_eq!(buffer.newest(), Some(&6));
+    }
+
+    #[test]
+    fn frame_timer_flags_stalls_and_lags_behind_changes() {
+        let steady = 1.0 / 60.0;
+        let mut timer = FrameTimer::default();
+
+        for _ in 0..100 {
+            timer.record(steady);
+        }
+        assert!(!timer.is_stall());
+        assert!((timer.smoothed() - steady).abs() < 1e-12);
+
+        timer.record(0.5);
+        assert!(timer.is_stall());
+        assert!((timer.smoothed() - steady).abs() < 1e-12);
+        assert_eq!(timer.frame_time(), timer.smoothed());
+
+        timer.record(0.05);
+        assert!(!timer.is_stall());
+        assert_eq!(timer.frame_time(), 0.05);
+        let expected = steady * FrameTimer::SMOOTHING + 0.05 * (1.0 - FrameTimer::SMOOTHING);
+        assert!((timer.smoothed() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mip_level_is_picked_from_the_on_screen_size() {
+        let entry = TextureEntry::from_image(Image::gen_image_color(64, 32, colors::WHITE))
+            .with_mip_levels(3);
+
+        let sizes: Vec<_> = (entry.mip_images.iter())
+            .map(|image| (image.width(), image.height()))
+            .collect();
+        assert_eq!(sizes, [(32, 16), (16, 8), (8, 4)]);
+
+        assert_eq!(entry.mip_level(128.0), 0);
+        assert_eq!(entry.mip_level(64.0), 0);
+        assert_eq!(entry.mip_level(40.0), 0);
+        assert_eq!(entry.mip_level(32.0), 1);
+        assert_eq!(entry.mip_level(20.0), 1);
+        assert_eq!(entry.mip_level(16.0), 2);
+        assert_eq!(entry.mip_level(8.0), 3);
+        assert_eq!(entry.mip_level(1.0), 3);
+        assert_eq!(entry.mip_level(0.0), 0);
+    }
+
+    #[test]
+    fn screen_positions_follow_a_rotated_view() {
+        // Ten world units per 300 pixels both ways, with up on screen being up in the world
+        let mut camera = Camera2D {
+            target: Vec2::new(5.0, -2.0),
+            zoom: Vec2::new(0.075, -0.1),
+            ..Default::default()
+        };
+        let offset_at = |camera: &Camera2D, x: f32, y: f32| {
+            screen_to_world(camera, Vec2::new(x, y), Vec2::new(800.0, 600.0)) - point![5.0, -2.0]
+        };
+        let assert_near = |offset: Vector2<f64>, expected: Vector2<f64>| {
+            assert!((offset - expected).norm() < 1e-4, "{offset} != {expected}");
+        };
+
+        assert_near(offset_at(&camera, 400.0, 300.0), vector![0.0, 0.0]);
+        assert_near(offset_at(&camera, 700.0, 300.0), vector![10.0, 0.0]);
+        assert_near(offset_at(&camera, 400.0, 0.0), vector![0.0, 10.0]);
+
+        // Turning the view by a quarter turn turns what's under each pixel with it
+        camera.rotation = 90.0;
+        assert_near(offset_at(&camera, 400.0, 300.0), vector![0.0, 0.0]);
+        assert_near(offset_at(&camera, 700.0, 300.0), vector![0.0, -10.0]);
+        assert_near(offset_at(&camera, 400.0, 0.0), vector![10.0, 0.0]);
+    }
+}
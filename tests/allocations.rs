@@ -0,0 +1,52 @@
+//! Checks that code meant for every frame doesn't allocate. This has its own test binary, since
+//! counting allocations needs a global allocator that would see every other test's allocations.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use electro_shoot::utils::RingBuffer;
+use nalgebra::Isometry2;
+
+/// Counts the allocations made on each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn ring_buffer_does_not_allocate() {
+    let before = allocations();
+
+    let mut buffer = RingBuffer::<Isometry2<f64>, 8>::new();
+    for step in 0..100 {
+        buffer.push(Isometry2::translation(step as f64, 0.0));
+    }
+    let newest = buffer
+        .iter()
+        .fold(0.0, |max: f64, position| max.max(position.translation.x));
+    buffer.clear();
+
+    assert_eq!(newest, 99.0);
+    assert_eq!(allocations(), before);
+}